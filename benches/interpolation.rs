@@ -0,0 +1,71 @@
+//! Times evaluating the comoving distance of a large catalogue directly and from a spline table.
+//! Run with `cargo bench`.
+
+use std::time::Instant;
+
+use cosmoxide::Cosmology;
+use rand::{Rng, SeedableRng};
+use rayon::prelude::*;
+
+use cosmo::interpolation::Table;
+use cosmo::precise::Precise;
+
+const ROWS: usize = 1_000_000;
+
+fn main() {
+    let cosmo = Cosmology {
+        omega_m: 0.3,
+        omega_k: 0.,
+        omega_l: 0.7,
+        h0: 70.,
+    };
+    let mut rng = rand::rngs::StdRng::seed_from_u64(1);
+    let redshifts: Vec<f64> = (0..ROWS).map(|_| rng.random_range(0.01..3.)).collect();
+
+    let start = Instant::now();
+    let direct: Vec<f64> = redshifts
+        .par_iter()
+        .map(|z| cosmo.comoving_distance(*z))
+        .collect();
+    let direct_time = start.elapsed();
+
+    // The tables are built from, and checked against, the adaptive quadrature backend, which
+    // unlike the direct evaluation is accurate to well below every tolerance.
+    let precise = Precise::new(&cosmo, 1e-13);
+    let exact: Vec<f64> = redshifts
+        .par_iter()
+        .map(|z| precise.comoving_distance(*z).value)
+        .collect();
+    let largest_error = |values: &[f64]| {
+        exact
+            .iter()
+            .zip(values)
+            .map(|(exact, value)| (value - exact).abs() / exact)
+            .fold(0., f64::max)
+    };
+    println!(
+        "direct: {:.3?}, largest relative error {:.1e}",
+        direct_time,
+        largest_error(&direct)
+    );
+
+    for rtol in [1e-6, 1e-8, 1e-10] {
+        let start = Instant::now();
+        let table = Table::new(|z| precise.comoving_distance(z).value, 0.01, 3., rtol).unwrap();
+        let interpolated: Vec<f64> = redshifts
+            .par_iter()
+            .map(|z| table.get(*z).unwrap())
+            .collect();
+        let time = start.elapsed();
+        let worst = largest_error(&interpolated);
+        println!(
+            "rtol {:e}: {} points, {:.3?} against {:.3?} direct ({:.0}x), largest relative error {:.1e}",
+            rtol,
+            table.points(),
+            time,
+            direct_time,
+            direct_time.as_secs_f64() / time.as_secs_f64(),
+            worst
+        );
+    }
+}
@@ -0,0 +1,257 @@
+use cosmoxide::Cosmology;
+
+use crate::numerics::{brent, simpson};
+
+/// Speed of light in km/s.
+pub const SPEED_OF_LIGHT: f64 = 299792.458;
+
+const INTEGRATION_STEPS: usize = 2000;
+
+/// Largest redshift searched by the cosmoxide inverses, which return 0 beyond it.
+pub const MAX_INVERSE_REDSHIFT: f64 = 1200.;
+
+/// Dimensionless Hubble parameter E(z) = H(z)/H0.
+pub fn efunc(cosmo: &Cosmology, z: f64) -> f64 {
+    let zp1 = 1. + z;
+    (cosmo.omega_m * zp1.powi(3) + cosmo.omega_k * zp1.powi(2) + cosmo.omega_l).sqrt()
+}
+
+/// Hubble distance c/H0 in Mpc.
+pub fn hubble_distance(cosmo: &Cosmology) -> f64 {
+    SPEED_OF_LIGHT / cosmo.h0
+}
+
+/// Hubble radius c/H(z) in Mpc.
+pub fn hubble_radius(cosmo: &Cosmology, z: f64) -> f64 {
+    SPEED_OF_LIGHT / cosmo.h_at_z(z)
+}
+
+/// Comoving particle horizon in Mpc: the distance light could have travelled since the big bang.
+pub fn particle_horizon(cosmo: &Cosmology, z: f64) -> f64 {
+    // Substituting a = u² removes the a^(-1/2) singularity at the big bang.
+    let u_max = (1. / (1. + z)).sqrt();
+    let integrand = |u: f64| {
+        2. / (cosmo.omega_m + cosmo.omega_k * u.powi(2) + cosmo.omega_l * u.powi(6)).sqrt()
+    };
+    hubble_distance(cosmo) * simpson(integrand, 0., u_max, INTEGRATION_STEPS)
+}
+
+/// Comoving event horizon in Mpc: the largest comoving distance light emitted at z will ever
+/// travel. Infinite if there is no dark energy.
+pub fn event_horizon(cosmo: &Cosmology, z: f64) -> f64 {
+    if cosmo.omega_l <= 0. {
+        return f64::INFINITY;
+    }
+    // The remaining future of the universe is z in (-1, 0].
+    let future = simpson(|x| 1. / efunc(cosmo, x), -1., 0., INTEGRATION_STEPS);
+    hubble_distance(cosmo) * future + cosmo.comoving_distance(z)
+}
+
+/// Redshift at a luminosity distance in Mpc, which cosmoxide has no inverse for. NaN if the
+/// distance isn't reached by MAX_INVERSE_REDSHIFT.
+pub fn inverse_lumdist(cosmo: &Cosmology, distance: f64) -> f64 {
+    brent(
+        |z| cosmo.luminosity_distance(z) - distance,
+        0.,
+        MAX_INVERSE_REDSHIFT,
+        |z| 1e-12 * z.max(1.),
+    )
+    .map_or(f64::NAN, |root| root.value)
+}
+
+/// Redshift at which the matter and dark energy densities are equal.
+pub fn matter_lambda_equality(cosmo: &Cosmology) -> f64 {
+    (cosmo.omega_l / cosmo.omega_m).cbrt() - 1.
+}
+
+/// Redshift at which the expansion starts accelerating (deceleration parameter q = 0).
+pub fn acceleration_onset(cosmo: &Cosmology) -> f64 {
+    (2. * cosmo.omega_l / cosmo.omega_m).cbrt() - 1.
+}
+
+/// The quantity a redshift argument is given as, chosen with --from.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Input {
+    Redshift,
+    ScaleFactor,
+    /// Age of the universe in Gyr.
+    Age,
+    /// Look back time in Gyr.
+    LookbackTime,
+    /// Comoving distance in Mpc.
+    ComovingDistance,
+}
+
+impl Input {
+    pub fn from_name(name: &str) -> Option<Input> {
+        match name {
+            "z" => Some(Input::Redshift),
+            "a" => Some(Input::ScaleFactor),
+            "t" => Some(Input::Age),
+            "lookback" => Some(Input::LookbackTime),
+            "dc" => Some(Input::ComovingDistance),
+            _ => None,
+        }
+    }
+
+    /// Checks that the quantity takes the given value at some redshift the inverses can reach,
+    /// up to MAX_INVERSE_REDSHIFT.
+    pub fn check(&self, cosmo: &Cosmology, value: f64) -> Result<(), String> {
+        let z_max = MAX_INVERSE_REDSHIFT;
+        match self {
+            Input::Redshift => Ok(()),
+            Input::ScaleFactor => match value > 0. && value <= 1. {
+                true => Ok(()),
+                false => Err("The scale factor must be in (0, 1].".to_string()),
+            },
+            Input::Age => match (cosmo.age(z_max), cosmo.age(0.)) {
+                (first, now) if (first..=now).contains(&value) => Ok(()),
+                (first, now) => Err(format!(
+                    "The age must be in [{:.4e}, {:.4}] Gyr, up to z = {}.",
+                    first, now, z_max
+                )),
+            },
+            Input::LookbackTime => match cosmo.look_back_time(z_max) {
+                last if (0. ..=last).contains(&value) => Ok(()),
+                last => Err(format!(
+                    "The look back time must be in [0, {:.4}] Gyr, up to z = {}.",
+                    last, z_max
+                )),
+            },
+            Input::ComovingDistance => match cosmo.comoving_distance(z_max) {
+                last if (0. ..=last).contains(&value) => Ok(()),
+                last => Err(format!(
+                    "The comoving distance must be in [0, {:.4}] Mpc, up to z = {}.",
+                    last, z_max
+                )),
+            },
+        }
+    }
+
+    /// Redshift at which the quantity takes the given value, or why it never does.
+    pub fn redshift(&self, cosmo: &Cosmology, value: f64) -> Result<f64, String> {
+        self.check(cosmo, value)?;
+        Ok(match self {
+            Input::Redshift => value,
+            Input::ScaleFactor => 1. / value - 1.,
+            Input::Age => cosmo.inverse_age(value),
+            Input::LookbackTime => cosmo.inverse_lookback_time(value),
+            Input::ComovingDistance => cosmo.inverse_codist(value),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const EINSTEIN_DE_SITTER: Cosmology = Cosmology {
+        omega_m: 1.,
+        omega_k: 0.,
+        omega_l: 0.,
+        h0: 70.,
+    };
+
+    const DE_SITTER: Cosmology = Cosmology {
+        omega_m: 0.,
+        omega_k: 0.,
+        omega_l: 1.,
+        h0: 70.,
+    };
+
+    #[test]
+    fn particle_horizon_of_einstein_de_sitter() {
+        // 2c/H0 / sqrt(1+z).
+        for z in [0_f64, 1., 10., 1000.] {
+            let expected = 2. * hubble_distance(&EINSTEIN_DE_SITTER) / (1. + z).sqrt();
+            let horizon = particle_horizon(&EINSTEIN_DE_SITTER, z);
+            assert!(
+                (horizon - expected).abs() < 1e-8 * expected,
+                "{} against {} at z = {}",
+                horizon,
+                expected,
+                z
+            );
+        }
+    }
+
+    #[test]
+    fn event_horizon_of_de_sitter() {
+        // Light emitted today only ever reaches the Hubble distance.
+        let horizon = event_horizon(&DE_SITTER, 0.);
+        assert!((horizon - hubble_distance(&DE_SITTER)).abs() < 1e-8 * horizon);
+        assert_eq!(event_horizon(&EINSTEIN_DE_SITTER, 0.), f64::INFINITY);
+    }
+
+    #[test]
+    fn planck18_particle_horizon() {
+        let cosmo = Cosmology {
+            omega_m: 0.30966,
+            omega_k: 0.,
+            omega_l: 0.69034,
+            h0: 67.66,
+        };
+        // The observable universe of 46.5 billion light years, which radiation shortens by about
+        // 1.5 per cent.
+        let particle = particle_horizon(&cosmo, 0.);
+        assert!((particle / 14260. - 1.).abs() < 0.02, "{}", particle);
+    }
+
+    #[test]
+    fn output_times_outside_the_history_are_rejected() {
+        let cosmo = Cosmology {
+            omega_m: 0.3,
+            omega_k: 0.,
+            omega_l: 0.7,
+            h0: 70.,
+        };
+        let now = cosmo.age(0.);
+        for age in [0.5, 5., now] {
+            assert_eq!(Input::Age.check(&cosmo, age), Ok(()));
+        }
+        for age in [-1., 0., now + 0.1] {
+            assert!(Input::Age.check(&cosmo, age).is_err(), "age {}", age);
+        }
+        for lookback in [0., 5., 13.] {
+            assert_eq!(Input::LookbackTime.check(&cosmo, lookback), Ok(()));
+        }
+        for lookback in [-0.1, now] {
+            assert!(
+                Input::LookbackTime.check(&cosmo, lookback).is_err(),
+                "look back time {}",
+                lookback
+            );
+        }
+    }
+
+    #[test]
+    fn inputs_convert_to_redshift() {
+        let cosmo = Cosmology {
+            omega_m: 0.3,
+            omega_k: 0.,
+            omega_l: 0.7,
+            h0: 70.,
+        };
+        let z = 2.;
+        for (name, value) in [
+            ("z", z),
+            ("a", 1. / (1. + z)),
+            ("t", cosmo.age(z)),
+            ("lookback", cosmo.look_back_time(z)),
+            ("dc", cosmo.comoving_distance(z)),
+        ] {
+            let input = Input::from_name(name).unwrap();
+            let redshift = input.redshift(&cosmo, value).unwrap();
+            assert!(
+                (redshift - z).abs() < 1e-6,
+                "{} gives z = {}",
+                name,
+                redshift
+            );
+        }
+        assert_eq!(Input::from_name("redshift"), None);
+        for a in [0., 1.5] {
+            assert!(Input::ScaleFactor.redshift(&cosmo, a).is_err());
+        }
+    }
+}
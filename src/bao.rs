@@ -0,0 +1,54 @@
+use cosmoxide::Cosmology;
+
+use crate::background::hubble_radius;
+
+/// Effective redshifts of the standard BAO survey samples.
+pub fn survey_redshifts(survey: &str) -> Option<&'static [f64]> {
+    match survey {
+        "6df" => Some(&[0.106]),
+        "boss" => Some(&[0.38, 0.51, 0.61]),
+        "eboss" => Some(&[0.15, 0.38, 0.51, 0.70, 0.85, 1.48, 2.33]),
+        "desi" => Some(&[0.295, 0.510, 0.706, 0.930, 1.317, 1.491, 2.330]),
+        _ => None,
+    }
+}
+
+/// Volume averaged distance D_V = [z D_M² D_H]^(1/3) in Mpc.
+pub fn volume_averaged_distance(cosmo: &Cosmology, z: f64) -> f64 {
+    let d_m = cosmo.comoving_transverse_distance(z);
+    (z * d_m * d_m * hubble_radius(cosmo, z)).cbrt()
+}
+
+/// Alcock-Paczynski parameter F_AP = D_M / D_H.
+pub fn alcock_paczynski(cosmo: &Cosmology, z: f64) -> f64 {
+    cosmo.comoving_transverse_distance(z) / hubble_radius(cosmo, z)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::background::hubble_distance;
+
+    const COSMO: Cosmology = Cosmology {
+        omega_m: 0.3,
+        omega_k: 0.,
+        omega_l: 0.7,
+        h0: 70.,
+    };
+
+    #[test]
+    fn low_redshift_limits() {
+        // D_M and D_H both tend to cz/H0 and c/H0.
+        let z = 1e-3;
+        let d_v = volume_averaged_distance(&COSMO, z);
+        assert!((d_v / (z * hubble_distance(&COSMO)) - 1.).abs() < 1e-3);
+        assert!((alcock_paczynski(&COSMO, z) / z - 1.).abs() < 1e-3);
+    }
+
+    #[test]
+    fn survey_effective_redshifts() {
+        assert_eq!(survey_redshifts("boss"), Some(&[0.38, 0.51, 0.61][..]));
+        assert_eq!(survey_redshifts("desi").map(<[f64]>::len), Some(7));
+        assert_eq!(survey_redshifts("sdss"), None);
+    }
+}
@@ -0,0 +1,1008 @@
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{BufWriter, Seek, SeekFrom};
+use std::path::Path;
+use std::sync::Arc;
+
+use arrow::array::{
+    Array, ArrayRef, Float64Array, Int64Array, RecordBatch, RecordBatchIterator, RecordBatchReader,
+    StringArray,
+};
+use arrow::compute::cast;
+use arrow::datatypes::{DataType, Field, Schema, SchemaRef};
+use cosmoxide::Cosmology;
+use parquet::arrow::ArrowWriter;
+use parquet::arrow::arrow_reader::ParquetRecordBatchReaderBuilder;
+use rayon::prelude::*;
+
+use crate::background;
+use crate::coords;
+use crate::fits;
+use crate::interpolation::Table;
+use crate::pairs::{Object, Pair};
+use crate::precise::Precise;
+use crate::quantity::{self, Quantity};
+use crate::sky::{self, Frame};
+use crate::votable;
+
+/// Rows read, evaluated and written at a time for tabular formats.
+const BATCH_ROWS: usize = 65536;
+
+/// Rows used to infer the column types of a CSV file.
+const CSV_INFERENCE_ROWS: usize = 1000;
+
+/// Smallest relative tolerance quantities are computed to when interpolating, near the limit of
+/// the quadrature in double precision.
+const MIN_RTOL: f64 = 1e-13;
+
+/// Catalogue file formats, recognised by extension.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+    Fits,
+    Csv,
+    Parquet,
+    Arrow,
+    VOTable,
+}
+
+impl Format {
+    pub fn from_path(path: &Path) -> Option<Format> {
+        let extension = path.extension()?.to_str()?.to_ascii_lowercase();
+        match extension.as_str() {
+            "fits" | "fit" | "fts" => Some(Format::Fits),
+            "csv" => Some(Format::Csv),
+            "parquet" | "pq" => Some(Format::Parquet),
+            "arrow" | "ipc" | "feather" => Some(Format::Arrow),
+            "vot" | "votable" | "xml" => Some(Format::VOTable),
+            _ => None,
+        }
+    }
+}
+
+/// A quantity to add to a catalogue under the name it was asked for.
+pub struct NewColumn {
+    pub name: String,
+    pub quantity: &'static Quantity,
+}
+
+/// Comoving cartesian coordinates to add to a catalogue, from its RA and Dec columns.
+pub struct Positions {
+    pub ra_column: String,
+    pub dec_column: String,
+    pub frame: Frame,
+}
+
+/// Sky positions and redshifts to add to a catalogue, from its comoving cartesian coordinates.
+pub struct SkyPositions {
+    /// X, Y and Z columns in Mpc.
+    pub columns: [String; 3],
+    pub frame: Frame,
+}
+
+/// Comoving separations to add to a catalogue of pairs, from the sky positions and redshifts of
+/// both objects in each row.
+pub struct Separations {
+    /// RA, Dec and redshift columns of the first object.
+    pub first: [String; 3],
+    /// RA, Dec and redshift columns of the second object.
+    pub second: [String; 3],
+}
+
+/// The columns to add to a catalogue and the columns they're computed from.
+pub struct Additions {
+    pub z_column: String,
+    pub quantities: Vec<NewColumn>,
+    pub positions: Option<Positions>,
+    /// Sky positions from cartesian coordinates, whose redshifts then replace the z column.
+    pub sky: Option<SkyPositions>,
+    pub separations: Option<Separations>,
+    /// Relative tolerance for interpolating quantities, or None to compute them for every row.
+    pub rtol: Option<f64>,
+}
+
+/// Name, unit and UCD of an added column.
+struct Added {
+    name: String,
+    unit: String,
+    ucd: &'static str,
+}
+
+/// A catalogue column read to compute the added ones, with the default name and UCD it may also
+/// be found by.
+struct Input {
+    name: String,
+    default: (String, &'static str),
+}
+
+/// The values of the input columns, split by what they're used for.
+struct Inputs<'a> {
+    redshifts: Option<&'a [f64]>,
+    cartesian: Option<[&'a [f64]; 3]>,
+    sky: Option<(&'a [f64], &'a [f64])>,
+    pairs: Option<[&'a [f64]; 6]>,
+}
+
+/// Ranges of the redshifts and comoving distances in a catalogue, to build tables over.
+#[derive(Debug, Clone, Copy, Default)]
+struct Ranges {
+    redshift: Option<(f64, f64)>,
+    distance: Option<(f64, f64)>,
+}
+
+impl Ranges {
+    fn merge(self, other: Ranges) -> Ranges {
+        Ranges {
+            redshift: merge(self.redshift, other.redshift),
+            distance: merge(self.distance, other.distance),
+        }
+    }
+}
+
+/// Names of the cartesian coordinate columns in a frame, cx, cy and cz for equatorial.
+pub fn cartesian_names(frame: Frame) -> [String; 3] {
+    let prefix = match frame {
+        Frame::Equatorial => "c",
+        Frame::Galactic => "g",
+        Frame::Supergalactic => "sg",
+    };
+    ["x", "y", "z"].map(|axis| format!("{}{}", prefix, axis))
+}
+
+const CARTESIAN_UCDS: [&str; 3] = ["pos.cartesian.x", "pos.cartesian.y", "pos.cartesian.z"];
+
+impl Additions {
+    fn columns(&self) -> Vec<Added> {
+        let mut columns = Vec::new();
+        if self.sky.is_some() {
+            for (name, unit, ucd) in [
+                ("ra", "deg", "pos.eq.ra"),
+                ("dec", "deg", "pos.eq.dec"),
+                ("z", "", "src.redshift"),
+            ] {
+                columns.push(Added {
+                    name: name.to_string(),
+                    unit: unit.to_string(),
+                    ucd,
+                });
+            }
+        }
+        columns.extend(self.quantities.iter().map(|column| Added {
+            name: column.name.clone(),
+            unit: ascii_unit(column.quantity.unit),
+            ucd: column.quantity.ucd,
+        }));
+        if let Some(positions) = &self.positions {
+            for (name, ucd) in cartesian_names(positions.frame)
+                .into_iter()
+                .zip(CARTESIAN_UCDS)
+            {
+                columns.push(Added {
+                    name,
+                    unit: "Mpc".to_string(),
+                    ucd,
+                });
+            }
+        }
+        if self.separations.is_some() {
+            for name in ["sep", "rp", "pi"] {
+                columns.push(Added {
+                    name: name.to_string(),
+                    unit: "Mpc".to_string(),
+                    ucd: "pos.distance",
+                });
+            }
+        }
+        columns
+    }
+
+    /// The columns read, in the order `split` expects them.
+    fn inputs(&self) -> Vec<Input> {
+        let input = |name: &str, default: &str, ucd| Input {
+            name: name.to_string(),
+            default: (default.to_string(), ucd),
+        };
+        let mut inputs = Vec::new();
+        match &self.sky {
+            Some(sky) => {
+                let defaults = cartesian_names(sky.frame);
+                for ((name, default), ucd) in sky.columns.iter().zip(&defaults).zip(CARTESIAN_UCDS)
+                {
+                    inputs.push(input(name, default, ucd));
+                }
+            }
+            None if !self.quantities.is_empty() || self.positions.is_some() => {
+                inputs.push(input(&self.z_column, "z", "src.redshift"));
+            }
+            None => {}
+        }
+        if let Some(positions) = &self.positions {
+            inputs.push(input(&positions.ra_column, "ra", "pos.eq.ra"));
+            inputs.push(input(&positions.dec_column, "dec", "pos.eq.dec"));
+        }
+        if let Some(separations) = &self.separations {
+            // The columns of both objects share UCDs, so they are only found by name.
+            for name in separations.first.iter().chain(&separations.second) {
+                inputs.push(input(name, "", ""));
+            }
+        }
+        inputs
+    }
+
+    fn split<'a>(&self, values: &'a [Vec<f64>]) -> Inputs<'a> {
+        let mut values = values.iter().map(Vec::as_slice);
+        let mut next = || values.next().unwrap();
+        let mut inputs = Inputs {
+            redshifts: None,
+            cartesian: None,
+            sky: None,
+            pairs: None,
+        };
+        if self.sky.is_some() {
+            inputs.cartesian = Some([next(), next(), next()]);
+        } else if !self.quantities.is_empty() || self.positions.is_some() {
+            inputs.redshifts = Some(next());
+        }
+        if self.positions.is_some() {
+            inputs.sky = Some((next(), next()));
+        }
+        if self.separations.is_some() {
+            inputs.pairs = Some([next(), next(), next(), next(), next(), next()]);
+        }
+        inputs
+    }
+
+    /// Checks that no added column has the name of an existing one.
+    fn check<'a>(&self, existing: impl Iterator<Item = &'a str> + Clone) -> Result<(), String> {
+        for column in self.columns() {
+            if existing
+                .clone()
+                .any(|name| name.eq_ignore_ascii_case(&column.name))
+            {
+                return Err(format!("The table already has a column {}.", column.name));
+            }
+        }
+        Ok(())
+    }
+
+    /// Ranges of the redshifts, and of the distances of cartesian coordinates, in some rows.
+    fn ranges(&self, values: &[Vec<f64>]) -> Ranges {
+        let inputs = self.split(values);
+        let mut redshifts = inputs.redshifts.map(range).unwrap_or_default();
+        if let Some(pairs) = inputs.pairs {
+            redshifts = merge(merge(redshifts, range(pairs[2])), range(pairs[5]));
+        }
+        let distances: Option<Vec<f64>> = inputs.cartesian.map(|[x, y, z]| {
+            (0..x.len())
+                .map(|i| (x[i] * x[i] + y[i] * y[i] + z[i] * z[i]).sqrt())
+                .collect()
+        });
+        Ranges {
+            redshift: redshifts,
+            distance: distances.as_deref().and_then(range),
+        }
+    }
+
+    /// Spline tables of the added quantities, of the comoving distance where positions or
+    /// separations are added, and of the redshift at a comoving distance where sky positions are,
+    /// over the ranges of a catalogue when a tolerance is given. They are built once for the
+    /// whole catalogue and shared by every batch and thread. Rows the tables don't cover are
+    /// computed by the same quadrature the tables are built from.
+    fn tables(&self, cosmo: &Cosmology, ranges: Ranges) -> Tables {
+        let Some(rtol) = self.rtol else {
+            return Tables {
+                quantities: self.quantities.iter().map(|_| None).collect(),
+                codist: None,
+                redshift: None,
+                precise: None,
+            };
+        };
+        // The tabulated values have to be more accurate than the table.
+        let precise = Precise::new(cosmo, (rtol / 100.).max(MIN_RTOL));
+        let at_distance = |distance: f64| match distance {
+            0. => 0.,
+            _ => precise
+                .inverse_codist(distance)
+                .map_or(f64::NAN, |z| z.value),
+        };
+        let redshift = ranges
+            .distance
+            .and_then(|(min, max)| Table::new(at_distance, min, max, rtol));
+        // With sky positions, the quantities are needed at the redshifts of the distances.
+        let redshifts = match self.sky {
+            Some(_) => ranges
+                .distance
+                .map(|(min, max)| (at_distance(min), at_distance(max)))
+                .filter(|(min, max)| min.is_finite() && max.is_finite()),
+            None => ranges.redshift,
+        };
+        let table = |quantity: &Quantity| {
+            let (min, max) = redshifts?;
+            Table::new(|z| (quantity.precise)(&precise, z).value, min, max, rtol)
+        };
+        let quantities = self
+            .quantities
+            .iter()
+            .map(|column| table(column.quantity))
+            .collect();
+        let codist = (self.positions.is_some() || self.separations.is_some())
+            .then(|| table(quantity::find("codist").unwrap()))
+            .flatten();
+        Tables {
+            quantities,
+            codist,
+            redshift,
+            precise: Some(precise),
+        }
+    }
+
+    /// Values of the added columns, in the order of `columns`, for rows with the values of the
+    /// input columns, in the order of `inputs`.
+    fn compute(&self, cosmo: &Cosmology, tables: &Tables, values: &[Vec<f64>]) -> Vec<Vec<f64>> {
+        let inputs = self.split(values);
+        let mut values = Vec::new();
+        let sky_redshifts = match (&self.sky, inputs.cartesian) {
+            (Some(sky), Some(cartesian)) => {
+                let (ra, dec, z) = sky_positions(cosmo, tables, cartesian, sky.frame);
+                values.extend([ra, dec, z.clone()]);
+                Some(z)
+            }
+            _ => None,
+        };
+        let redshifts = sky_redshifts.as_deref().or(inputs.redshifts).unwrap_or(&[]);
+        values.extend(
+            self.quantities
+                .iter()
+                .zip(&tables.quantities)
+                .map(|(column, table)| {
+                    evaluate(
+                        column.quantity,
+                        cosmo,
+                        redshifts,
+                        table.as_ref(),
+                        tables.precise.as_ref(),
+                    )
+                }),
+        );
+        if let (Some(positions), Some((ra, dec))) = (&self.positions, inputs.sky) {
+            let distances = tables.codist(cosmo, redshifts);
+            let cartesian: Vec<[f64; 3]> = distances
+                .par_iter()
+                .zip(ra)
+                .zip(dec)
+                .map(|((distance, ra), dec)| {
+                    coords::cartesian_at_distance(*distance, *ra, *dec, positions.frame)
+                })
+                .collect();
+            for axis in 0..3 {
+                values.push(cartesian.iter().map(|position| position[axis]).collect());
+            }
+        }
+        if let Some([ra1, dec1, z1, ra2, dec2, z2]) = inputs.pairs {
+            let first = tables.codist(cosmo, z1);
+            let second = tables.codist(cosmo, z2);
+            let separations: Vec<coords::Separation> = (0..first.len())
+                .into_par_iter()
+                .map(|i| {
+                    let position = |distance: f64, ra: f64, dec: f64| {
+                        coords::cartesian_at_distance(distance, ra, dec, Frame::Equatorial)
+                    };
+                    coords::separation(
+                        position(first[i], ra1[i], dec1[i]),
+                        position(second[i], ra2[i], dec2[i]),
+                    )
+                })
+                .collect();
+            values.push(
+                separations
+                    .iter()
+                    .map(|separation| separation.comoving)
+                    .collect(),
+            );
+            values.push(
+                separations
+                    .iter()
+                    .map(|separation| separation.projected)
+                    .collect(),
+            );
+            values.push(
+                separations
+                    .iter()
+                    .map(|separation| separation.line_of_sight)
+                    .collect(),
+            );
+        }
+        values
+    }
+}
+
+/// J2000 RA and Dec in degrees and redshift of comoving cartesian coordinates in Mpc, with NaN
+/// redshifts beyond the reach of the inverse. Redshifts are interpolated from the table of them
+/// against comoving distance where it covers the distance.
+fn sky_positions(
+    cosmo: &Cosmology,
+    tables: &Tables,
+    [x, y, z]: [&[f64]; 3],
+    frame: Frame,
+) -> (Vec<f64>, Vec<f64>, Vec<f64>) {
+    let limit = cosmo.comoving_distance(background::MAX_INVERSE_REDSHIFT);
+    let rows: Vec<(f64, f64, f64)> = (0..x.len())
+        .into_par_iter()
+        .map(|i| {
+            let position = [x[i], y[i], z[i]];
+            let distance = sky::dot(position, position).sqrt();
+            let (ra, dec) = sky::lon_lat(frame.to_equatorial(position));
+            let redshift = match tables
+                .redshift
+                .as_ref()
+                .and_then(|table| table.get(distance))
+            {
+                Some(redshift) => redshift,
+                None if distance == 0. => 0.,
+                None if distance <= limit => match &tables.precise {
+                    Some(precise) => precise
+                        .inverse_codist(distance)
+                        .map_or(f64::NAN, |z| z.value),
+                    None => cosmo.inverse_codist(distance),
+                },
+                None => f64::NAN,
+            };
+            (ra, dec, redshift)
+        })
+        .collect();
+    (
+        rows.iter().map(|row| row.0).collect(),
+        rows.iter().map(|row| row.1).collect(),
+        rows.iter().map(|row| row.2).collect(),
+    )
+}
+
+/// Tables to interpolate the added columns from, where they are interpolated.
+struct Tables {
+    quantities: Vec<Option<Table>>,
+    codist: Option<Table>,
+    /// Redshift against comoving distance.
+    redshift: Option<Table>,
+    /// The quadrature the tables are built from, for the rows they don't cover.
+    precise: Option<Precise>,
+}
+
+impl Tables {
+    /// Comoving distance at each redshift, for positions and separations.
+    fn codist(&self, cosmo: &Cosmology, redshifts: &[f64]) -> Vec<f64> {
+        let codist = quantity::find("codist").unwrap();
+        evaluate(
+            codist,
+            cosmo,
+            redshifts,
+            self.codist.as_ref(),
+            self.precise.as_ref(),
+        )
+    }
+}
+
+/// Smallest and largest positive finite values, if there are any.
+fn range(values: &[f64]) -> Option<(f64, f64)> {
+    values
+        .iter()
+        .filter(|value| value.is_finite() && **value > 0.)
+        .fold(None, |range, value| merge(range, Some((*value, *value))))
+}
+
+fn merge(a: Option<(f64, f64)>, b: Option<(f64, f64)>) -> Option<(f64, f64)> {
+    match (a, b) {
+        (Some((min, max)), Some((low, high))) => Some((min.min(low), max.max(high))),
+        (a, b) => a.or(b),
+    }
+}
+
+/// Unit string of a quantity written with ASCII exponents, as FITS expects.
+pub fn ascii_unit(unit: &str) -> String {
+    unit.replace('²', "2").replace('³', "3")
+}
+
+/// Quantity at each redshift, with NaN for missing or negative redshifts. Given a table, the
+/// quantity is interpolated from it where it covers the redshift instead of being computed, and
+/// given the quadrature the table is built from, computed by it elsewhere.
+pub fn evaluate(
+    quantity: &Quantity,
+    cosmo: &Cosmology,
+    redshifts: &[f64],
+    table: Option<&Table>,
+    precise: Option<&Precise>,
+) -> Vec<f64> {
+    let direct = |z: f64| match precise {
+        _ if !(z.is_finite() && z >= 0.) => f64::NAN,
+        Some(precise) => (quantity.precise)(precise, z).value,
+        None => (quantity.value)(cosmo, z),
+    };
+    redshifts
+        .par_iter()
+        .map(|z| match table.and_then(|table| table.get(*z)) {
+            Some(value) => value,
+            None => direct(*z),
+        })
+        .collect()
+}
+
+/// Adds columns to the first binary table of a FITS file (or the given HDU), copying every other
+/// HDU unchanged. Returns the number of rows.
+pub fn process_fits(
+    input: &Path,
+    output: &Path,
+    additions: &Additions,
+    cosmo: &Cosmology,
+    hdu: Option<usize>,
+) -> Result<usize, String> {
+    let mut hdus = fits::read(input)?;
+    let index = match hdu {
+        Some(index) => index,
+        None => hdus
+            .iter()
+            .position(|hdu| hdu.is_bintable())
+            .ok_or("The file has no binary table.")?,
+    };
+    let table = hdus
+        .get_mut(index)
+        .ok_or(format!("The file has no HDU {}.", index))?;
+    if !table.is_bintable() {
+        return Err(format!("HDU {} is not a binary table.", index));
+    }
+    let existing = table.columns()?;
+    additions.check(existing.iter().map(|column| column.name.as_str()))?;
+
+    let inputs = additions
+        .inputs()
+        .iter()
+        .map(|input| table.column_values(&input.name))
+        .collect::<Result<Vec<_>, _>>()?;
+    let tables = additions.tables(cosmo, additions.ranges(&inputs));
+    let values = additions.compute(cosmo, &tables, &inputs);
+    let columns = additions.columns();
+    let appended: Vec<(&str, &str, Vec<f64>)> = columns
+        .iter()
+        .zip(values)
+        .map(|(column, values)| (column.name.as_str(), column.unit.as_str(), values))
+        .collect();
+    table.append_columns(&appended)?;
+    let rows = table.rows()?;
+    fits::write(output, &hdus)?;
+    Ok(rows)
+}
+
+fn open(path: &Path) -> Result<File, String> {
+    File::open(path).map_err(|error| format!("{}: {}", path.display(), error))
+}
+
+/// Reads a CSV, Parquet or Arrow IPC file in batches. VOTables are read whole as one batch.
+fn reader(path: &Path, format: Format) -> Result<Box<dyn RecordBatchReader>, String> {
+    let mut file = open(path)?;
+    Ok(match format {
+        Format::Csv => {
+            let (schema, _) = arrow::csv::reader::Format::default()
+                .with_header(true)
+                .infer_schema(&mut file, Some(CSV_INFERENCE_ROWS))
+                .map_err(|error| error.to_string())?;
+            file.seek(SeekFrom::Start(0))
+                .map_err(|error| error.to_string())?;
+            Box::new(
+                arrow::csv::ReaderBuilder::new(Arc::new(schema))
+                    .with_header(true)
+                    .with_batch_size(BATCH_ROWS)
+                    .build(file)
+                    .map_err(|error| error.to_string())?,
+            )
+        }
+        Format::Parquet => Box::new(
+            ParquetRecordBatchReaderBuilder::try_new(file)
+                .map_err(|error| error.to_string())?
+                .with_batch_size(BATCH_ROWS)
+                .build()
+                .map_err(|error| error.to_string())?,
+        ),
+        Format::Arrow => Box::new(
+            arrow::ipc::reader::FileReader::try_new(file, None)
+                .map_err(|error| error.to_string())?,
+        ),
+        Format::VOTable => {
+            let batch = votable::read(path)?;
+            let schema = batch.schema();
+            Box::new(RecordBatchIterator::new(vec![Ok(batch)], schema))
+        }
+        Format::Fits => unreachable!("FITS catalogues are not read in batches"),
+    })
+}
+
+enum Writer {
+    Csv(Box<arrow::csv::Writer<File>>),
+    Parquet(Box<ArrowWriter<File>>),
+    Arrow(Box<arrow::ipc::writer::FileWriter<File>>),
+    VOTable(Box<votable::Writer<BufWriter<File>>>),
+}
+
+impl Writer {
+    fn create(path: &Path, format: Format, schema: SchemaRef) -> Result<Writer, String> {
+        let file = File::create(path).map_err(|error| format!("{}: {}", path.display(), error))?;
+        Ok(match format {
+            Format::Csv => Writer::Csv(Box::new(
+                arrow::csv::WriterBuilder::new()
+                    .with_header(true)
+                    .build(file),
+            )),
+            Format::Parquet => Writer::Parquet(Box::new(
+                ArrowWriter::try_new(file, schema, None).map_err(|error| error.to_string())?,
+            )),
+            Format::Arrow => Writer::Arrow(Box::new(
+                arrow::ipc::writer::FileWriter::try_new(file, &schema)
+                    .map_err(|error| error.to_string())?,
+            )),
+            Format::VOTable => Writer::VOTable(Box::new(votable::Writer::try_new(
+                BufWriter::new(file),
+                &schema,
+            )?)),
+            Format::Fits => unreachable!("FITS catalogues are not written in batches"),
+        })
+    }
+
+    fn write(&mut self, batch: &RecordBatch) -> Result<(), String> {
+        match self {
+            Writer::Csv(writer) => writer.write(batch).map_err(|error| error.to_string()),
+            Writer::Parquet(writer) => writer.write(batch).map_err(|error| error.to_string()),
+            Writer::Arrow(writer) => writer.write(batch).map_err(|error| error.to_string()),
+            Writer::VOTable(writer) => writer.write(batch),
+        }
+    }
+
+    fn finish(self) -> Result<(), String> {
+        match self {
+            Writer::Csv(_) => Ok(()),
+            Writer::Parquet(writer) => writer
+                .close()
+                .map(|_| ())
+                .map_err(|error| error.to_string()),
+            Writer::Arrow(mut writer) => writer.finish().map_err(|error| error.to_string()),
+            Writer::VOTable(mut writer) => writer.finish(),
+        }
+    }
+}
+
+/// Values of a numeric column as floats, with NaN for nulls.
+fn floats(column: &ArrayRef, name: &str) -> Result<Vec<f64>, String> {
+    let floats = cast(column, &DataType::Float64).map_err(|error| error.to_string())?;
+    let floats = floats
+        .as_any()
+        .downcast_ref::<Float64Array>()
+        .ok_or(format!("Could not read column {} as numbers.", name))?;
+    Ok((0..floats.len())
+        .map(|row| {
+            if floats.is_null(row) {
+                f64::NAN
+            } else {
+                floats.value(row)
+            }
+        })
+        .collect())
+}
+
+/// Index of a column, found by name or else by a word of its UCD. The default name of the column
+/// also matches a column with the default UCD, such as src.redshift for z.
+fn column_index(schema: &Schema, name: &str, default: (&str, &str)) -> Result<usize, String> {
+    let by_ucd = |ucd: &str| {
+        schema.fields().iter().position(|field| {
+            field.metadata().get("ucd").is_some_and(|words| {
+                words
+                    .split(';')
+                    .any(|word| word.trim().eq_ignore_ascii_case(ucd))
+            })
+        })
+    };
+    schema
+        .fields()
+        .iter()
+        .position(|field| field.name().eq_ignore_ascii_case(name))
+        .or_else(|| by_ucd(name))
+        .or_else(|| {
+            if name == default.0 {
+                by_ucd(default.1)
+            } else {
+                None
+            }
+        })
+        .ok_or(format!("No column named {:?} or with that UCD.", name))
+}
+
+/// Adds columns to a CSV, Parquet, Arrow IPC or VOTable catalogue, one batch of rows at a time.
+/// Units and UCDs are kept in the metadata of the new fields, except in CSV files which have
+/// nowhere to put them. Returns the number of rows.
+pub fn process_table(
+    input: &Path,
+    input_format: Format,
+    output: &Path,
+    output_format: Format,
+    additions: &Additions,
+    cosmo: &Cosmology,
+) -> Result<usize, String> {
+    let batches = reader(input, input_format)?;
+    let schema = batches.schema();
+    additions.check(schema.fields().iter().map(|field| field.name().as_str()))?;
+    let indices = additions
+        .inputs()
+        .iter()
+        .map(|input| column_index(&schema, &input.name, (&input.default.0, input.default.1)))
+        .collect::<Result<Vec<_>, _>>()?;
+    let read = |batch: &RecordBatch| -> Result<Vec<Vec<f64>>, String> {
+        indices
+            .iter()
+            .map(|index| floats(batch.column(*index), schema.field(*index).name()))
+            .collect()
+    };
+
+    let mut fields: Vec<Field> = schema
+        .fields()
+        .iter()
+        .map(|field| field.as_ref().clone())
+        .collect();
+    fields.extend(additions.columns().into_iter().map(|column| {
+        Field::new(column.name, DataType::Float64, true).with_metadata(HashMap::from([
+            ("unit".to_string(), column.unit),
+            ("ucd".to_string(), column.ucd.to_string()),
+        ]))
+    }));
+    let output_schema = Arc::new(Schema::new_with_metadata(fields, schema.metadata().clone()));
+
+    // Interpolation tables need the redshift range of the whole catalogue, so the columns are
+    // read in a first pass.
+    let mut ranges = Ranges::default();
+    if additions.rtol.is_some() {
+        for batch in reader(input, input_format)? {
+            let batch = batch.map_err(|error| error.to_string())?;
+            ranges = ranges.merge(additions.ranges(&read(&batch)?));
+        }
+    }
+    let tables = additions.tables(cosmo, ranges);
+
+    let mut writer = Writer::create(output, output_format, output_schema.clone())?;
+    let mut rows = 0;
+    for batch in batches {
+        let batch = batch.map_err(|error| error.to_string())?;
+        let mut arrays = batch.columns().to_vec();
+        for values in additions.compute(cosmo, &tables, &read(&batch)?) {
+            let array: Float64Array = values
+                .into_iter()
+                .map(|value| (!value.is_nan()).then_some(value))
+                .collect();
+            arrays.push(Arc::new(array));
+        }
+        let batch = RecordBatch::try_new(output_schema.clone(), arrays)
+            .map_err(|error| error.to_string())?;
+        writer.write(&batch)?;
+        rows += batch.num_rows();
+    }
+    writer.finish()?;
+    Ok(rows)
+}
+
+/// Columns holding the sky positions and redshifts of a catalogue's objects.
+pub struct ObjectColumns {
+    pub z_column: String,
+    pub ra_column: String,
+    pub dec_column: String,
+    /// Column identifying the objects, copied to the list of pairs.
+    pub id_column: Option<String>,
+}
+
+/// Sky position and redshift of every row of a catalogue, and the text of its ID column if one is
+/// named. FITS ID columns must be numeric.
+pub fn read_objects(
+    input: &Path,
+    columns: &ObjectColumns,
+    hdu: Option<usize>,
+) -> Result<(Vec<Object>, Option<Vec<String>>), String> {
+    let format =
+        Format::from_path(input).ok_or(format!("Unknown catalogue format {}.", input.display()))?;
+    let objects = |ra: Vec<f64>, dec: Vec<f64>, z: Vec<f64>| -> Vec<Object> {
+        ra.into_iter()
+            .zip(dec)
+            .zip(z)
+            .map(|((ra, dec), z)| Object { ra, dec, z })
+            .collect()
+    };
+    if format == Format::Fits {
+        let hdus = fits::read(input)?;
+        let table = match hdu {
+            Some(index) => hdus
+                .get(index)
+                .ok_or(format!("The file has no HDU {}.", index))?,
+            None => hdus
+                .iter()
+                .find(|hdu| hdu.is_bintable())
+                .ok_or("The file has no binary table.")?,
+        };
+        let ids = match &columns.id_column {
+            Some(name) => Some(
+                table
+                    .column_values(name)?
+                    .iter()
+                    .map(|id| id.to_string())
+                    .collect(),
+            ),
+            None => None,
+        };
+        return Ok((
+            objects(
+                table.column_values(&columns.ra_column)?,
+                table.column_values(&columns.dec_column)?,
+                table.column_values(&columns.z_column)?,
+            ),
+            ids,
+        ));
+    }
+
+    let batches = reader(input, format)?;
+    let schema = batches.schema();
+    let z_index = column_index(&schema, &columns.z_column, ("z", "src.redshift"))?;
+    let ra_index = column_index(&schema, &columns.ra_column, ("ra", "pos.eq.ra"))?;
+    let dec_index = column_index(&schema, &columns.dec_column, ("dec", "pos.eq.dec"))?;
+    let id_index = match &columns.id_column {
+        Some(name) => Some(column_index(&schema, name, ("id", "meta.id"))?),
+        None => None,
+    };
+    let (mut ra, mut dec, mut z) = (Vec::new(), Vec::new(), Vec::new());
+    let mut ids = id_index.map(|_| Vec::new());
+    for batch in batches {
+        let batch = batch.map_err(|error| error.to_string())?;
+        let column = |index: usize| floats(batch.column(index), schema.field(index).name());
+        ra.extend(column(ra_index)?);
+        dec.extend(column(dec_index)?);
+        z.extend(column(z_index)?);
+        if let (Some(index), Some(ids)) = (id_index, ids.as_mut()) {
+            let text =
+                cast(batch.column(index), &DataType::Utf8).map_err(|error| error.to_string())?;
+            let text = text
+                .as_any()
+                .downcast_ref::<StringArray>()
+                .ok_or("Could not read the ID column as text.")?;
+            ids.extend((0..text.len()).map(|row| {
+                if text.is_null(row) {
+                    String::new()
+                } else {
+                    text.value(row).to_string()
+                }
+            }));
+        }
+    }
+    Ok((objects(ra, dec, z), ids))
+}
+
+/// Writes a list of pairs to a CSV, Parquet, Arrow IPC or VOTable file, with the row numbers of the
+/// objects counting from 0 and their IDs if given.
+pub fn write_pairs(output: &Path, pairs: &[Pair], ids: Option<&[String]>) -> Result<(), String> {
+    let format = match Format::from_path(output) {
+        Some(Format::Fits) | None => {
+            return Err(
+                "Pairs can be written to CSV, Parquet, Arrow IPC or VOTable files.".to_string(),
+            );
+        }
+        Some(format) => format,
+    };
+    let field = |name: &str, data_type: DataType, unit: &str, ucd: &str| {
+        let mut metadata = HashMap::from([("ucd".to_string(), ucd.to_string())]);
+        if !unit.is_empty() {
+            metadata.insert("unit".to_string(), unit.to_string());
+        }
+        Field::new(name, data_type, false).with_metadata(metadata)
+    };
+    let mut fields = vec![
+        field("row1", DataType::Int64, "", "meta.record"),
+        field("row2", DataType::Int64, "", "meta.record"),
+    ];
+    let mut arrays: Vec<ArrayRef> = vec![
+        Arc::new(Int64Array::from_iter_values(
+            pairs.iter().map(|pair| pair.first as i64),
+        )),
+        Arc::new(Int64Array::from_iter_values(
+            pairs.iter().map(|pair| pair.second as i64),
+        )),
+    ];
+    if let Some(ids) = ids {
+        fields.push(field("id1", DataType::Utf8, "", "meta.id"));
+        fields.push(field("id2", DataType::Utf8, "", "meta.id"));
+        arrays.push(Arc::new(StringArray::from_iter_values(
+            pairs.iter().map(|pair| &ids[pair.first]),
+        )));
+        arrays.push(Arc::new(StringArray::from_iter_values(
+            pairs.iter().map(|pair| &ids[pair.second]),
+        )));
+    }
+    let separations = |value: fn(&Pair) -> f64| pairs.iter().map(value).collect::<Vec<f64>>();
+    let values = [
+        (
+            "sep",
+            "arcsec",
+            "pos.angDistance",
+            separations(|pair| pair.separation.angle),
+        ),
+        (
+            "rp_phys",
+            "kpc",
+            "pos.distance;arith.diff",
+            separations(|pair| pair.separation.physical),
+        ),
+        (
+            "rp_co",
+            "kpc",
+            "pos.distance;arith.diff",
+            separations(|pair| pair.separation.comoving),
+        ),
+        (
+            "dv",
+            "km/s",
+            "spect.dopplerVeloc;arith.diff",
+            separations(|pair| pair.separation.velocity),
+        ),
+    ];
+    for (name, unit, ucd, values) in values {
+        fields.push(field(name, DataType::Float64, unit, ucd));
+        arrays.push(Arc::new(Float64Array::from(values)));
+    }
+
+    let schema = Arc::new(Schema::new(fields));
+    let batch = RecordBatch::try_new(schema.clone(), arrays).map_err(|error| error.to_string())?;
+    let mut writer = Writer::create(output, format, schema)?;
+    writer.write(&batch)?;
+    writer.finish()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const COSMO: Cosmology = Cosmology {
+        omega_m: 0.3,
+        omega_k: 0.,
+        omega_l: 0.7,
+        h0: 70.,
+    };
+
+    #[test]
+    fn interpolated_rows_agree_with_direct_rows() {
+        let rtol = 1e-8;
+        let additions = Additions {
+            z_column: "z".to_string(),
+            quantities: ["codist", "lumdist", "age"]
+                .map(|name| NewColumn {
+                    name: name.to_string(),
+                    quantity: quantity::find(name).unwrap(),
+                })
+                .into(),
+            positions: None,
+            sky: None,
+            separations: None,
+            rtol: Some(rtol),
+        };
+        // Tables over part of the catalogue, as if built from its first rows, so the rest are
+        // computed directly.
+        let ranges = Ranges {
+            redshift: Some((0.5, 2.)),
+            distance: None,
+        };
+        let tables = additions.tables(&COSMO, ranges);
+        assert!(tables.quantities.iter().all(Option::is_some));
+        let redshifts: Vec<f64> = (0..=120).map(|i| 0.025 * i as f64).collect();
+        let values = additions.compute(&COSMO, &tables, std::slice::from_ref(&redshifts));
+
+        let reference = Precise::new(&COSMO, MIN_RTOL);
+        for (column, values) in additions.quantities.iter().zip(values) {
+            for (z, value) in redshifts.iter().zip(values) {
+                let expected = (column.quantity.precise)(&reference, *z).value;
+                assert!(
+                    (value - expected).abs() <= rtol * expected.abs(),
+                    "{} at z = {}: {} against {}",
+                    column.name,
+                    z,
+                    value,
+                    expected
+                );
+            }
+        }
+    }
+}
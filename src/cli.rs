@@ -1,5 +1,9 @@
 
-use clap::{Arg, Command, ArgAction};
+use clap::{Arg, Command, ArgAction, value_parser};
+use clap_complete::Shell;
+
+use crate::preset::PRESETS;
+use crate::quantity::QUANTITIES;
 
 pub fn cli() -> Command {
     Command::new("cosmo")
@@ -7,6 +11,13 @@ pub fn cli() -> Command {
         .version("1.0.1")
         .author("Trytan Lambert")
         .subcommand_required(true)
+        .arg(
+            Arg::new("from")
+                .long("from")
+                .global(true)
+                .value_parser(["z", "a", "t", "lookback", "dc"])
+                .help("Give redshifts as a scale factor (a), age in Gyr (t), look back time in Gyr (lookback) or comoving distance in Mpc (dc). [default z]"),
+        )
         .subcommand(
     Command::new("all")
                 .about("Print out a summary of values for the given redshift.")
@@ -17,30 +28,16 @@ pub fn cli() -> Command {
                     .index(1)
                     .help("Redshift.")
                 )
+                .args(cosmology_args())
+                .args(uncertainty_args())
                 .arg(
-                    Arg::new("omega_matter")
-                    .long("omega-m")
-                    .short('m')
-                    .help("Omega matter, mass density of the universe. [default 0.3]")
-                )
-                .arg(
-                    Arg::new("omega_lambda")
-                    .long("omega-l")
-                    .short('l')
-                    .help("Omega lambda, Effective mass density of dark energy. [default 0.7]")
-                )
-                .arg(
-                    Arg::new("omega_k")
-                    .long("omega-k")
-                    .short('k')
-                    .help("Omega k, Effective mass density of relativistic particles. [default 0.0]")
-                )
-                .arg(
-                    Arg::new("hubble_constant")
-                    .long("hubble-const")
-                    .short('H')
-                    .help("Hubble constant, default is 70 km/s/Mpc")
+                    Arg::new("verbose")
+                    .long("verbose")
+                    .short('v')
+                    .help("Also print horizons, characteristic scales and growth quantities.")
+                    .action(ArgAction::SetTrue)
                 )
+                .arg(sigma8_arg().requires("verbose"))
 
         )
         .subcommand(
@@ -49,7 +46,7 @@ pub fn cli() -> Command {
                 .about("Calculate comoving distance in Mpc")
                 .arg(
                     Arg::new("input")
-                        .required(true)
+                        .required_unless_present("pdf")
                         .index(1)
                         .help("Either redshift or comoving distance"),
                 )
@@ -60,30 +57,14 @@ pub fn cli() -> Command {
                         .help("Inverse. Redshift at a given comoving distance in Mpc.")
                         .action(ArgAction::SetTrue),
                 )
-                .arg(
-                    Arg::new("omega_matter")
-                    .long("omega-m")
-                    .short('m')
-                    .help("Omega matter, mass density of the universe. [default 0.3]")
-                )
-                .arg(
-                    Arg::new("omega_lambda")
-                    .long("omega-l")
-                    .short('l')
-                    .help("Omega lambda, Effective mass density of dark energy. [default 0.7]")
-                )
-                .arg(
-                    Arg::new("omega_k")
-                    .long("omega-k")
-                    .short('k')
-                    .help("Omega k, Effective mass density of relativistic particles. [default 0.0]")
-                )
-                .arg(
-                    Arg::new("hubble_constant")
-                    .long("hubble-const")
-                    .short('H')
-                    .help("Hubble constant, default is 70 km/s/Mpc")
-                ),
+                .args(cosmology_args())
+                .args(uncertainty_args())
+                .args(sampling_args())
+                .arg(precision_arg())
+                .args(pdf_args())
+                .mut_arg("pdf", |arg| arg.conflicts_with("inverse"))
+                .mut_arg("z_err", |arg| arg.conflicts_with("inverse"))
+                .mut_arg("v_pec", |arg| arg.conflicts_with("inverse")),
         )
 
         .subcommand(
@@ -92,7 +73,7 @@ pub fn cli() -> Command {
                 .about("Calculate the luminosity distance in Mpc")
                 .arg(
                     Arg::new("input")
-                        .required(true)
+                        .required_unless_present("pdf")
                         .index(1)
                         .help("Either redshift or luminosity distance in Mpc"),
                 )
@@ -103,30 +84,14 @@ pub fn cli() -> Command {
                         .help("Inverse. Redshift at a given comoving distance in Mpc.")
                         .action(ArgAction::SetTrue),
                 )
-                .arg(
-                    Arg::new("omega_matter")
-                    .long("omega-m")
-                    .short('m')
-                    .help("Omega matter, mass density of the universe. [default 0.3]")
-                )
-                .arg(
-                    Arg::new("omega_lambda")
-                    .long("omega-l")
-                    .short('l')
-                    .help("Omega lambda, Effective mass density of dark energy. [default 0.7]")
-                )
-                .arg(
-                    Arg::new("omega_k")
-                    .long("omega-k")
-                    .short('k')
-                    .help("Omega k, Effective mass density of relativistic particles. [default 0.0]")
-                )
-                .arg(
-                    Arg::new("hubble_constant")
-                    .long("hubble-const")
-                    .short('H')
-                    .help("Hubble constant, default is 70 km/s/Mpc")
-                ),
+                .args(cosmology_args())
+                .args(uncertainty_args())
+                .args(sampling_args())
+                .arg(precision_arg())
+                .args(pdf_args())
+                .mut_arg("pdf", |arg| arg.conflicts_with("inverse"))
+                .mut_arg("z_err", |arg| arg.conflicts_with("inverse"))
+                .mut_arg("v_pec", |arg| arg.conflicts_with("inverse")),
         )
         .subcommand(
             Command::new("covol")
@@ -145,30 +110,12 @@ pub fn cli() -> Command {
                         .help("Inverse. Redshift at a given comoving volume in Gpc³.")
                         .action(ArgAction::SetTrue),
                 )
-                .arg(
-                    Arg::new("omega_matter")
-                    .long("omega-m")
-                    .short('m')
-                    .help("Omega matter, mass density of the universe. [default 0.3]")
-                )
-                .arg(
-                    Arg::new("omega_lambda")
-                    .long("omega-l")
-                    .short('l')
-                    .help("Omega lambda, Effective mass density of dark energy. [default 0.7]")
-                )
-                .arg(
-                    Arg::new("omega_k")
-                    .long("omega-k")
-                    .short('k')
-                    .help("Omega k, Effective mass density of relativistic particles. [default 0.0]")
-                )
-                .arg(
-                    Arg::new("hubble_constant")
-                    .long("hubble-const")
-                    .short('H')
-                    .help("Hubble constant, default is 70 km/s/Mpc")
-                ),
+                .args(cosmology_args())
+                .args(uncertainty_args())
+                .args(sampling_args())
+                .arg(precision_arg())
+                .mut_arg("z_err", |arg| arg.conflicts_with("inverse"))
+                .mut_arg("v_pec", |arg| arg.conflicts_with("inverse")),
         )
         .subcommand(
             Command::new("lookback")
@@ -176,7 +123,7 @@ pub fn cli() -> Command {
                 .about("Calculate the lookback time in Gyr")
                 .arg(
                     Arg::new("input")
-                        .required(true)
+                        .required_unless_present("pdf")
                         .index(1)
                         .help("Either redshift or lookback time in Gyr"),
                 )
@@ -187,30 +134,14 @@ pub fn cli() -> Command {
                         .help("Inverse. Redshift at a given lookback time in Gyr")
                         .action(ArgAction::SetTrue),
                 )
-                .arg(
-                    Arg::new("omega_matter")
-                    .long("omega-m")
-                    .short('m')
-                    .help("Omega matter, mass density of the universe. [default 0.3]")
-                )
-                .arg(
-                    Arg::new("omega_lambda")
-                    .long("omega-l")
-                    .short('l')
-                    .help("Omega lambda, Effective mass density of dark energy. [default 0.7]")
-                )
-                .arg(
-                    Arg::new("omega_k")
-                    .long("omega-k")
-                    .short('k')
-                    .help("Omega k, Effective mass density of relativistic particles. [default 0.0]")
-                )
-                .arg(
-                    Arg::new("hubble_constant")
-                    .long("hubble-const")
-                    .short('H')
-                    .help("Hubble constant, default is 70 km/s/Mpc")
-                ),
+                .args(cosmology_args())
+                .args(uncertainty_args())
+                .args(sampling_args())
+                .arg(precision_arg())
+                .args(pdf_args())
+                .mut_arg("pdf", |arg| arg.conflicts_with("inverse"))
+                .mut_arg("z_err", |arg| arg.conflicts_with("inverse"))
+                .mut_arg("v_pec", |arg| arg.conflicts_with("inverse")),
         )
         .subcommand(
             Command::new("age")
@@ -218,7 +149,7 @@ pub fn cli() -> Command {
                 .about("Calculate the age of the universe in Gyr at a given redshift.")
                 .arg(
                     Arg::new("input")
-                        .required(true)
+                        .required_unless_present("pdf")
                         .index(1)
                         .help("Either redshift or age in Gyr"),
                 )
@@ -229,122 +160,790 @@ pub fn cli() -> Command {
                         .help("Inverse. Redshift at a given age in Gyr")
                         .action(ArgAction::SetTrue),
                 )
+                .args(cosmology_args())
+                .args(uncertainty_args())
+                .args(sampling_args())
+                .arg(precision_arg())
+                .args(pdf_args())
+                .mut_arg("pdf", |arg| arg.conflicts_with("inverse"))
+                .mut_arg("z_err", |arg| arg.conflicts_with("inverse"))
+                .mut_arg("v_pec", |arg| arg.conflicts_with("inverse")),
+        )
+        .subcommand(
+            Command::new("distmod")
+                .aliases(["DistanceMod", "DistMod", "Distmod", "distance_modulus", "dist_mod"])
+                .about("Distance modulus at a given redshift")
+                .arg(Arg::new("z").required_unless_present("pdf").index(1).help("redshift"))
+                .args(cosmology_args())
+                .args(uncertainty_args())
+                .args(sampling_args())
+                .arg(precision_arg())
                 .arg(
-                    Arg::new("omega_matter")
-                    .long("omega-m")
-                    .short('m')
-                    .help("Omega matter, mass density of the universe. [default 0.3]")
+                    Arg::new("apparent_magnitude")
+                        .long("mag")
+                        .help("Apparent magnitude. Also print the absolute magnitude.")
                 )
+                .args(pdf_args()),
+        )
+        .subcommand(
+            Command::new("angscale_phys")
+                .aliases(["angscale", "angular_scale", "angular_scale_physical", "angscale_physical"])
+                .about("The physical angular scale on sky in units of kpc/arcsec. (Default angular scale)")
+                .arg(Arg::new("z").required_unless_present("pdf").index(1).help("redshift"))
+                .arg(Arg::new("mpc").long("mpc-per-arcmin").short('M').help("Return the angular scale in units of Mpc/arcmin").action(ArgAction::SetTrue))
+                .args(cosmology_args())
+                .args(uncertainty_args())
+                .args(sampling_args())
+                .arg(precision_arg())
                 .arg(
-                    Arg::new("omega_lambda")
-                    .long("omega-l")
-                    .short('l')
-                    .help("Omega lambda, Effective mass density of dark energy. [default 0.7]")
+                    Arg::new("size")
+                        .long("size")
+                        .help("Angular size in arcsec. Also print the size in kpc.")
                 )
+                .args(pdf_args()),
+        )
+        .subcommand(
+            Command::new("angscale_co")
+                .aliases(["angular_scale_comoving", "angscale_comoving"])
+                .about("The co-moving angular scale on sky in units of kpc/arcsec.")
+                .arg(Arg::new("z").required_unless_present("pdf").index(1).help("redshift"))
+                .arg(Arg::new("mpc").long("mpc-per-arcmin").short('M').help("Return the angular scale in units of Mpc/arcmin").action(ArgAction::SetTrue))
+                .args(cosmology_args())
+                .mut_arg("hubble_constant", |arg| arg.alias("hubble-constant"))
+                .args(uncertainty_args())
+                .args(sampling_args())
+                .arg(precision_arg())
                 .arg(
-                    Arg::new("omega_k")
-                    .long("omega-k")
-                    .short('k')
-                    .help("Omega k, Effective mass density of relativistic particles. [default 0.0]")
+                    Arg::new("size")
+                        .long("size")
+                        .help("Angular size in arcsec. Also print the size in kpc.")
                 )
-                .arg(
-                    Arg::new("hubble_constant")
-                    .long("hubble-const")
-                    .short('H')
-                    .help("Hubble constant, default is 70 km/s/Mpc")
-                ),
+                .args(pdf_args()),
         )
         .subcommand(
-            Command::new("distmod")
-                .aliases(["DistanceMod", "DistMod", "Distmod", "distance_modulus", "dist_mod"])
-                .about("Distance modulus at a given redshift")
+            Command::new("horizons")
+                .aliases(["horizon", "Horizons", "scales"])
+                .about("Particle horizon, event horizon, Hubble radius and characteristic redshifts.")
                 .arg(Arg::new("z").required(true).index(1).help("redshift"))
+                .args(cosmology_args())
+                .args(uncertainty_args()),
+        )
+        .subcommand(
+            Command::new("cmb")
+                .aliases(["CMB", "recombination", "early"])
+                .about("Matter-radiation equality, recombination, drag epoch, sound horizon and CMB distance priors.")
+                .args(cosmology_args())
+                .args(early_universe_args()),
+        )
+        .subcommand(
+            Command::new("bao")
+                .aliases(["BAO"])
+                .about("BAO distance observables D_M/r_d, D_H/r_d, D_V/r_d and F_AP at the given redshifts.")
                 .arg(
-                    Arg::new("omega_matter")
-                    .long("omega-m")
-                    .short('m')
-                    .help("Omega matter, mass density of the universe. [default 0.3]")
+                    Arg::new("z")
+                        .index(1)
+                        .num_args(1..)
+                        .required_unless_present("survey")
+                        .help("One or more redshifts."),
                 )
                 .arg(
-                    Arg::new("omega_lambda")
-                    .long("omega-l")
-                    .short('l')
-                    .help("Omega lambda, Effective mass density of dark energy. [default 0.7]")
+                    Arg::new("survey")
+                        .long("survey")
+                        .short('s')
+                        .value_parser(["6df", "boss", "eboss", "desi"])
+                        .help("Tabulate at the effective redshifts of a survey's BAO samples."),
                 )
                 .arg(
-                    Arg::new("omega_k")
-                    .long("omega-k")
-                    .short('k')
-                    .help("Omega k, Effective mass density of relativistic particles. [default 0.0]")
+                    Arg::new("r_drag")
+                        .long("rd")
+                        .help("Sound horizon at the drag epoch in Mpc. [default computed from the cosmology]"),
                 )
+                .args(cosmology_args())
+                .args(early_universe_args()),
+        )
+        .subcommand(
+            Command::new("growth")
+                .aliases(["Growth", "growth_factor", "growth_rate"])
+                .about("Linear growth factor D(z), growth rate f(z) and fσ8 at a given redshift.")
+                .arg(Arg::new("z").required(true).index(1).help("redshift"))
+                .arg(sigma8_arg())
                 .arg(
-                    Arg::new("hubble_constant")
-                    .long("hubble-const")
-                    .short('H')
-                    .help("Hubble constant, default is 70 km/s/Mpc")
+                    Arg::new("early")
+                        .long("early")
+                        .short('e')
+                        .help("Normalise the growth factor to D = a at early times instead of D(0) = 1.")
+                        .action(ArgAction::SetTrue),
+                )
+                .args(cosmology_args())
+                .args(uncertainty_args()),
+        )
+        .subcommand(
+            Command::new("power")
+                .aliases(["Power", "pk", "power_spectrum"])
+                .about("Linear matter power spectrum P(k, z) in (Mpc/h)³, or σ(R) and σ(M).")
+                .arg(Arg::new("z").index(1).help("Redshift. [default 0]"))
+                .args(power_spectrum_args())
+                .arg(Arg::new("k_min").long("kmin").help("Smallest wavenumber in h/Mpc. [default 1e-4]"))
+                .arg(Arg::new("k_max").long("kmax").help("Largest wavenumber in h/Mpc. [default 10]"))
+                .arg(Arg::new("n_k").long("nk").help("Number of log-spaced wavenumbers. [default 50]"))
+                .arg(
+                    Arg::new("radius")
+                        .long("radius")
+                        .short('R')
+                        .num_args(1..)
+                        .help("Print σ(R) for these radii in Mpc/h instead of P(k)."),
+                )
+                .arg(
+                    Arg::new("mass")
+                        .long("mass")
+                        .short('M')
+                        .num_args(1..)
+                        .help("Print σ(M) for these masses in M_sun/h instead of P(k)."),
+                )
+                .args(cosmology_args())
+                .args(early_universe_args()),
+        )
+        .subcommand(
+            Command::new("halo")
+                .aliases(["Halo", "halos"])
+                .about("Halo mass functions, halo properties and halo number counts.")
+                .subcommand_required(true)
+                .subcommand(
+                    Command::new("massfunc")
+                        .aliases(["mf", "mass_function"])
+                        .about("Press-Schechter, Sheth-Tormen and Tinker08 mass functions dn/dlnM in (h/Mpc)³.")
+                        .arg(Arg::new("z").index(1).help("Redshift. [default 0]"))
+                        .arg(Arg::new("m_min").long("mmin").help("Smallest mass in M_sun/h. [default 1e10]"))
+                        .arg(Arg::new("m_max").long("mmax").help("Largest mass in M_sun/h. [default 1e15]"))
+                        .arg(Arg::new("n_m").long("nm").help("Number of log-spaced masses. [default 11]"))
+                        .args(power_spectrum_args())
+                        .args(cosmology_args())
+                        .args(early_universe_args()),
+                )
+                .subcommand(
+                    Command::new("props")
+                        .aliases(["properties"])
+                        .about("Overdensity, radius, concentration and circular velocity for the 200c, 200m and vir conventions.")
+                        .arg(Arg::new("mass").required(true).index(1).help("Halo mass in M_sun/h."))
+                        .arg(Arg::new("z").index(2).help("Redshift. [default 0]"))
+                        .args(cosmology_args()),
+                )
+                .subcommand(
+                    Command::new("counts")
+                        .aliases(["number_counts"])
+                        .about("Expected number of halos above a mass within a survey volume.")
+                        .arg(Arg::new("m_min").required(true).index(1).help("Minimum halo mass in M_sun/h."))
+                        .arg(Arg::new("z_min").long("zmin").help("Lower redshift of the survey. [default 0]"))
+                        .arg(Arg::new("z_max").long("zmax").required(true).help("Upper redshift of the survey."))
+                        .arg(Arg::new("area").long("area").short('a').help("Survey area in deg². [default full sky]"))
+                        .arg(
+                            Arg::new("model")
+                                .long("model")
+                                .value_parser(["ps", "st", "tinker08"])
+                                .help("Mass function. [default tinker08]"),
+                        )
+                        .args(power_spectrum_args())
+                        .args(cosmology_args())
+                        .args(early_universe_args()),
                 ),
         )
         .subcommand(
-            Command::new("angscale_phys")
-                .aliases(["angscale", "angular_scale", "angular_scale_physical", "angscale_physical"])
-                .about("The physical angular scale on sky in units of kpc/arcsec. (Default angular scale)")
-                .arg(Arg::new("z").required(true).index(1).help("redshift"))
-                .arg(Arg::new("mpc").long("mpc-per-arcmin").short('M').help("Return the angular scale in units of Mpc/arcmin").action(ArgAction::SetTrue))
-                                .arg(
-                    Arg::new("omega_matter")
-                    .long("omega-m")
-                    .short('m')
-                    .help("Omega matter, mass density of the universe. [default 0.3]")
+            Command::new("velocity")
+                .aliases(["Velocity", "vel"])
+                .about("Conversions between redshifts, recession velocities and peculiar velocities.")
+                .subcommand_required(true)
+                .subcommand(
+                    Command::new("convert")
+                        .about("Recession velocity in km/s at a given redshift.")
+                        .allow_negative_numbers(true)
+                        .arg(
+                            Arg::new("input")
+                                .required(true)
+                                .index(1)
+                                .help("Either redshift or velocity in km/s"),
+                        )
+                        .arg(
+                            Arg::new("inverse")
+                                .long("inverse")
+                                .short('i')
+                                .help("Inverse. Redshift at a given velocity in km/s.")
+                                .action(ArgAction::SetTrue),
+                        )
+                        .arg(
+                            Arg::new("convention")
+                                .long("convention")
+                                .short('c')
+                                .value_parser(["relativistic", "optical", "radio"])
+                                .help("Velocity convention for the inverse. [default relativistic]"),
+                        ),
+                )
+                .subcommand(
+                    Command::new("combine")
+                        .about("Observed redshift from a cosmological redshift and a peculiar velocity, (1+z_obs) = (1+z_cos)(1+z_pec).")
+                        .allow_negative_numbers(true)
+                        .arg(
+                            Arg::new("input")
+                                .required(true)
+                                .index(1)
+                                .help("Either cosmological or observed redshift"),
+                        )
+                        .arg(
+                            Arg::new("v_pec")
+                                .required(true)
+                                .index(2)
+                                .help("Line of sight peculiar velocity in km/s."),
+                        )
+                        .arg(
+                            Arg::new("inverse")
+                                .long("inverse")
+                                .short('i')
+                                .help("Inverse. Cosmological redshift at a given observed redshift.")
+                                .action(ArgAction::SetTrue),
+                        ),
+                )
+                .subcommand(
+                    Command::new("cmb")
+                        .aliases(["cmb_frame"])
+                        .about("Convert a heliocentric redshift to the CMB rest frame for a sky position.")
+                        .allow_negative_numbers(true)
+                        .arg(
+                            Arg::new("input")
+                                .required(true)
+                                .index(1)
+                                .help("Either heliocentric or CMB frame redshift"),
+                        )
+                        .arg(Arg::new("ra").required(true).index(2).help("Right ascension (J2000) in degrees."))
+                        .arg(Arg::new("dec").required(true).index(3).help("Declination (J2000) in degrees."))
+                        .arg(
+                            Arg::new("inverse")
+                                .long("inverse")
+                                .short('i')
+                                .help("Inverse. Heliocentric redshift at a given CMB frame redshift.")
+                                .action(ArgAction::SetTrue),
+                        ),
+                ),
+        )
+        .subcommand(
+            Command::new("coords")
+                .aliases(["coordinates", "xyz"])
+                .about("Comoving cartesian coordinates of sky positions and separations between them.")
+                .subcommand_required(true)
+                .subcommand(
+                    Command::new("cartesian")
+                        .about("Comoving X, Y, Z in Mpc at a redshift and sky position.")
+                        .allow_negative_numbers(true)
+                        .arg(
+                            Arg::new("input")
+                                .required(true)
+                                .index(1)
+                                .help("Either redshift or comoving X in Mpc"),
+                        )
+                        .arg(Arg::new("ra").required(true).index(2).help("Right ascension (J2000) in degrees, or comoving Y in Mpc."))
+                        .arg(Arg::new("dec").required(true).index(3).help("Declination (J2000) in degrees, or comoving Z in Mpc."))
+                        .arg(frame_arg())
+                        .arg(
+                            Arg::new("inverse")
+                                .long("inverse")
+                                .short('i')
+                                .help("Inverse. Redshift and sky position at comoving X, Y, Z.")
+                                .action(ArgAction::SetTrue),
+                        )
+                        .args(cosmology_args()),
+                )
+                .subcommand(
+                    Command::new("separation")
+                        .aliases(["sep"])
+                        .about("Comoving separation of two objects, and its parts across (r_p) and along (π) the line of sight.")
+                        .allow_negative_numbers(true)
+                        .arg(Arg::new("z1").required(true).index(1).help("Redshift of the first object."))
+                        .arg(Arg::new("ra1").required(true).index(2).help("Right ascension (J2000) of the first object in degrees."))
+                        .arg(Arg::new("dec1").required(true).index(3).help("Declination (J2000) of the first object in degrees."))
+                        .arg(Arg::new("z2").required(true).index(4).help("Redshift of the second object."))
+                        .arg(Arg::new("ra2").required(true).index(5).help("Right ascension (J2000) of the second object in degrees."))
+                        .arg(Arg::new("dec2").required(true).index(6).help("Declination (J2000) of the second object in degrees."))
+                        .args(cosmology_args()),
+                )
+                .subcommand(
+                    Command::new("pair")
+                        .about("Projected physical and comoving separation and velocity difference of two objects.")
+                        .allow_negative_numbers(true)
+                        .arg(Arg::new("z1").required(true).index(1).help("Redshift of the first object."))
+                        .arg(Arg::new("ra1").required(true).index(2).help("Right ascension (J2000) of the first object in degrees."))
+                        .arg(Arg::new("dec1").required(true).index(3).help("Declination (J2000) of the first object in degrees."))
+                        .arg(Arg::new("z2").required(true).index(4).help("Redshift of the second object."))
+                        .arg(Arg::new("ra2").required(true).index(5).help("Right ascension (J2000) of the second object in degrees."))
+                        .arg(Arg::new("dec2").required(true).index(6).help("Declination (J2000) of the second object in degrees."))
+                        .args(cosmology_args()),
+                )
+                .subcommand(
+                    Command::new("pairs")
+                        .about("Find all pairs in a catalogue within a projected separation and velocity difference.")
+                        .arg(
+                            Arg::new("input")
+                            .required(true)
+                            .index(1)
+                            .help("Input catalogue (.fits, .csv, .parquet, .arrow or .vot).")
+                        )
+                        .arg(
+                            Arg::new("output")
+                            .long("out")
+                            .short('o')
+                            .required(true)
+                            .help("Output list of pairs (.csv, .parquet, .arrow or .vot).")
+                        )
+                        .arg(
+                            Arg::new("rp")
+                            .long("rp")
+                            .required(true)
+                            .help("Largest projected separation in kpc.")
+                        )
+                        .arg(
+                            Arg::new("dv")
+                            .long("dv")
+                            .required(true)
+                            .help("Largest line of sight velocity difference in km/s.")
+                        )
+                        .arg(
+                            Arg::new("comoving")
+                            .long("comoving")
+                            .help("The projected separation limit is comoving rather than physical.")
+                            .action(ArgAction::SetTrue)
+                        )
+                        .arg(
+                            Arg::new("z_column")
+                            .long("zcol")
+                            .short('z')
+                            .help("Name or UCD of the redshift column. [default z, or the column with UCD src.redshift]")
+                        )
+                        .arg(
+                            Arg::new("ra_column")
+                            .long("racol")
+                            .help("Name or UCD of the right ascension (J2000) column in degrees. [default ra, or the column with UCD pos.eq.ra]")
+                        )
+                        .arg(
+                            Arg::new("dec_column")
+                            .long("deccol")
+                            .help("Name or UCD of the declination (J2000) column in degrees. [default dec, or the column with UCD pos.eq.dec]")
+                        )
+                        .arg(
+                            Arg::new("id_column")
+                            .long("idcol")
+                            .help("Name or UCD of a column identifying the objects, copied to the pairs.")
+                        )
+                        .arg(
+                            Arg::new("hdu")
+                            .long("hdu")
+                            .value_parser(value_parser!(usize))
+                            .help("Index of the table HDU, counting the primary HDU as 0. [default first binary table]")
+                        )
+                        .args(cosmology_args()),
+                ),
+        )
+        .subcommand(
+            Command::new("sim")
+                .aliases(["simulation"])
+                .about("Helpers for simulation boxes, snapshots and lightcones. Lengths are comoving in Mpc/h.")
+                .subcommand_required(true)
+                .subcommand(
+                    Command::new("box")
+                        .about("Redshift range spanned along the line of sight by a box centred at a redshift.")
+                        .arg(Arg::new("z").required(true).index(1).help("Redshift of the box centre."))
+                        .arg(Arg::new("side").required(true).index(2).help("Box side in Mpc/h."))
+                        .args(cosmology_args()),
+                )
+                .subcommand(
+                    Command::new("lightcone")
+                        .about("Replications of a box needed to fill a lightcone.")
+                        .arg(Arg::new("side").required(true).index(1).help("Box side in Mpc/h."))
+                        .arg(Arg::new("z_max").long("zmax").required(true).help("Redshift the lightcone reaches."))
+                        .arg(Arg::new("area").long("area").short('a').help("Lightcone area in deg². [default full sky]"))
+                        .args(cosmology_args()),
+                )
+                .subcommand(
+                    Command::new("snapshots")
+                        .aliases(["snaps"])
+                        .about("Comoving distances of snapshot redshifts and the lightcone shells they fill.")
+                        .arg(
+                            Arg::new("input")
+                                .required(true)
+                                .num_args(1..)
+                                .help("Snapshot redshifts, or comoving distances in Mpc/h with --inverse."),
+                        )
+                        .arg(
+                            Arg::new("inverse")
+                                .long("inverse")
+                                .short('i')
+                                .help("Inverse. Redshifts at comoving distances in Mpc/h.")
+                                .action(ArgAction::SetTrue),
+                        )
+                        .args(cosmology_args()),
+                )
+                .subcommand(
+                    Command::new("outputs")
+                        .about("Scale factors and redshifts of snapshot output times.")
+                        .arg(
+                            Arg::new("times")
+                                .required(true)
+                                .num_args(1..)
+                                .help("Output times as ages of the universe in Gyr."),
+                        )
+                        .arg(
+                            Arg::new("lookback")
+                                .long("lookback")
+                                .help("The times are look back times rather than ages.")
+                                .action(ArgAction::SetTrue),
+                        )
+                        .args(cosmology_args()),
+                ),
+        )
+        .subcommand(
+            Command::new("repl")
+                .aliases(["interactive", "shell"])
+                .about("Interactive session keeping a cosmology between calculations.")
+                .args(cosmology_args()),
+        )
+        .subcommand(
+            Command::new("catalog")
+                .aliases(["catalogue", "cat"])
+                .about("Add computed columns to a FITS, CSV, Parquet, Arrow IPC or VOTable catalogue.")
+                .arg(
+                    Arg::new("input")
+                    .required(true)
+                    .index(1)
+                    .help("Input catalogue (.fits, .csv, .parquet, .arrow or .vot).")
+                )
+                .arg(
+                    Arg::new("output")
+                    .long("out")
+                    .short('o')
+                    .required(true)
+                    .help("Output catalogue (.fits, .csv, .parquet, .arrow or .vot). FITS input must be written as FITS.")
                 )
                 .arg(
-                    Arg::new("omega_lambda")
-                    .long("omega-l")
-                    .short('l')
-                    .help("Omega lambda, Effective mass density of dark energy. [default 0.7]")
+                    Arg::new("z_column")
+                    .long("zcol")
+                    .short('z')
+                    .help("Name or UCD of the redshift column. [default z, or the column with UCD src.redshift]")
                 )
                 .arg(
-                    Arg::new("omega_k")
-                    .long("omega-k")
-                    .short('k')
-                    .help("Omega k, Effective mass density of relativistic particles. [default 0.0]")
+                    Arg::new("add")
+                    .long("add")
+                    .short('a')
+                    .required_unless_present_any(["xyz", "sky", "separation"])
+                    .value_delimiter(',')
+                    .help("Comma separated quantities to add, e.g. codist,lumdist,distmod,angscale,age.")
                 )
                 .arg(
-                    Arg::new("hubble_constant")
-                    .long("hubble-const")
-                    .short('H')
-                    .help("Hubble constant, default is 70 km/s/Mpc")
-                ),
+                    Arg::new("xyz")
+                    .long("xyz")
+                    .help("Add comoving cartesian coordinates in Mpc from the RA, Dec and redshift columns, named cx, cy, cz (gx, gy, gz galactic or sgx, sgy, sgz supergalactic).")
+                    .action(ArgAction::SetTrue)
+                )
+                .arg(
+                    Arg::new("ra_column")
+                    .long("racol")
+                    .requires("xyz")
+                    .help("Name or UCD of the right ascension (J2000) column in degrees. [default ra, or the column with UCD pos.eq.ra]")
+                )
+                .arg(
+                    Arg::new("dec_column")
+                    .long("deccol")
+                    .requires("xyz")
+                    .help("Name or UCD of the declination (J2000) column in degrees. [default dec, or the column with UCD pos.eq.dec]")
+                )
+                .arg(
+                    Arg::new("sky")
+                    .long("sky")
+                    .conflicts_with_all(["xyz", "z_column"])
+                    .help("Add the J2000 RA and Dec in degrees and the redshift of comoving cartesian coordinates in Mpc, named ra, dec and z. Quantities given to --add are then evaluated at these redshifts.")
+                    .action(ArgAction::SetTrue)
+                )
+                .arg(
+                    Arg::new("xyz_columns")
+                    .long("xyzcols")
+                    .requires("sky")
+                    .value_delimiter(',')
+                    .help("Comma separated names or UCDs of the X, Y and Z columns in Mpc. [default cx,cy,cz, gx,gy,gz galactic or sgx,sgy,sgz supergalactic]")
+                )
+                .arg(frame_arg())
+                .arg(
+                    Arg::new("separation")
+                    .long("sep")
+                    .help("Add the comoving separation in Mpc of the two objects in each row, named sep, and its parts across (rp) and along (pi) the line of sight.")
+                    .action(ArgAction::SetTrue)
+                )
+                .arg(
+                    Arg::new("separation_columns")
+                    .long("sepcols")
+                    .requires("separation")
+                    .value_delimiter(',')
+                    .help("Comma separated names of the RA, Dec and redshift columns of the first and then the second object. [default ra1,dec1,z1,ra2,dec2,z2]")
+                )
+                .arg(
+                    Arg::new("hdu")
+                    .long("hdu")
+                    .value_parser(value_parser!(usize))
+                    .help("Index of the table HDU, counting the primary HDU as 0. [default first binary table]")
+                )
+                .arg(
+                    Arg::new("interpolate")
+                    .long("interp")
+                    .help("Interpolate the quantities from a spline table to this relative tolerance, e.g. 1e-8, instead of integrating for every row.")
+                )
+                .arg(
+                    Arg::new("threads")
+                    .long("threads")
+                    .short('j')
+                    .help("Number of threads to evaluate with. [default number of CPUs]")
+                )
+                .args(cosmology_args()),
         )
         .subcommand(
-            Command::new("angscale_co")
-                .aliases(["angular_scale_comoving", "angscale_comoving"])
-                .about("The co-moving angular scale on sky in units of kpc/arcsec.")
-                .arg(Arg::new("z").required(true).index(1).help("redshift"))
-                .arg(Arg::new("Mpc").long("mpc-per-arcmin").short('M').help("Return the angular scale in units of Mpc/arcmin").action(ArgAction::SetTrue))
-                                .arg(
-                    Arg::new("omega_matter")
-                    .long("omega-m")
-                    .short('m')
-                    .help("Omega matter, mass density of the universe. [default 0.3]")
+            Command::new("eval")
+                .aliases(["calc", "expr"])
+                .about("Evaluate an expression of cosmological quantities, constants and units.")
+                .allow_negative_numbers(true)
+                .arg(
+                    Arg::new("expression")
+                    .required(true)
+                    .index(1)
+                    .num_args(1..)
+                    .help("Expression, e.g. '4 pi dl(0.5)^2 in cm^2' or '3 arcsec * angscale(1)'.")
                 )
+                .args(cosmology_args()),
+        )
+        .subcommand(
+            Command::new("plot")
+                .about("Plot a quantity against redshift in the terminal or to an SVG or PNG file.")
                 .arg(
-                    Arg::new("omega_lambda")
-                    .long("omega-l")
-                    .short('l')
-                    .help("Omega lambda, Effective mass density of dark energy. [default 0.7]")
+                    Arg::new("quantity")
+                    .required(true)
+                    .index(1)
+                    .value_parser(QUANTITIES.iter().map(|quantity| quantity.name).collect::<Vec<_>>())
+                    .help("Quantity to plot.")
                 )
                 .arg(
-                    Arg::new("omega_k")
-                    .long("omega-k")
-                    .short('k')
-                    .help("Omega k, Effective mass density of relativistic particles. [default 0.0]")
+                    Arg::new("zmin")
+                    .long("zmin")
+                    .help("Lowest redshift. [default 0, or 0.01 with --logx]")
                 )
                 .arg(
-                    Arg::new("hubble_constant")
-                    .alias("hubble-constant")
-                    .long("hubble-const")
-                    .short('H')
-                    .help("Hubble constant, default is 70 km/s/Mpc")
+                    Arg::new("zmax")
+                    .long("zmax")
+                    .help("Highest redshift. [default 3]")
+                )
+                .arg(
+                    Arg::new("points")
+                    .long("points")
+                    .short('n')
+                    .help("Number of redshifts to evaluate. [default 200]")
+                )
+                .arg(
+                    Arg::new("log_x")
+                    .long("logx")
+                    .help("Logarithmic redshift axis.")
+                    .action(ArgAction::SetTrue)
+                )
+                .arg(
+                    Arg::new("log_y")
+                    .long("logy")
+                    .help("Logarithmic value axis.")
+                    .action(ArgAction::SetTrue)
+                )
+                .arg(
+                    Arg::new("presets")
+                    .long("preset")
+                    .short('p')
+                    .num_args(1..)
+                    .value_parser(PRESETS.iter().map(|preset| preset.name).collect::<Vec<_>>())
+                    .help("Preset cosmologies to overlay on the given cosmology.")
+                )
+                .arg(
+                    Arg::new("width")
+                    .long("width")
+                    .help("Plot width in characters. [default 72]")
+                )
+                .arg(
+                    Arg::new("height")
+                    .long("height")
+                    .help("Plot height in characters. [default 20]")
+                )
+                .arg(
+                    Arg::new("out")
+                    .long("out")
+                    .short('o')
+                    .help("Write the figure to an .svg or .png file instead of the terminal.")
+                )
+                .args(cosmology_args()),
+        )
+        .subcommand(
+            Command::new("completions")
+                .about("Print a shell completion script.")
+                .arg(
+                    Arg::new("shell")
+                    .required(true)
+                    .index(1)
+                    .value_parser(value_parser!(Shell))
+                    .help("Shell to complete for.")
                 ),
         )
+        .subcommand(
+            Command::new("man")
+                .about("Print or write man pages.")
+                .arg(
+                    Arg::new("subcommand")
+                    .index(1)
+                    .num_args(1..)
+                    .help("Sub-command to print the page of, e.g. 'codist' or 'halo props'. [default cosmo]")
+                )
+                .arg(
+                    Arg::new("dir")
+                    .long("dir")
+                    .short('d')
+                    .conflicts_with("subcommand")
+                    .help("Directory to write the pages of cosmo and every sub-command to.")
+                ),
+        )
+        .subcommand(
+            Command::new("tui")
+                .about("Full screen summary which updates as the redshift and cosmology are changed.")
+                .arg(
+                    Arg::new("z")
+                    .index(1)
+                    .help("Starting redshift. [default 1]")
+                )
+                .args(cosmology_args()),
+        )
+}
+
+/// The cosmological parameter arguments shared by every subcommand.
+fn cosmology_args() -> [Arg; 4] {
+    [
+        Arg::new("omega_matter")
+            .long("omega-m")
+            .short('m')
+            .help("Omega matter, mass density of the universe. [default 0.3]"),
+        Arg::new("omega_lambda")
+            .long("omega-l")
+            .short('l')
+            .help("Omega lambda, Effective mass density of dark energy. [default 0.7]"),
+        Arg::new("omega_k")
+            .long("omega-k")
+            .short('k')
+            .help("Omega k, Effective mass density of relativistic particles. [default 0.0]"),
+        Arg::new("hubble_constant")
+            .long("hubble-const")
+            .short('H')
+            .help("Hubble constant, default is 70 km/s/Mpc"),
+    ]
+}
+
+/// The baryon and radiation arguments used by the early universe subcommands.
+fn early_universe_args() -> [Arg; 3] {
+    [
+        Arg::new("omega_baryon")
+            .long("omega-b")
+            .short('b')
+            .help("Omega baryon, mass density of baryons. [default 0.049]"),
+        Arg::new("t_cmb")
+            .long("t-cmb")
+            .help("Temperature of the CMB today in K. [default 2.7255]"),
+        Arg::new("n_eff")
+            .long("n-eff")
+            .help("Effective number of neutrino species. [default 3.046]"),
+    ]
+}
+
+/// The arguments defining the linear matter power spectrum.
+fn power_spectrum_args() -> [Arg; 4] {
+    [
+        Arg::new("transfer")
+            .long("transfer")
+            .short('t')
+            .value_parser(["eh", "nowiggle", "bbks"])
+            .help("Transfer function: Eisenstein & Hu with or without wiggles, or BBKS. [default eh]"),
+        Arg::new("sigma8")
+            .long("sigma8")
+            .short('s')
+            .help("Normalise to σ8 today. [default 0.8]"),
+        Arg::new("a_s")
+            .long("as")
+            .conflicts_with("sigma8")
+            .help("Normalise to the primordial amplitude A_s at k = 0.05/Mpc instead of σ8."),
+        Arg::new("n_s")
+            .long("ns")
+            .help("Spectral index of the primordial power spectrum. [default 0.965]"),
+    ]
+}
+
+/// The amplitude of matter fluctuations today, used for fσ8.
+fn sigma8_arg() -> Arg {
+    Arg::new("sigma8")
+        .long("sigma8")
+        .short('s')
+        .help("Amplitude of matter fluctuations today, σ8. [default 0.8]")
+}
+
+/// The redshift uncertainty arguments propagated into the single redshift quantities.
+fn uncertainty_args() -> [Arg; 2] {
+    [
+        Arg::new("z_err")
+            .long("zerr")
+            .help("Redshift uncertainty, propagated to the result as value ± error."),
+        Arg::new("v_pec")
+            .long("vpec")
+            .help("Peculiar velocity dispersion in km/s, propagated to the result as value ± error."),
+    ]
+}
+
+/// The frame of comoving cartesian coordinates.
+fn frame_arg() -> Arg {
+    Arg::new("frame")
+        .long("frame")
+        .short('f')
+        .value_parser(["equatorial", "galactic", "supergalactic"])
+        .help("Frame of the cartesian axes. [default equatorial]")
+}
+
+/// The Monte Carlo arguments for propagating cosmological parameter uncertainties.
+fn sampling_args() -> [Arg; 3] {
+    [
+        Arg::new("n_samples")
+            .long("sample")
+            .value_parser(value_parser!(usize))
+            .conflicts_with_all(["z_err", "v_pec"])
+            .help("Number of cosmologies to draw. --omega-m and --hubble-const then accept value±sigma, and omega lambda follows from flatness."),
+        Arg::new("covariance")
+            .long("cov")
+            .requires("n_samples")
+            .help("File with the 2x2 covariance matrix of (omega matter, Hubble constant) to sample from."),
+        Arg::new("seed")
+            .long("seed")
+            .value_parser(value_parser!(u64))
+            .requires("n_samples")
+            .help("Seed for the random draws, for reproducible results."),
+    ]
+}
+
+/// The relative tolerance for evaluating a quantity by adaptive quadrature.
+fn precision_arg() -> Arg {
+    Arg::new("rtol")
+        .long("rtol")
+        .conflicts_with_all(["z_err", "v_pec", "n_samples"])
+        .help("Relative tolerance. Evaluates the result by adaptive Gauss-Kronrod quadrature, and inverses by Brent's method, and prints its estimated numerical error.")
+}
+
+/// The arguments for propagating a photometric redshift PDF.
+fn pdf_args() -> [Arg; 2] {
+    [
+        Arg::new("pdf")
+            .long("pdf")
+            .conflicts_with_all(["z_err", "v_pec", "n_samples", "rtol"])
+            .help("Redshift PDF, either a file with z and p(z) columns or a Gaussian z±sigma. Prints summary statistics of the result."),
+        Arg::new("pdf_table")
+            .long("pdf-table")
+            .requires("pdf")
+            .help("Also print the PDF of the result at each redshift of the PDF.")
+            .action(ArgAction::SetTrue),
+    ]
 }
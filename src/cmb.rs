@@ -0,0 +1,147 @@
+use cosmoxide::Cosmology;
+
+use crate::background::{SPEED_OF_LIGHT, hubble_distance};
+use crate::numerics::simpson;
+
+const INTEGRATION_STEPS: usize = 4000;
+
+/// Baryon and radiation content, which the background `Cosmology` does not track.
+#[derive(Debug, Clone, Copy)]
+pub struct EarlyUniverse {
+    pub omega_b: f64,
+    pub t_cmb: f64,
+    pub n_eff: f64,
+}
+
+impl EarlyUniverse {
+    /// Physical photon density Ω_γ h² for the given CMB temperature.
+    pub fn omega_gamma_h2(&self) -> f64 {
+        2.469e-5 * (self.t_cmb / 2.7255).powi(4)
+    }
+
+    /// Photon plus massless neutrino density Ω_r.
+    pub fn omega_r(&self, cosmo: &Cosmology) -> f64 {
+        let h = cosmo.h0 / 100.;
+        self.omega_gamma_h2() * (1. + 0.2271 * self.n_eff) / (h * h)
+    }
+
+    /// a² E(a), where E(a) = H(a)/H0 including radiation. Finite at a = 0.
+    fn a2_efunc(&self, cosmo: &Cosmology, a: f64) -> f64 {
+        (self.omega_r(cosmo)
+            + cosmo.omega_m * a
+            + cosmo.omega_k * a.powi(2)
+            + cosmo.omega_l * a.powi(4))
+        .sqrt()
+    }
+
+    /// Redshift of matter-radiation equality.
+    pub fn z_equality(&self, cosmo: &Cosmology) -> f64 {
+        cosmo.omega_m / self.omega_r(cosmo) - 1.
+    }
+
+    /// Redshift of recombination (photon decoupling) from the Hu & Sugiyama (1996) fit.
+    pub fn z_recombination(&self, cosmo: &Cosmology) -> f64 {
+        let h = cosmo.h0 / 100.;
+        let ombh2 = self.omega_b * h * h;
+        let omh2 = cosmo.omega_m * h * h;
+        let g1 = 0.0783 * ombh2.powf(-0.238) / (1. + 39.5 * ombh2.powf(0.763));
+        let g2 = 0.560 / (1. + 21.1 * ombh2.powf(1.81));
+        1048. * (1. + 0.00124 * ombh2.powf(-0.738)) * (1. + g1 * omh2.powf(g2))
+    }
+
+    /// Redshift of the baryon drag epoch from the Eisenstein & Hu (1998) fit.
+    pub fn z_drag(&self, cosmo: &Cosmology) -> f64 {
+        let h = cosmo.h0 / 100.;
+        let ombh2 = self.omega_b * h * h;
+        let omh2 = cosmo.omega_m * h * h;
+        let b1 = 0.313 * omh2.powf(-0.419) * (1. + 0.607 * omh2.powf(0.674));
+        let b2 = 0.238 * omh2.powf(0.223);
+        1291. * omh2.powf(0.251) / (1. + 0.659 * omh2.powf(0.828)) * (1. + b1 * ombh2.powf(b2))
+    }
+
+    /// Comoving sound horizon at redshift z in Mpc.
+    pub fn sound_horizon(&self, cosmo: &Cosmology, z: f64) -> f64 {
+        let h = cosmo.h0 / 100.;
+        // Baryon-to-photon momentum density ratio R = 3ρ_b / 4ρ_γ is proportional to a.
+        let r_coefficient = 3. * self.omega_b * h * h / (4. * self.omega_gamma_h2());
+        let integrand = |a: f64| {
+            let sound_speed = 1. / (3. * (1. + r_coefficient * a)).sqrt();
+            sound_speed / self.a2_efunc(cosmo, a)
+        };
+        hubble_distance(cosmo) * simpson(integrand, 0., 1. / (1. + z), INTEGRATION_STEPS)
+    }
+
+    /// Comoving distance to redshift z in Mpc, including radiation.
+    pub fn comoving_distance(&self, cosmo: &Cosmology, z: f64) -> f64 {
+        let integrand = |a: f64| 1. / self.a2_efunc(cosmo, a);
+        hubble_distance(cosmo) * simpson(integrand, 1. / (1. + z), 1., INTEGRATION_STEPS)
+    }
+
+    /// CMB shift parameter R = sqrt(Ω_m) H0 D_M(z_*) / c.
+    pub fn shift_parameter(&self, cosmo: &Cosmology) -> f64 {
+        let z_star = self.z_recombination(cosmo);
+        cosmo.omega_m.sqrt() * cosmo.h0 * self.comoving_distance(cosmo, z_star) / SPEED_OF_LIGHT
+    }
+
+    /// Acoustic scale ℓ_A = π D_M(z_*) / r_s(z_*).
+    pub fn acoustic_scale(&self, cosmo: &Cosmology) -> f64 {
+        let z_star = self.z_recombination(cosmo);
+        std::f64::consts::PI * self.comoving_distance(cosmo, z_star)
+            / self.sound_horizon(cosmo, z_star)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Planck 2018 TT,TE,EE+lowE+lensing+BAO with massless neutrinos.
+    const PLANCK18: Cosmology = Cosmology {
+        omega_m: 0.30966,
+        omega_k: 0.,
+        omega_l: 0.69034,
+        h0: 67.66,
+    };
+
+    const EARLY: EarlyUniverse = EarlyUniverse {
+        omega_b: 0.04897,
+        t_cmb: 2.7255,
+        n_eff: 3.046,
+    };
+
+    fn assert_close(name: &str, value: f64, expected: f64, rtol: f64) {
+        assert!(
+            (value / expected - 1.).abs() < rtol,
+            "{} is {} against {}",
+            name,
+            value,
+            expected
+        );
+    }
+
+    #[test]
+    fn planck18_epochs() {
+        assert_close("z_eq", EARLY.z_equality(&PLANCK18), 3387., 5e-3);
+        // The Hu & Sugiyama fit is good to about 0.5 per cent.
+        let z_star = EARLY.z_recombination(&PLANCK18);
+        assert_close("z_*", z_star, 1089.8, 5e-3);
+        assert_close("r_*", EARLY.sound_horizon(&PLANCK18, z_star), 144.57, 5e-3);
+        // The Eisenstein & Hu fit puts the drag epoch about 40 too late, which lengthens r_drag
+        // by about 2.5 per cent.
+        let z_drag = EARLY.z_drag(&PLANCK18);
+        assert_close("z_drag", z_drag, 1060.0, 0.05);
+        assert_close(
+            "r_drag",
+            EARLY.sound_horizon(&PLANCK18, z_drag),
+            147.21,
+            0.03,
+        );
+    }
+
+    #[test]
+    fn planck18_distance_priors() {
+        // The compressed Planck 2018 likelihood of Chen, Huang & Wang (2019).
+        assert_close("R", EARLY.shift_parameter(&PLANCK18), 1.7495, 5e-3);
+        assert_close("l_A", EARLY.acoustic_scale(&PLANCK18), 302.1, 5e-3);
+    }
+}
@@ -0,0 +1,115 @@
+use cosmoxide::Cosmology;
+
+use crate::background::MAX_INVERSE_REDSHIFT;
+use crate::sky::{Frame, dot, lon_lat, unit_vector};
+
+/// Comoving cartesian position in Mpc of an object at a comoving distance in Mpc and J2000
+/// (RA, Dec) in degrees, with axes in the given frame.
+pub fn cartesian_at_distance(distance: f64, ra: f64, dec: f64, frame: Frame) -> [f64; 3] {
+    frame
+        .from_equatorial(unit_vector(ra, dec))
+        .map(|component| distance * component)
+}
+
+/// Comoving cartesian position in Mpc of an object at redshift z and J2000 (RA, Dec) in degrees.
+pub fn cartesian(cosmo: &Cosmology, z: f64, ra: f64, dec: f64, frame: Frame) -> [f64; 3] {
+    cartesian_at_distance(cosmo.comoving_distance(z), ra, dec, frame)
+}
+
+/// J2000 (RA, Dec) in degrees and redshift of a comoving cartesian position in Mpc. The redshift
+/// is NaN beyond the reach of the inverse comoving distance.
+pub fn sky_position(cosmo: &Cosmology, position: [f64; 3], frame: Frame) -> (f64, f64, f64) {
+    let distance = dot(position, position).sqrt();
+    let (ra, dec) = lon_lat(frame.to_equatorial(position));
+    let z = if distance <= cosmo.comoving_distance(MAX_INVERSE_REDSHIFT) {
+        cosmo.inverse_codist(distance)
+    } else {
+        f64::NAN
+    };
+    (ra, dec, z)
+}
+
+/// Separation of two comoving positions, split along and across the line of sight.
+#[derive(Debug, Clone, Copy)]
+pub struct Separation {
+    /// Comoving distance between the positions in Mpc.
+    pub comoving: f64,
+    /// Comoving separation across the line of sight, r_p, in Mpc.
+    pub projected: f64,
+    /// Comoving separation along the line of sight, π, in Mpc.
+    pub line_of_sight: f64,
+}
+
+/// Separation of two comoving positions, taking the line of sight through their midpoint.
+pub fn separation(a: [f64; 3], b: [f64; 3]) -> Separation {
+    let difference = [a[0] - b[0], a[1] - b[1], a[2] - b[2]];
+    let middle = [a[0] + b[0], a[1] + b[1], a[2] + b[2]];
+    let comoving = dot(difference, difference).sqrt();
+    let length = dot(middle, middle).sqrt();
+    let line_of_sight = if length > 0. {
+        (dot(difference, middle) / length).abs()
+    } else {
+        0.
+    };
+    Separation {
+        comoving,
+        projected: (comoving * comoving - line_of_sight * line_of_sight)
+            .max(0.)
+            .sqrt(),
+        line_of_sight,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const COSMO: Cosmology = Cosmology {
+        omega_m: 0.3,
+        omega_k: 0.,
+        omega_l: 0.7,
+        h0: 70.,
+    };
+
+    #[test]
+    fn sky_position_inverts_cartesian() {
+        for frame in [Frame::Equatorial, Frame::Galactic, Frame::Supergalactic] {
+            for (ra, dec, z) in [(10., -30., 0.05), (150.1, 2.2, 1.), (299.9, 75., 3.)] {
+                let position = cartesian(&COSMO, z, ra, dec, frame);
+                let (ra_back, dec_back, z_back) = sky_position(&COSMO, position, frame);
+                // The published rotation matrices are orthogonal to about ten digits.
+                assert!(
+                    (ra_back - ra).abs() < 1e-7 && (dec_back - dec).abs() < 1e-7,
+                    "({}, {}) against ({}, {}) in {:?}",
+                    ra_back,
+                    dec_back,
+                    ra,
+                    dec,
+                    frame
+                );
+                assert!((z_back - z).abs() < 1e-6 * z, "{} against {}", z_back, z);
+            }
+        }
+    }
+
+    #[test]
+    fn positions_beyond_the_inverse_have_no_redshift() {
+        let distance = 2. * COSMO.comoving_distance(MAX_INVERSE_REDSHIFT);
+        let position = cartesian_at_distance(distance, 10., 20., Frame::Equatorial);
+        assert!(sky_position(&COSMO, position, Frame::Equatorial).2.is_nan());
+    }
+
+    #[test]
+    fn separation_along_and_across_the_line_of_sight() {
+        let near = cartesian_at_distance(100., 10., 20., Frame::Equatorial);
+        let far = cartesian_at_distance(130., 10., 20., Frame::Equatorial);
+        let along = separation(near, far);
+        assert!((along.comoving - 30.).abs() < 1e-9);
+        assert!((along.line_of_sight - 30.).abs() < 1e-9);
+        assert!(along.projected < 1e-6);
+
+        let beside = separation([100., 1., 0.], [100., -1., 0.]);
+        assert!((beside.projected - 2.).abs() < 1e-12);
+        assert!(beside.line_of_sight < 1e-12);
+    }
+}
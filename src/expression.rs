@@ -0,0 +1,603 @@
+use std::f64::consts::{E, PI};
+
+use cosmoxide::Cosmology;
+
+/// Exponents of length (m), mass (kg), time (s) and angle (rad).
+type Dimension = [f64; 4];
+
+const DIMENSIONLESS: Dimension = [0., 0., 0., 0.];
+const LENGTH: Dimension = [1., 0., 0., 0.];
+const MASS: Dimension = [0., 1., 0., 0.];
+const TIME: Dimension = [0., 0., 1., 0.];
+const ANGLE: Dimension = [0., 0., 0., 1.];
+
+const METRE: f64 = 1.;
+const PARSEC: f64 = 3.085677581491367e16;
+const MEGAPARSEC: f64 = 1e6 * PARSEC;
+const YEAR: f64 = 3.15576e7;
+const GIGAYEAR: f64 = 1e9 * YEAR;
+const SOLAR_MASS: f64 = 1.988409870698051e30;
+const ARCSECOND: f64 = PI / 648000.;
+
+/// Named constants and units in SI.
+const CONSTANTS: &[(&str, f64, Dimension)] = &[
+    ("pi", PI, DIMENSIONLESS),
+    ("π", PI, DIMENSIONLESS),
+    ("e", E, DIMENSIONLESS),
+    ("c", 299792458., [1., 0., -1., 0.]),
+    ("G", 6.6743e-11, [3., -1., -2., 0.]),
+    ("m", METRE, LENGTH),
+    ("cm", 1e-2, LENGTH),
+    ("km", 1e3, LENGTH),
+    ("AU", 1.495978707e11, LENGTH),
+    ("ly", 9.4607304725808e15, LENGTH),
+    ("pc", PARSEC, LENGTH),
+    ("kpc", 1e3 * PARSEC, LENGTH),
+    ("Mpc", MEGAPARSEC, LENGTH),
+    ("Gpc", 1e9 * PARSEC, LENGTH),
+    ("s", 1., TIME),
+    ("yr", YEAR, TIME),
+    ("Myr", 1e6 * YEAR, TIME),
+    ("Gyr", GIGAYEAR, TIME),
+    ("kg", 1., MASS),
+    ("g", 1e-3, MASS),
+    ("M_sun", SOLAR_MASS, MASS),
+    ("rad", 1., ANGLE),
+    ("deg", PI / 180., ANGLE),
+    ("arcmin", 60. * ARCSECOND, ANGLE),
+    ("arcsec", ARCSECOND, ANGLE),
+];
+
+/// Units results are shown in when no unit is asked for. The first of a dimension giving a
+/// number of at least one is used.
+const DISPLAY_UNITS: &[(&str, f64, Dimension)] = &[
+    ("Mpc", MEGAPARSEC, LENGTH),
+    ("kpc", 1e3 * PARSEC, LENGTH),
+    ("pc", PARSEC, LENGTH),
+    ("Mpc²", MEGAPARSEC * MEGAPARSEC, [2., 0., 0., 0.]),
+    (
+        "Mpc³",
+        MEGAPARSEC * MEGAPARSEC * MEGAPARSEC,
+        [3., 0., 0., 0.],
+    ),
+    ("M_sun", SOLAR_MASS, MASS),
+    ("Gyr", GIGAYEAR, TIME),
+    ("km/s", 1e3, [1., 0., -1., 0.]),
+    ("km/s/Mpc", 1e3 / MEGAPARSEC, [0., 0., -1., 0.]),
+    ("arcsec", ARCSECOND, ANGLE),
+    ("kpc/arcsec", 1e3 * PARSEC / ARCSECOND, [1., 0., 0., -1.]),
+];
+
+/// Error for results like sqrt(-1) or 1/0, which have no value to show.
+const NOT_FINITE: &str = "The result is not a finite number.";
+
+/// A number with a physical dimension, held in SI units.
+#[derive(Debug, Clone, Copy)]
+struct Value {
+    number: f64,
+    dimension: Dimension,
+}
+
+impl Value {
+    fn new(number: f64, dimension: Dimension) -> Value {
+        Value { number, dimension }
+    }
+
+    fn is_dimensionless(&self) -> bool {
+        self.dimension == DIMENSIONLESS
+    }
+
+    /// The value with its angle dimension converted away in radians.
+    fn without_angle(&self) -> Value {
+        let mut dimension = self.dimension;
+        dimension[3] = 0.;
+        Value::new(self.number, dimension)
+    }
+}
+
+fn dimension_name(dimension: &Dimension) -> String {
+    let names = ["m", "kg", "s", "rad"];
+    let parts: Vec<String> = names
+        .iter()
+        .zip(dimension)
+        .filter(|(_, exponent)| **exponent != 0.)
+        .map(|(name, exponent)| {
+            if *exponent == 1. {
+                name.to_string()
+            } else {
+                format!("{}^{}", name, exponent)
+            }
+        })
+        .collect();
+    if parts.is_empty() {
+        "dimensionless".to_string()
+    } else {
+        parts.join(" ")
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Number(f64),
+    Identifier(String),
+    Operator(char),
+}
+
+/// Tokens of an expression, each with the index of the character it starts at.
+fn tokenise(input: &str) -> Result<Vec<(Token, usize)>, String> {
+    let characters: Vec<char> = input.chars().collect();
+    let mut tokens = Vec::new();
+    let mut i = 0;
+    while i < characters.len() {
+        let character = characters[i];
+        let start = i;
+        if character.is_whitespace() {
+            i += 1;
+        } else if character.is_ascii_digit() || character == '.' {
+            let start = i;
+            while i < characters.len() && (characters[i].is_ascii_digit() || characters[i] == '.') {
+                i += 1;
+            }
+            // Exponent, as in 1e-3.
+            if i < characters.len() && (characters[i] == 'e' || characters[i] == 'E') {
+                let sign = matches!(characters.get(i + 1), Some('-' | '+')) as usize;
+                if characters
+                    .get(i + 1 + sign)
+                    .is_some_and(|digit| digit.is_ascii_digit())
+                {
+                    i += 1 + sign;
+                    while i < characters.len() && characters[i].is_ascii_digit() {
+                        i += 1;
+                    }
+                }
+            }
+            let text: String = characters[start..i].iter().collect();
+            let number = text
+                .parse::<f64>()
+                .map_err(|_| format!("{:?} is not a valid number.", text))?;
+            tokens.push((Token::Number(number), start));
+        } else if character.is_alphabetic() || character == '_' {
+            let start = i;
+            while i < characters.len()
+                && (characters[i].is_alphabetic()
+                    || characters[i].is_ascii_digit()
+                    || characters[i] == '_')
+            {
+                i += 1;
+            }
+            tokens.push((
+                Token::Identifier(characters[start..i].iter().collect()),
+                start,
+            ));
+        } else {
+            let operator = match character {
+                '×' | '·' => '*',
+                '÷' => '/',
+                '−' => '-',
+                '*' if characters.get(i + 1) == Some(&'*') => {
+                    i += 1;
+                    '^'
+                }
+                '+' | '-' | '*' | '/' | '^' | '(' | ')' | ',' | '²' | '³' => character,
+                _ => return Err(format!("Unexpected character {:?}.", character)),
+            };
+            tokens.push((Token::Operator(operator), start));
+            i += 1;
+        }
+    }
+    Ok(tokens)
+}
+
+/// Product or, with divide, quotient of two values.
+fn multiply(left: Value, right: Value, divide: bool) -> Value {
+    let sign = if divide { -1. } else { 1. };
+    let mut dimension = left.dimension;
+    for (exponent, other) in dimension.iter_mut().zip(right.dimension) {
+        *exponent += sign * other;
+    }
+    let number = if divide {
+        left.number / right.number
+    } else {
+        left.number * right.number
+    };
+    Value::new(number, dimension)
+}
+
+/// Recursive descent evaluation of a tokenised expression.
+struct Parser<'a> {
+    tokens: Vec<Token>,
+    position: usize,
+    cosmo: &'a Cosmology,
+}
+
+impl Parser<'_> {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.position)
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.position).cloned();
+        self.position += 1;
+        token
+    }
+
+    fn expect(&mut self, operator: char) -> Result<(), String> {
+        match self.next() {
+            Some(Token::Operator(found)) if found == operator => Ok(()),
+            _ => Err(format!("Expected '{}'.", operator)),
+        }
+    }
+
+    fn expression(&mut self) -> Result<Value, String> {
+        let mut value = self.term()?;
+        while let Some(Token::Operator(operator @ ('+' | '-'))) = self.peek().cloned() {
+            self.position += 1;
+            let right = self.term()?;
+            if value.dimension != right.dimension {
+                return Err(format!(
+                    "Cannot add {} to {}.",
+                    dimension_name(&right.dimension),
+                    dimension_name(&value.dimension)
+                ));
+            }
+            value.number = if operator == '+' {
+                value.number + right.number
+            } else {
+                value.number - right.number
+            };
+        }
+        Ok(value)
+    }
+
+    fn term(&mut self) -> Result<Value, String> {
+        let mut value = self.product()?;
+        while let Some(Token::Operator(operator @ ('*' | '/'))) = self.peek().cloned() {
+            self.position += 1;
+            let right = self.product()?;
+            value = multiply(value, right, operator == '/');
+        }
+        Ok(value)
+    }
+
+    /// Juxtaposition, as in "3 arcsec" or "4 pi", multiplies and binds tighter than '*' and '/',
+    /// so "1 / 2 pi" is 1 / (2 pi) and "1 / 2 Mpc" is per two megaparsecs.
+    fn product(&mut self) -> Result<Value, String> {
+        let mut value = self.unary()?;
+        loop {
+            match self.peek() {
+                Some(Token::Identifier(name)) if name == "in" => return Ok(value),
+                Some(Token::Number(_))
+                | Some(Token::Identifier(_))
+                | Some(Token::Operator('(')) => {
+                    let right = self.power()?;
+                    value = multiply(value, right, false);
+                }
+                _ => return Ok(value),
+            }
+        }
+    }
+
+    fn unary(&mut self) -> Result<Value, String> {
+        match self.peek() {
+            Some(Token::Operator('-')) => {
+                self.position += 1;
+                let value = self.unary()?;
+                Ok(Value::new(-value.number, value.dimension))
+            }
+            Some(Token::Operator('+')) => {
+                self.position += 1;
+                self.unary()
+            }
+            _ => self.power(),
+        }
+    }
+
+    fn power(&mut self) -> Result<Value, String> {
+        let base = self.primary()?;
+        let exponent = match self.peek() {
+            Some(Token::Operator('^')) => {
+                self.position += 1;
+                let exponent = self.unary()?;
+                if !exponent.is_dimensionless() {
+                    return Err("Exponents must be dimensionless.".to_string());
+                }
+                exponent.number
+            }
+            Some(Token::Operator('²')) => {
+                self.position += 1;
+                2.
+            }
+            Some(Token::Operator('³')) => {
+                self.position += 1;
+                3.
+            }
+            _ => return Ok(base),
+        };
+        Ok(Value::new(
+            base.number.powf(exponent),
+            base.dimension.map(|d| d * exponent),
+        ))
+    }
+
+    fn primary(&mut self) -> Result<Value, String> {
+        match self.next() {
+            Some(Token::Number(number)) => Ok(Value::new(number, DIMENSIONLESS)),
+            Some(Token::Operator('(')) => {
+                let value = self.expression()?;
+                self.expect(')')?;
+                Ok(value)
+            }
+            Some(Token::Identifier(name)) => {
+                if let Some(Token::Operator('(')) = self.peek() {
+                    self.position += 1;
+                    let mut arguments = vec![self.expression()?];
+                    while let Some(Token::Operator(',')) = self.peek() {
+                        self.position += 1;
+                        arguments.push(self.expression()?);
+                    }
+                    self.expect(')')?;
+                    return self.call(&name, &arguments);
+                }
+                CONSTANTS
+                    .iter()
+                    .find(|(constant, _, _)| *constant == name)
+                    .map(|(_, number, dimension)| Value::new(*number, *dimension))
+                    .ok_or(format!("Unknown constant or unit {:?}.", name))
+            }
+            Some(token) => Err(format!("Unexpected {:?}.", token)),
+            None => Err("Unexpected end of expression.".to_string()),
+        }
+    }
+
+    fn call(&self, name: &str, arguments: &[Value]) -> Result<Value, String> {
+        let cosmo = self.cosmo;
+        let redshifts = || -> Result<Vec<f64>, String> {
+            arguments
+                .iter()
+                .map(|argument| {
+                    if argument.is_dimensionless() {
+                        Ok(argument.number)
+                    } else {
+                        Err(format!("{}() takes a dimensionless argument.", name))
+                    }
+                })
+                .collect()
+        };
+        let single = || -> Result<f64, String> {
+            match redshifts()?.as_slice() {
+                [z] => Ok(*z),
+                _ => Err(format!("{}() takes one argument.", name)),
+            }
+        };
+        let value = match name {
+            "codist" | "dc" => Value::new(cosmo.comoving_distance(single()?) * MEGAPARSEC, LENGTH),
+            "lumdist" | "dl" => {
+                Value::new(cosmo.luminosity_distance(single()?) * MEGAPARSEC, LENGTH)
+            }
+            "dm" => Value::new(
+                cosmo.comoving_transverse_distance(single()?) * MEGAPARSEC,
+                LENGTH,
+            ),
+            "angdist" | "da" => match redshifts()?.as_slice() {
+                [z] => Value::new(cosmo.angular_diameter_distance(*z) * MEGAPARSEC, LENGTH),
+                // Between two redshifts, which is valid for flat cosmologies.
+                [z1, z2] => Value::new(
+                    (cosmo.comoving_transverse_distance(*z2)
+                        - cosmo.comoving_transverse_distance(*z1))
+                        / (1. + z2)
+                        * MEGAPARSEC,
+                    LENGTH,
+                ),
+                _ => return Err("da() takes one or two redshifts.".to_string()),
+            },
+            "covol" => Value::new(
+                cosmo.comoving_volume(single()?) * MEGAPARSEC.powi(3),
+                [3., 0., 0., 0.],
+            ),
+            "distmod" | "mu" => Value::new(cosmo.distance_modulus(single()?), DIMENSIONLESS),
+            "age" => Value::new(cosmo.age(single()?) * GIGAYEAR, TIME),
+            "lookback" => Value::new(cosmo.look_back_time(single()?) * GIGAYEAR, TIME),
+            "H" | "hz" => Value::new(
+                cosmo.h_at_z(single()?) * 1e3 / MEGAPARSEC,
+                [0., 0., -1., 0.],
+            ),
+            "angscale" | "angscale_phys" => Value::new(
+                cosmo.kpc_per_arcsecond_physical(single()?) * 1e3 * PARSEC / ARCSECOND,
+                [1., 0., 0., -1.],
+            ),
+            "angscale_co" => Value::new(
+                cosmo.kpc_per_arcsecond_comoving(single()?) * 1e3 * PARSEC / ARCSECOND,
+                [1., 0., 0., -1.],
+            ),
+            "a" => Value::new(1. / (1. + single()?), DIMENSIONLESS),
+            "sin" | "cos" | "tan" => {
+                let [angle] = arguments else {
+                    return Err(format!("{}() takes one argument.", name));
+                };
+                if !angle.without_angle().is_dimensionless() {
+                    return Err(format!("{}() takes an angle.", name));
+                }
+                let number = match name {
+                    "sin" => angle.number.sin(),
+                    "cos" => angle.number.cos(),
+                    _ => angle.number.tan(),
+                };
+                Value::new(number, DIMENSIONLESS)
+            }
+            "sqrt" | "abs" => {
+                let [argument] = arguments else {
+                    return Err(format!("{}() takes one argument.", name));
+                };
+                if name == "abs" {
+                    Value::new(argument.number.abs(), argument.dimension)
+                } else {
+                    Value::new(argument.number.sqrt(), argument.dimension.map(|d| d / 2.))
+                }
+            }
+            "exp" => Value::new(single()?.exp(), DIMENSIONLESS),
+            "ln" => Value::new(single()?.ln(), DIMENSIONLESS),
+            "log10" => Value::new(single()?.log10(), DIMENSIONLESS),
+            _ => return Err(format!("Unknown function {:?}.", name)),
+        };
+        Ok(value)
+    }
+}
+
+/// Parses an expression from tokens, stopping at a top level "in". Returns the value and the
+/// position of the "in", if there is one.
+fn parse(tokens: Vec<Token>, cosmo: &Cosmology) -> Result<(Value, Option<usize>), String> {
+    let mut parser = Parser {
+        tokens,
+        position: 0,
+        cosmo,
+    };
+    let value = parser.expression()?;
+    match parser.peek() {
+        None => Ok((value, None)),
+        Some(Token::Identifier(name)) if name == "in" => Ok((value, Some(parser.position))),
+        Some(token) => Err(format!("Unexpected {:?}.", token)),
+    }
+}
+
+/// Evaluates an expression against a cosmology, returning the result and its unit. A trailing
+/// "in <unit>" converts to that unit, otherwise a unit is chosen from the dimension.
+pub fn evaluate(input: &str, cosmo: &Cosmology) -> Result<(f64, String), String> {
+    let (tokens, starts): (Vec<Token>, Vec<usize>) = tokenise(input)?.into_iter().unzip();
+    let (value, conversion) = parse(tokens.clone(), cosmo)?;
+    if !value.number.is_finite() {
+        return Err(NOT_FINITE.to_string());
+    }
+    if let Some(position) = conversion {
+        if position + 1 == tokens.len() {
+            return Err("Expected a unit after 'in'.".to_string());
+        }
+        let target: String = input.chars().skip(starts[position + 1]).collect();
+        let target = target.trim_end();
+        let unit = match parse(tokens[position + 1..].to_vec(), cosmo)? {
+            (unit, None) => unit,
+            (_, Some(_)) => return Err("Only one 'in' conversion is allowed.".to_string()),
+        };
+        let (value, unit) = if value.dimension == unit.dimension {
+            (value, unit)
+        } else {
+            (value.without_angle(), unit.without_angle())
+        };
+        if value.dimension != unit.dimension {
+            return Err(format!(
+                "Cannot convert {} to {}.",
+                dimension_name(&value.dimension),
+                target
+            ));
+        }
+        let number = value.number / unit.number;
+        if !number.is_finite() {
+            return Err(NOT_FINITE.to_string());
+        }
+        return Ok((number, target.to_string()));
+    }
+    if value.is_dimensionless() {
+        return Ok((value.number, String::new()));
+    }
+    for candidate in [value, value.without_angle()] {
+        let units: Vec<_> = DISPLAY_UNITS
+            .iter()
+            .filter(|(_, _, dimension)| *dimension == candidate.dimension)
+            .collect();
+        if let Some((name, factor, _)) = units
+            .iter()
+            .find(|(_, factor, _)| (candidate.number / factor).abs() >= 1.)
+            .or(units.last())
+        {
+            return Ok((candidate.number / factor, name.to_string()));
+        }
+    }
+    let value = value.without_angle();
+    Ok((value.number, dimension_name(&value.dimension)))
+}
+
+/// Fixed notation for everyday magnitudes and scientific notation otherwise.
+pub fn format_number(value: f64) -> String {
+    let magnitude = value.abs();
+    if magnitude == 0. || (1e-3..1e7).contains(&magnitude) {
+        format!("{:.4}", value)
+    } else {
+        format!("{:.4e}", value)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const COSMO: Cosmology = Cosmology {
+        omega_m: 0.3,
+        omega_k: 0.,
+        omega_l: 0.7,
+        h0: 70.,
+    };
+
+    fn value(input: &str) -> f64 {
+        evaluate(input, &COSMO).unwrap().0
+    }
+
+    fn close(a: f64, b: f64) -> bool {
+        (a - b).abs() <= 1e-12 * b.abs()
+    }
+
+    #[test]
+    fn precedence() {
+        assert!(close(value("1 + 2 * 3"), 7.));
+        assert!(close(value("2 ^ 3 ^ 2"), 512.));
+        assert!(close(value("-2 ^ 2"), -4.));
+        assert!(close(value("2 * (1 + 2)²"), 18.));
+        assert!(close(value("8 / 2 / 2"), 2.));
+        assert!(close(value("2 - -1"), 3.));
+    }
+
+    #[test]
+    fn juxtaposition_binds_tighter_than_division() {
+        assert!(close(value("1 / 2 pi"), 1. / (2. * PI)));
+        assert!(close(value("4 pi 2"), 8. * PI));
+        assert!(close(value("2 pi / 2 pi"), 1.));
+        assert!(close(value("2 3²"), 18.));
+    }
+
+    #[test]
+    fn units_are_tracked_and_chosen_for_display() {
+        assert_eq!(evaluate("3 kpc + 2 kpc", &COSMO).unwrap().1, "kpc");
+        assert_eq!(evaluate("1 Mpc^3", &COSMO).unwrap().1, "Mpc³");
+        assert_eq!(evaluate("c / 1 Mpc", &COSMO).unwrap().1, "km/s/Mpc");
+        let (hubble, unit) = evaluate("H(0)", &COSMO).unwrap();
+        assert_eq!(unit, "km/s/Mpc");
+        assert!(close(hubble, 70.));
+        assert!(evaluate("1 Mpc + 1 Gyr", &COSMO).is_err());
+        assert!(evaluate("2 ^ (1 m)", &COSMO).is_err());
+    }
+
+    #[test]
+    fn conversion() {
+        assert!(close(value("1 Mpc in kpc"), 1e3));
+        assert!(close(value("1 pc in AU"), PARSEC / 1.495978707e11));
+        assert!(close(value("1 / 2 pi in rad"), 1. / (2. * PI)));
+        assert!(close(value("(1 deg) in arcsec"), 3600.));
+        assert!(close(value("1 deg in rad"), PI / 180.));
+        assert_eq!(evaluate("1 Mpc in   km", &COSMO).unwrap().1, "km");
+        assert_eq!(evaluate("c in km/s", &COSMO).unwrap().1, "km/s");
+        assert!(close(value("1/H(0) in Gyr"), MEGAPARSEC / 70e3 / GIGAYEAR));
+        assert!(evaluate("1 Mpc in Gyr", &COSMO).is_err());
+        assert!(evaluate("1 Mpc in", &COSMO).is_err());
+        assert!(evaluate("1 Mpc in km in m", &COSMO).is_err());
+    }
+
+    #[test]
+    fn results_must_be_finite() {
+        for input in ["sqrt(-1)", "1/0", "-1/0 Mpc", "0/0", "1 Mpc in 0 km"] {
+            assert_eq!(
+                evaluate(input, &COSMO),
+                Err(NOT_FINITE.to_string()),
+                "{}",
+                input
+            );
+        }
+    }
+}
@@ -0,0 +1,241 @@
+use std::path::Path;
+
+use crate::plot::{Axis, Series};
+
+const WIDTH: f64 = 640.;
+const HEIGHT: f64 = 480.;
+
+/// Plotting area margins in pixels: left, right, top, bottom.
+const MARGINS: (f64, f64, f64, f64) = (80., 20., 20., 60.);
+
+/// Resolution multiplier of PNG figures over the SVG size.
+const PNG_SCALE: f32 = 3.;
+
+const FONT: &str = "DejaVu Sans, Helvetica, Arial, sans-serif";
+
+/// Line colours given to successive curves.
+const PALETTE: [&str; 6] = [
+    "#1f77b4", "#d62728", "#2ca02c", "#ff7f0e", "#9467bd", "#8c564b",
+];
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
+
+/// Tick positions and their SVG labels. Linear axes step through 1, 2 or 5 times a power of ten
+/// and logarithmic axes through powers of ten.
+fn ticks(axis: &Axis) -> Vec<(f64, String)> {
+    if axis.log {
+        let first = axis.min.log10().ceil() as i32;
+        let last = axis.max.log10().floor() as i32;
+        return (first..=last)
+            .map(|power| {
+                let label = match power {
+                    0 => "1".to_string(),
+                    1 => "10".to_string(),
+                    _ => format!(r#"10<tspan dy="-7" font-size="10">{}</tspan>"#, power),
+                };
+                (10_f64.powi(power), label)
+            })
+            .collect();
+    }
+    let step = tick_step(axis.max - axis.min);
+    let decimals = (-step.log10().floor()).max(0.) as usize;
+    let first = (axis.min / step).ceil() as i64;
+    let last = (axis.max / step + 1e-9).floor() as i64;
+    (first..=last)
+        .map(|i| {
+            let value = i as f64 * step;
+            (value, format!("{:.*}", decimals, value))
+        })
+        .collect()
+}
+
+fn tick_step(range: f64) -> f64 {
+    let raw = range / 5.;
+    let magnitude = 10_f64.powf(raw.log10().floor());
+    let normalised = raw / magnitude;
+    let nice = if normalised < 1.5 {
+        1.
+    } else if normalised < 3. {
+        2.
+    } else if normalised < 7. {
+        5.
+    } else {
+        10.
+    };
+    nice * magnitude
+}
+
+/// Widens an axis out to the nearest ticks so that curves don't end between them.
+fn rounded(axis: &Axis) -> Axis {
+    if axis.log {
+        Axis {
+            min: 10_f64.powf(axis.min.log10().floor()),
+            max: 10_f64.powf(axis.max.log10().ceil()),
+            log: true,
+        }
+    } else {
+        let step = tick_step(axis.max - axis.min);
+        Axis {
+            min: (axis.min / step).floor() * step,
+            max: (axis.max / step).ceil() * step,
+            log: false,
+        }
+    }
+}
+
+/// SVG figure of the series with labelled axes and a legend.
+pub fn svg(series: &[Series], x: &Axis, y: &Axis, x_label: &str, y_label: &str) -> String {
+    let y = rounded(y);
+    let (left, right, top, bottom) = MARGINS;
+    let (plot_width, plot_height) = (WIDTH - left - right, HEIGHT - top - bottom);
+    let to_x = |z: f64| x.position(z).map(|f| left + f * plot_width);
+    let to_y = |v: f64| y.position(v).map(|f| top + (1. - f) * plot_height);
+
+    let mut svg = format!(
+        r#"<svg xmlns="http://www.w3.org/2000/svg" width="{WIDTH}" height="{HEIGHT}" viewBox="0 0 {WIDTH} {HEIGHT}" font-family="{FONT}" font-size="14">
+<rect width="{WIDTH}" height="{HEIGHT}" fill="white"/>
+"#
+    );
+
+    // Grid lines and tick labels.
+    for (value, label) in ticks(x) {
+        let Some(px) = to_x(value) else { continue };
+        svg.push_str(&format!(
+            r##"<line x1="{px:.2}" y1="{top}" x2="{px:.2}" y2="{:.2}" stroke="#dddddd" stroke-width="0.8"/>
+<text x="{px:.2}" y="{:.2}" text-anchor="middle">{label}</text>
+"##,
+            top + plot_height,
+            top + plot_height + 20.
+        ));
+    }
+    for (value, label) in ticks(&y) {
+        let Some(py) = to_y(value) else { continue };
+        svg.push_str(&format!(
+            r##"<line x1="{left}" y1="{py:.2}" x2="{:.2}" y2="{py:.2}" stroke="#dddddd" stroke-width="0.8"/>
+<text x="{:.2}" y="{:.2}" text-anchor="end">{label}</text>
+"##,
+            left + plot_width,
+            left - 8.,
+            py + 5.
+        ));
+    }
+
+    // Curves, broken wherever a point can't be shown.
+    svg.push_str(&format!(
+        r#"<clipPath id="plot-area"><rect x="{left}" y="{top}" width="{plot_width}" height="{plot_height}"/></clipPath>
+"#
+    ));
+    let mut drawn = Vec::new();
+    for (index, curve) in series.iter().enumerate() {
+        let colour = PALETTE[index % PALETTE.len()];
+        let mut segments: Vec<Vec<(f64, f64)>> = vec![Vec::new()];
+        for (z, value) in &curve.points {
+            match (to_x(*z), to_y(*value)) {
+                (Some(px), Some(py)) => segments.last_mut().unwrap().push((px, py)),
+                _ => segments.push(Vec::new()),
+            }
+        }
+        for segment in segments.iter().filter(|segment| segment.len() > 1) {
+            drawn.extend(segment.iter().copied());
+            let points: Vec<String> = segment
+                .iter()
+                .map(|(px, py)| format!("{:.2},{:.2}", px, py))
+                .collect();
+            svg.push_str(&format!(
+                r#"<polyline points="{}" fill="none" stroke="{colour}" stroke-width="2" stroke-linejoin="round" clip-path="url(#plot-area)"/>
+"#,
+                points.join(" ")
+            ));
+        }
+    }
+
+    // Frame, axis labels and legend.
+    svg.push_str(&format!(
+        r#"<rect x="{left}" y="{top}" width="{plot_width}" height="{plot_height}" fill="none" stroke="black" stroke-width="1"/>
+<text x="{:.2}" y="{:.2}" text-anchor="middle" font-size="16">{}</text>
+<text x="{:.2}" y="{:.2}" text-anchor="middle" font-size="16" transform="rotate(-90 {:.2} {:.2})">{}</text>
+"#,
+        left + plot_width / 2.,
+        HEIGHT - 15.,
+        escape(x_label),
+        left - 60.,
+        top + plot_height / 2.,
+        left - 60.,
+        top + plot_height / 2.,
+        escape(y_label)
+    ));
+    if series.len() > 1 {
+        let longest = series
+            .iter()
+            .map(|curve| curve.label.chars().count())
+            .max()
+            .unwrap_or(0);
+        let (box_width, box_height) = (50. + 7.5 * longest as f64, 8. + 20. * series.len() as f64);
+        // Place the legend in whichever corner covers the fewest points of the curves.
+        let corners = [
+            (left + 6., top + 6.),
+            (left + plot_width - 6. - box_width, top + 6.),
+            (
+                left + plot_width - 6. - box_width,
+                top + plot_height - 6. - box_height,
+            ),
+            (left + 6., top + plot_height - 6. - box_height),
+        ];
+        let covered = |(bx, by): (f64, f64)| {
+            drawn
+                .iter()
+                .filter(|(px, py)| {
+                    (bx..bx + box_width).contains(px) && (by..by + box_height).contains(py)
+                })
+                .count()
+        };
+        let (bx, by) = corners
+            .into_iter()
+            .min_by_key(|corner| covered(*corner))
+            .unwrap();
+        svg.push_str(&format!(
+            r##"<rect x="{bx:.2}" y="{by:.2}" width="{box_width:.2}" height="{box_height:.2}" fill="white" stroke="#cccccc"/>
+"##
+        ));
+        for (index, curve) in series.iter().enumerate() {
+            let colour = PALETTE[index % PALETTE.len()];
+            let ly = by + 14. + 20. * index as f64;
+            svg.push_str(&format!(
+                r#"<line x1="{:.2}" y1="{ly:.2}" x2="{:.2}" y2="{ly:.2}" stroke="{colour}" stroke-width="2"/>
+<text x="{:.2}" y="{:.2}">{}</text>
+"#,
+                bx + 6.,
+                bx + 31.,
+                bx + 38.,
+                ly + 5.,
+                escape(&curve.label)
+            ));
+        }
+    }
+    svg.push_str("</svg>\n");
+    svg
+}
+
+/// Rasterises an SVG figure to a PNG file, using the system fonts for text.
+pub fn write_png(svg: &str, path: &Path) -> Result<(), String> {
+    let mut options = resvg::usvg::Options::default();
+    options.fontdb_mut().load_system_fonts();
+    let tree = resvg::usvg::Tree::from_str(svg, &options).map_err(|error| error.to_string())?;
+    let size = tree
+        .size()
+        .to_int_size()
+        .scale_by(PNG_SCALE)
+        .ok_or("Figure is too large.")?;
+    let mut pixmap = resvg::tiny_skia::Pixmap::new(size.width(), size.height())
+        .ok_or("Could not allocate the figure.")?;
+    resvg::render(
+        &tree,
+        resvg::tiny_skia::Transform::from_scale(PNG_SCALE, PNG_SCALE),
+        &mut pixmap.as_mut(),
+    );
+    pixmap.save_png(path).map_err(|error| error.to_string())
+}
@@ -0,0 +1,425 @@
+use std::path::Path;
+
+const BLOCK: usize = 2880;
+const CARD: usize = 80;
+
+fn padded(size: usize) -> usize {
+    size.div_ceil(BLOCK) * BLOCK
+}
+
+/// Header cards of an HDU, excluding END, as their raw bytes so that cards with non-ASCII text
+/// are written back unchanged.
+#[derive(Debug, Clone)]
+pub struct Header {
+    cards: Vec<Vec<u8>>,
+}
+
+impl Header {
+    fn keyword(card: &[u8]) -> &str {
+        std::str::from_utf8(&card[..card.len().min(8)])
+            .unwrap_or("")
+            .trim_end()
+    }
+
+    /// Value of a keyword with strings unquoted and comments removed.
+    pub fn value(&self, keyword: &str) -> Option<String> {
+        let card = self
+            .cards
+            .iter()
+            .find(|card| Header::keyword(card) == keyword && card.get(8..10) == Some(b"= "))?;
+        let value = String::from_utf8_lossy(&card[10..]);
+        let value = value.trim_start();
+        if let Some(quoted) = value.strip_prefix('\'') {
+            let mut string = String::new();
+            let mut characters = quoted.chars().peekable();
+            while let Some(character) = characters.next() {
+                if character == '\'' {
+                    if characters.peek() == Some(&'\'') {
+                        characters.next();
+                    } else {
+                        break;
+                    }
+                }
+                string.push(character);
+            }
+            Some(string.trim_end().to_string())
+        } else {
+            Some(value.split('/').next().unwrap_or("").trim().to_string())
+        }
+    }
+
+    fn integer(&self, keyword: &str) -> Result<i64, String> {
+        self.value(keyword)
+            .and_then(|value| value.parse::<i64>().ok())
+            .ok_or(format!("Missing or invalid {} keyword.", keyword))
+    }
+
+    fn float(&self, keyword: &str, default: f64) -> f64 {
+        self.value(keyword)
+            .and_then(|value| value.replace('D', "E").parse::<f64>().ok())
+            .unwrap_or(default)
+    }
+
+    /// Replaces a keyword's card, or adds it at the end of the header, with an already formatted
+    /// value field.
+    fn set_field(&mut self, keyword: &str, field: &str) {
+        let card = format!("{:<80}", format!("{:<8}= {}", keyword, field)).into_bytes();
+        match self
+            .cards
+            .iter_mut()
+            .find(|card| Header::keyword(card) == keyword)
+        {
+            Some(existing) => *existing = card,
+            None => self.cards.push(card),
+        }
+    }
+
+    fn set(&mut self, keyword: &str, value: &str) {
+        self.set_field(keyword, &format!("{:>20}", value));
+    }
+
+    fn set_string(&mut self, keyword: &str, value: &str) {
+        self.set_field(keyword, &format!("'{:<8}'", value.replace('\'', "''")));
+    }
+
+    fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes: Vec<u8> = self
+            .cards
+            .iter()
+            .flat_map(|card| {
+                card.iter()
+                    .copied()
+                    .chain(std::iter::repeat(b' '))
+                    .take(CARD)
+            })
+            .collect();
+        bytes.extend(format!("{:<80}", "END").bytes());
+        bytes.resize(padded(bytes.len()), b' ');
+        bytes
+    }
+}
+
+/// A header and its data unit, without padding.
+#[derive(Debug, Clone)]
+pub struct Hdu {
+    pub header: Header,
+    data: Vec<u8>,
+}
+
+/// A column of a binary table.
+#[derive(Debug, Clone)]
+pub struct Column {
+    pub name: String,
+    code: char,
+    repeat: usize,
+    offset: usize,
+    scale: f64,
+    zero: f64,
+    null: Option<i64>,
+}
+
+impl Column {
+    fn width(code: char, repeat: usize) -> Result<usize, String> {
+        Ok(match code {
+            'L' | 'B' | 'A' => repeat,
+            'X' => repeat.div_ceil(8),
+            'I' => 2 * repeat,
+            'J' | 'E' => 4 * repeat,
+            'K' | 'D' | 'C' | 'P' => 8 * repeat,
+            'M' | 'Q' => 16 * repeat,
+            _ => return Err(format!("Unknown column format code {:?}.", code)),
+        })
+    }
+
+    /// Value in row bytes as a float, applying the scaling and mapping nulls to NaN.
+    fn read(&self, row: &[u8]) -> Result<f64, String> {
+        let bytes = &row[self.offset..];
+        let raw = |n: usize| -> [u8; 8] {
+            let mut buffer = [0; 8];
+            buffer[..n].copy_from_slice(&bytes[..n]);
+            buffer
+        };
+        let (value, integer) = match self.code {
+            'B' => (bytes[0] as f64, Some(bytes[0] as i64)),
+            'I' => {
+                let v = i16::from_be_bytes([bytes[0], bytes[1]]);
+                (v as f64, Some(v as i64))
+            }
+            'J' => {
+                let v = i32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]);
+                (v as f64, Some(v as i64))
+            }
+            'K' => {
+                let v = i64::from_be_bytes(raw(8));
+                (v as f64, Some(v))
+            }
+            'E' => (
+                f32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]) as f64,
+                None,
+            ),
+            'D' => (f64::from_be_bytes(raw(8)), None),
+            _ => {
+                return Err(format!(
+                    "Column {} is not numeric (format {}).",
+                    self.name, self.code
+                ));
+            }
+        };
+        if integer.is_some() && integer == self.null {
+            return Ok(f64::NAN);
+        }
+        Ok(self.zero + self.scale * value)
+    }
+}
+
+impl Hdu {
+    pub fn is_bintable(&self) -> bool {
+        self.header.value("XTENSION").as_deref() == Some("BINTABLE")
+    }
+
+    fn row_bytes(&self) -> Result<usize, String> {
+        Ok(self.header.integer("NAXIS1")? as usize)
+    }
+
+    pub fn rows(&self) -> Result<usize, String> {
+        Ok(self.header.integer("NAXIS2")? as usize)
+    }
+
+    pub fn columns(&self) -> Result<Vec<Column>, String> {
+        let fields = self.header.integer("TFIELDS")? as usize;
+        let mut offset = 0;
+        let mut columns = Vec::with_capacity(fields);
+        for n in 1..=fields {
+            let format = self
+                .header
+                .value(&format!("TFORM{}", n))
+                .ok_or(format!("Missing TFORM{} keyword.", n))?;
+            let digits: String = format.chars().take_while(|c| c.is_ascii_digit()).collect();
+            let repeat = if digits.is_empty() {
+                1
+            } else {
+                digits.parse::<usize>().unwrap()
+            };
+            let code = format[digits.len()..]
+                .chars()
+                .next()
+                .ok_or(format!("Invalid TFORM{} {:?}.", n, format))?;
+            columns.push(Column {
+                name: self
+                    .header
+                    .value(&format!("TTYPE{}", n))
+                    .unwrap_or(format!("col{}", n)),
+                code,
+                repeat,
+                offset,
+                scale: self.header.float(&format!("TSCAL{}", n), 1.),
+                zero: self.header.float(&format!("TZERO{}", n), 0.),
+                null: self
+                    .header
+                    .value(&format!("TNULL{}", n))
+                    .and_then(|value| value.parse().ok()),
+            });
+            offset += Column::width(code, repeat)?;
+        }
+        Ok(columns)
+    }
+
+    /// Values of a scalar numeric column, matched case-insensitively by name.
+    pub fn column_values(&self, name: &str) -> Result<Vec<f64>, String> {
+        let column = self
+            .columns()?
+            .into_iter()
+            .find(|column| column.name.eq_ignore_ascii_case(name))
+            .ok_or(format!("No column named {:?}.", name))?;
+        if column.repeat != 1 {
+            return Err(format!("Column {} is not a scalar.", column.name));
+        }
+        let row_bytes = self.row_bytes()?;
+        (0..self.rows()?)
+            .map(|row| column.read(&self.data[row * row_bytes..(row + 1) * row_bytes]))
+            .collect()
+    }
+
+    /// Appends double precision columns given as (name, unit, values) to a binary table.
+    pub fn append_columns(&mut self, columns: &[(&str, &str, Vec<f64>)]) -> Result<(), String> {
+        let row_bytes = self.row_bytes()?;
+        let rows = self.rows()?;
+        let fields = self.header.integer("TFIELDS")? as usize;
+        let added = 8 * columns.len();
+
+        let mut data = Vec::with_capacity(self.data.len() + added * rows);
+        for row in 0..rows {
+            data.extend_from_slice(&self.data[row * row_bytes..(row + 1) * row_bytes]);
+            for (_, _, values) in columns {
+                data.extend_from_slice(&values[row].to_be_bytes());
+            }
+        }
+        // The heap follows the table unchanged.
+        data.extend_from_slice(&self.data[row_bytes * rows..]);
+        self.data = data;
+
+        self.header.set("NAXIS1", &(row_bytes + added).to_string());
+        self.header
+            .set("TFIELDS", &(fields + columns.len()).to_string());
+        if let Ok(heap) = self.header.integer("THEAP") {
+            self.header
+                .set("THEAP", &(heap as usize + added * rows).to_string());
+        }
+        for (i, (name, unit, _)) in columns.iter().enumerate() {
+            let n = fields + i + 1;
+            self.header.set_string(&format!("TTYPE{}", n), name);
+            self.header.set_string(&format!("TFORM{}", n), "D");
+            if !unit.is_empty() {
+                self.header.set_string(&format!("TUNIT{}", n), unit);
+            }
+        }
+        Ok(())
+    }
+}
+
+fn parse_header(bytes: &[u8], start: usize) -> Result<(Header, usize), String> {
+    let mut cards = Vec::new();
+    let mut offset = start;
+    loop {
+        let card = bytes
+            .get(offset..offset + CARD)
+            .ok_or("Header has no END card.")?;
+        offset += CARD;
+        if Header::keyword(card) == "END" {
+            break;
+        }
+        cards.push(card.to_vec());
+    }
+    Ok((Header { cards }, start + padded(offset - start)))
+}
+
+fn data_size(header: &Header) -> Result<usize, String> {
+    let axes = header.integer("NAXIS")?;
+    if axes == 0 {
+        return Ok(0);
+    }
+    let mut elements = 1;
+    for n in 1..=axes {
+        elements *= header.integer(&format!("NAXIS{}", n))? as usize;
+    }
+    let bits = header.integer("BITPIX")?.unsigned_abs() as usize;
+    let parameters = header.integer("PCOUNT").unwrap_or(0) as usize;
+    let groups = header.integer("GCOUNT").unwrap_or(1) as usize;
+    Ok(bits / 8 * groups * (parameters + elements))
+}
+
+pub fn read(path: &Path) -> Result<Vec<Hdu>, String> {
+    let bytes = std::fs::read(path).map_err(|error| error.to_string())?;
+    let mut hdus = Vec::new();
+    let mut offset = 0;
+    while offset + BLOCK <= bytes.len() {
+        let (header, start) = parse_header(&bytes, offset)?;
+        let size = data_size(&header)?;
+        let data = bytes
+            .get(start..start + size)
+            .ok_or("File ends before the data does.")?
+            .to_vec();
+        hdus.push(Hdu { header, data });
+        offset = start + padded(size);
+    }
+    if hdus.is_empty() {
+        return Err("Not a FITS file.".to_string());
+    }
+    Ok(hdus)
+}
+
+pub fn write(path: &Path, hdus: &[Hdu]) -> Result<(), String> {
+    let mut bytes = Vec::new();
+    for hdu in hdus {
+        bytes.extend(hdu.header.to_bytes());
+        bytes.extend_from_slice(&hdu.data);
+        bytes.resize(padded(bytes.len()), 0);
+    }
+    std::fs::write(path, bytes).map_err(|error| error.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn header(cards: &[(&str, &str)]) -> Header {
+        let mut header = Header { cards: Vec::new() };
+        for (keyword, value) in cards {
+            match value.strip_prefix('\'') {
+                Some(string) => header.set_string(keyword, string),
+                None => header.set(keyword, value),
+            }
+        }
+        header
+    }
+
+    #[test]
+    fn appended_columns_round_trip() {
+        let primary = Hdu {
+            header: header(&[
+                ("SIMPLE", "T"),
+                ("BITPIX", "8"),
+                ("NAXIS", "0"),
+                ("EXTEND", "T"),
+            ]),
+            data: Vec::new(),
+        };
+        let mut table_header = header(&[
+            ("XTENSION", "'BINTABLE"),
+            ("BITPIX", "8"),
+            ("NAXIS", "2"),
+            ("NAXIS1", "6"),
+            ("NAXIS2", "3"),
+            ("PCOUNT", "0"),
+            ("GCOUNT", "1"),
+            ("TFIELDS", "2"),
+            ("TTYPE1", "'Z"),
+            ("TFORM1", "'E"),
+            ("TTYPE2", "'ID"),
+            ("TFORM2", "'I"),
+            ("TNULL2", "-1"),
+        ]);
+        // A Latin-1 card, which isn't valid UTF-8, has to survive unchanged.
+        let mut latin = b"OBSERVER= 'Jos\xe9'".to_vec();
+        latin.resize(CARD, b' ');
+        table_header.cards.push(latin.clone());
+        let redshifts = [0.1_f32, 0.5, 2.];
+        let mut data = Vec::new();
+        for (z, id) in redshifts.iter().zip([0_i16, 1, -1]) {
+            data.extend_from_slice(&z.to_be_bytes());
+            data.extend_from_slice(&id.to_be_bytes());
+        }
+        let mut table = Hdu {
+            header: table_header,
+            data,
+        };
+        let distances = vec![418.4, 1888.6, 5179.9];
+        table
+            .append_columns(&[("codist", "Mpc", distances.clone())])
+            .unwrap();
+
+        let path = std::env::temp_dir().join(format!("cosmo-fits-{}.fits", std::process::id()));
+        write(&path, &[primary, table]).unwrap();
+        let hdus = read(&path);
+        std::fs::remove_file(&path).unwrap();
+        let hdus = hdus.unwrap();
+
+        assert_eq!(hdus.len(), 2);
+        let table = &hdus[1];
+        assert!(table.is_bintable());
+        assert_eq!(table.rows(), Ok(3));
+        assert_eq!(table.header.value("NAXIS1").as_deref(), Some("14"));
+        assert_eq!(table.header.value("TUNIT3").as_deref(), Some("Mpc"));
+        assert_eq!(
+            table.header.value("OBSERVER").as_deref(),
+            Some("Jos\u{fffd}")
+        );
+        assert!(table.header.cards.contains(&latin));
+        let read_redshifts: Vec<f64> = redshifts.iter().map(|z| *z as f64).collect();
+        assert_eq!(table.column_values("z"), Ok(read_redshifts));
+        assert_eq!(table.column_values("codist"), Ok(distances));
+        let ids = table.column_values("ID").unwrap();
+        assert_eq!(ids[..2], [0., 1.]);
+        assert!(ids[2].is_nan());
+    }
+}
@@ -0,0 +1,113 @@
+use cosmoxide::Cosmology;
+
+use crate::background::efunc;
+use crate::numerics::simpson;
+
+const INTEGRATION_STEPS: usize = 2000;
+
+/// Growth index γ of the approximation f(z) ≈ Ω_m(z)^γ for a cosmological constant.
+pub const GROWTH_INDEX: f64 = 0.55;
+
+/// ∫_0^a da' / (a' E(a'))³, written so the integrand is finite at a' = 0.
+fn growth_integral(cosmo: &Cosmology, a: f64) -> f64 {
+    let integrand = |x: f64| {
+        (cosmo.omega_m + cosmo.omega_k * x + cosmo.omega_l * x.powi(3)).powf(-1.5) * x.powf(1.5)
+    };
+    simpson(integrand, 0., a, INTEGRATION_STEPS)
+}
+
+/// Linear growth factor normalised so that D(a) → a at early times (Heath 1977).
+pub fn growth_factor_early(cosmo: &Cosmology, z: f64) -> f64 {
+    let a = 1. / (1. + z);
+    2.5 * cosmo.omega_m * efunc(cosmo, z) * growth_integral(cosmo, a)
+}
+
+/// Linear growth factor normalised so that D(0) = 1.
+pub fn growth_factor(cosmo: &Cosmology, z: f64) -> f64 {
+    growth_factor_early(cosmo, z) / growth_factor_early(cosmo, 0.)
+}
+
+/// Matter density parameter Ω_m(z) at redshift z.
+pub fn omega_m_at_z(cosmo: &Cosmology, z: f64) -> f64 {
+    cosmo.omega_m * (1. + z).powi(3) / efunc(cosmo, z).powi(2)
+}
+
+/// Linear growth rate f = dlnD/dlna.
+pub fn growth_rate(cosmo: &Cosmology, z: f64) -> f64 {
+    let a = 1. / (1. + z);
+    let e = efunc(cosmo, z);
+    let dln_e_dln_a =
+        -(3. * cosmo.omega_m / a.powi(3) + 2. * cosmo.omega_k / a.powi(2)) / (2. * e * e);
+    dln_e_dln_a + 1. / (a * a * e.powi(3) * growth_integral(cosmo, a))
+}
+
+/// Growth rate from the γ-parameterisation f ≈ Ω_m(z)^γ.
+pub fn growth_rate_approx(cosmo: &Cosmology, z: f64) -> f64 {
+    omega_m_at_z(cosmo, z).powf(GROWTH_INDEX)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const COSMO: Cosmology = Cosmology {
+        omega_m: 0.3,
+        omega_k: 0.,
+        omega_l: 0.7,
+        h0: 70.,
+    };
+
+    const EINSTEIN_DE_SITTER: Cosmology = Cosmology {
+        omega_m: 1.,
+        omega_k: 0.,
+        omega_l: 0.,
+        h0: 70.,
+    };
+
+    #[test]
+    fn einstein_de_sitter_grows_as_scale_factor() {
+        for z in [0_f64, 0.5, 2., 10.] {
+            assert!((growth_factor(&EINSTEIN_DE_SITTER, z) * (1. + z) - 1.).abs() < 1e-8);
+            assert!((growth_rate(&EINSTEIN_DE_SITTER, z) - 1.).abs() < 1e-8);
+        }
+    }
+
+    #[test]
+    fn early_normalisation() {
+        let z = 1000_f64;
+        assert!((growth_factor_early(&COSMO, z) * (1. + z) - 1.).abs() < 1e-6);
+    }
+
+    #[test]
+    fn growth_rate_is_logarithmic_derivative() {
+        let step: f64 = 1e-4;
+        for z in [0_f64, 0.5, 1., 3.] {
+            let ln_a = -(1. + z).ln();
+            let growth = |ln_a: f64| growth_factor(&COSMO, (-ln_a).exp() - 1.).ln();
+            let derivative = (growth(ln_a + step) - growth(ln_a - step)) / (2. * step);
+            assert!(
+                (growth_rate(&COSMO, z) - derivative).abs() < 1e-6,
+                "{} against {} at z = {}",
+                growth_rate(&COSMO, z),
+                derivative,
+                z
+            );
+        }
+    }
+
+    #[test]
+    fn growth_rate_today() {
+        // f(0) = 0.513 for Ω_m = 0.3, which Ω_m^0.55 matches to about half a per cent.
+        let f = growth_rate(&COSMO, 0.);
+        assert!((f - 0.513).abs() < 1e-3, "{}", f);
+        for z in [0_f64, 0.5, 1., 3.] {
+            let (exact, approx) = (growth_rate(&COSMO, z), growth_rate_approx(&COSMO, z));
+            assert!(
+                (approx / exact - 1.).abs() < 1e-2,
+                "{} against {}",
+                approx,
+                exact
+            );
+        }
+    }
+}
@@ -0,0 +1,237 @@
+use std::f64::consts::PI;
+
+use cosmoxide::Cosmology;
+
+use crate::background::{efunc, hubble_distance};
+use crate::growth::{growth_factor, omega_m_at_z};
+use crate::numerics::simpson;
+use crate::power::{LinearPower, RHO_CRIT};
+
+/// Critical linear overdensity for spherical collapse.
+pub const DELTA_C: f64 = 1.686;
+
+/// Gravitational constant in Mpc (km/s)² / M_sun.
+const GRAVITATIONAL_CONSTANT: f64 = 4.30091e-9;
+
+/// Largest halo mass considered when integrating the mass function, in M_sun/h.
+const MAX_HALO_MASS: f64 = 1e16;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum MassFunctionModel {
+    PressSchechter,
+    ShethTormen,
+    Tinker08,
+}
+
+impl MassFunctionModel {
+    pub fn from_name(name: &str) -> Option<MassFunctionModel> {
+        match name {
+            "ps" => Some(MassFunctionModel::PressSchechter),
+            "st" => Some(MassFunctionModel::ShethTormen),
+            "tinker08" => Some(MassFunctionModel::Tinker08),
+            _ => None,
+        }
+    }
+
+    /// Multiplicity function f(σ) at redshift z.
+    pub fn multiplicity(&self, sigma: f64, z: f64) -> f64 {
+        let nu = DELTA_C / sigma;
+        match self {
+            MassFunctionModel::PressSchechter => (2. / PI).sqrt() * nu * (-nu * nu / 2.).exp(),
+            MassFunctionModel::ShethTormen => {
+                let (big_a, a, p) = (0.3222, 0.707, 0.3);
+                big_a
+                    * (2. * a / PI).sqrt()
+                    * (1. + (a * nu * nu).powf(-p))
+                    * nu
+                    * (-a * nu * nu / 2.).exp()
+            }
+            MassFunctionModel::Tinker08 => {
+                // Parameters for Δ = 200 with respect to the mean density.
+                let alpha = 10_f64.powf(-(0.75 / (200_f64 / 75.).log10()).powf(1.2));
+                let big_a = 0.186 * (1. + z).powf(-0.14);
+                let a = 1.47 * (1. + z).powf(-0.06);
+                let b = 2.57 * (1. + z).powf(-alpha);
+                let c = 1.19;
+                big_a * ((sigma / b).powf(-a) + 1.) * (-c / (sigma * sigma)).exp()
+            }
+        }
+    }
+}
+
+/// Logarithmic slope dlnσ/dlnM, which is independent of redshift.
+fn sigma_slope(power: &LinearPower, m: f64) -> f64 {
+    let step: f64 = 0.05;
+    let upper = power.sigma_m(m * step.exp(), 0.).ln();
+    let lower = power.sigma_m(m * (-step).exp(), 0.).ln();
+    (upper - lower) / (2. * step)
+}
+
+/// Halo mass function dn/dlnM in (h/Mpc)³ for mass m in M_sun/h.
+pub fn mass_function(power: &LinearPower, model: MassFunctionModel, m: f64, z: f64) -> f64 {
+    let rho_m = power.cosmology().omega_m * RHO_CRIT;
+    let sigma = power.sigma_m(m, z);
+    rho_m / m * model.multiplicity(sigma, z) * sigma_slope(power, m).abs()
+}
+
+/// Expected number of halos more massive than m_min between z_min and z_max over an area in deg².
+pub fn number_counts(
+    power: &LinearPower,
+    model: MassFunctionModel,
+    m_min: f64,
+    z_min: f64,
+    z_max: f64,
+    area: f64,
+) -> f64 {
+    let cosmo = power.cosmology();
+    let h = cosmo.h0 / 100.;
+    let solid_angle = area * (PI / 180.).powi(2);
+    let rho_m = cosmo.omega_m * RHO_CRIT;
+    // Comoving volume element dV/dz over the survey area in (Mpc/h)³.
+    let volume_element = |z: f64| {
+        let d_m = cosmo.comoving_transverse_distance(z);
+        solid_angle * hubble_distance(cosmo) * d_m * d_m / efunc(cosmo, z) * h.powi(3)
+    };
+    let per_log_mass = |ln_m: f64| {
+        let m = ln_m.exp();
+        let sigma_today = power.sigma_m(m, 0.);
+        let slope = sigma_slope(power, m).abs();
+        let per_redshift = |z: f64| {
+            let sigma = sigma_today * growth_factor(cosmo, z);
+            volume_element(z) * rho_m / m * model.multiplicity(sigma, z) * slope
+        };
+        simpson(per_redshift, z_min, z_max, 20)
+    };
+    simpson(per_log_mass, m_min.ln(), MAX_HALO_MASS.ln(), 60)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Overdensity {
+    Critical200,
+    Mean200,
+    Virial,
+}
+
+impl Overdensity {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Overdensity::Critical200 => "200c",
+            Overdensity::Mean200 => "200m",
+            Overdensity::Virial => "vir",
+        }
+    }
+
+    /// Overdensity with respect to the critical density at z. The virial overdensity is the
+    /// Bryan & Norman (1998) fit.
+    pub fn delta_critical(&self, cosmo: &Cosmology, z: f64) -> f64 {
+        let omega_m = omega_m_at_z(cosmo, z);
+        match self {
+            Overdensity::Critical200 => 200.,
+            Overdensity::Mean200 => 200. * omega_m,
+            Overdensity::Virial => {
+                let x = omega_m - 1.;
+                18. * PI * PI + 82. * x - 39. * x * x
+            }
+        }
+    }
+
+    /// Physical halo radius in Mpc/h for mass m in M_sun/h.
+    pub fn radius(&self, cosmo: &Cosmology, m: f64, z: f64) -> f64 {
+        let rho_crit = RHO_CRIT * efunc(cosmo, z).powi(2);
+        (3. * m / (4. * PI * self.delta_critical(cosmo, z) * rho_crit)).cbrt()
+    }
+
+    /// Concentration from the Duffy et al. (2008) full sample fits.
+    pub fn concentration(&self, m: f64, z: f64) -> f64 {
+        let (a, b, c) = match self {
+            Overdensity::Critical200 => (5.71, -0.084, -0.47),
+            Overdensity::Mean200 => (10.14, -0.081, -1.01),
+            Overdensity::Virial => (7.85, -0.081, -0.71),
+        };
+        a * (m / 2e12).powf(b) * (1. + z).powf(c)
+    }
+
+    /// Circular velocity at the halo radius in km/s.
+    pub fn circular_velocity(&self, cosmo: &Cosmology, m: f64, z: f64) -> f64 {
+        (GRAVITATIONAL_CONSTANT * m / self.radius(cosmo, m, z)).sqrt()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const COSMO: Cosmology = Cosmology {
+        omega_m: 0.3,
+        omega_k: 0.,
+        omega_l: 0.7,
+        h0: 70.,
+    };
+
+    const EINSTEIN_DE_SITTER: Cosmology = Cosmology {
+        omega_m: 1.,
+        omega_k: 0.,
+        omega_l: 0.,
+        h0: 70.,
+    };
+
+    #[test]
+    fn press_schechter_and_sheth_tormen_contain_all_mass() {
+        for model in [
+            MassFunctionModel::PressSchechter,
+            MassFunctionModel::ShethTormen,
+        ] {
+            let per_ln_nu = |ln_nu: f64| model.multiplicity(DELTA_C / ln_nu.exp(), 0.);
+            let total = simpson(per_ln_nu, -40., 3., 4000);
+            assert!((total - 1.).abs() < 1e-3, "{:?}: {}", model, total);
+        }
+    }
+
+    #[test]
+    fn tinker08_multiplicity() {
+        // A ((σ/b)^-a + 1) exp(-c/σ²) with the Δ = 200 parameters of Tinker et al. (2008).
+        let f = MassFunctionModel::Tinker08.multiplicity(1., 0.);
+        assert!((f - 0.2832).abs() < 1e-4, "{}", f);
+    }
+
+    #[test]
+    fn bryan_norman_overdensity() {
+        let virial = Overdensity::Virial.delta_critical(&EINSTEIN_DE_SITTER, 0.);
+        assert!((virial - 18. * PI * PI).abs() < 1e-10);
+        let virial = Overdensity::Virial.delta_critical(&COSMO, 0.);
+        assert!((virial - 101.1).abs() < 0.1, "{}", virial);
+        let mean = Overdensity::Mean200.delta_critical(&COSMO, 0.);
+        assert!((mean - 60.).abs() < 1e-10);
+    }
+
+    #[test]
+    fn duffy08_concentration() {
+        for (overdensity, c) in [
+            (Overdensity::Critical200, 5.71),
+            (Overdensity::Mean200, 10.14),
+            (Overdensity::Virial, 7.85),
+        ] {
+            assert_eq!(overdensity.concentration(2e12, 0.), c);
+        }
+        let c = Overdensity::Critical200.concentration(1e14, 1.);
+        assert!((c - 5.71 * 50_f64.powf(-0.084) * 2_f64.powf(-0.47)).abs() < 1e-12);
+    }
+
+    #[test]
+    fn radius_and_circular_velocity() {
+        // R_200c = 163 kpc/h for 10^12 M_sun/h today, and V_200c = 10 H(z) R_200c.
+        let overdensity = Overdensity::Critical200;
+        let radius = overdensity.radius(&COSMO, 1e12, 0.);
+        assert!((radius - 0.1627).abs() < 1e-4, "{}", radius);
+        for z in [0., 1.] {
+            let velocity = overdensity.circular_velocity(&COSMO, 1e12, z);
+            let expected = 1000. * efunc(&COSMO, z) * overdensity.radius(&COSMO, 1e12, z);
+            assert!(
+                (velocity / expected - 1.).abs() < 1e-3,
+                "{} against {}",
+                velocity,
+                expected
+            );
+        }
+    }
+}
@@ -0,0 +1,196 @@
+use rayon::prelude::*;
+
+/// Intervals of the first grid, which is halved until the tolerance is met.
+const INITIAL_INTERVALS: usize = 64;
+
+/// Largest grid tried before giving up on a table.
+const MAX_INTERVALS: usize = 1 << 20;
+
+/// A clamped cubic spline through values on a uniform grid.
+struct Spline {
+    start: f64,
+    step: f64,
+    values: Vec<f64>,
+    curvatures: Vec<f64>,
+}
+
+impl Spline {
+    fn fit(start: f64, step: f64, values: Vec<f64>) -> Spline {
+        let n = values.len() - 1;
+        let y = &values;
+        // End slopes from fourth order one-sided differences, so that the ends are as accurate as
+        // the interior.
+        let first = (-25. * y[0] + 48. * y[1] - 36. * y[2] + 16. * y[3] - 3. * y[4]) / (12. * step);
+        let last = (25. * y[n] - 48. * y[n - 1] + 36. * y[n - 2] - 16. * y[n - 3] + 3. * y[n - 4])
+            / (12. * step);
+
+        // Tridiagonal system for the second derivatives, solved by forward elimination and back
+        // substitution.
+        let mut diagonal = vec![4.; n + 1];
+        let mut rhs: Vec<f64> = (0..=n)
+            .map(|i| match i {
+                0 => 6. / step * ((y[1] - y[0]) / step - first),
+                _ if i == n => 6. / step * (last - (y[n] - y[n - 1]) / step),
+                _ => 6. / (step * step) * (y[i + 1] - 2. * y[i] + y[i - 1]),
+            })
+            .collect();
+        diagonal[0] = 2.;
+        diagonal[n] = 2.;
+        for i in 1..=n {
+            let factor = 1. / diagonal[i - 1];
+            diagonal[i] -= factor;
+            rhs[i] -= factor * rhs[i - 1];
+        }
+        let mut curvatures = vec![0.; n + 1];
+        curvatures[n] = rhs[n] / diagonal[n];
+        for i in (0..n).rev() {
+            curvatures[i] = (rhs[i] - curvatures[i + 1]) / diagonal[i];
+        }
+        Spline {
+            start,
+            step,
+            values,
+            curvatures,
+        }
+    }
+
+    fn value(&self, x: f64) -> f64 {
+        let last = self.values.len() - 2;
+        let i = (((x - self.start) / self.step).floor().max(0.) as usize).min(last);
+        let t = (x - self.start) / self.step - i as f64;
+        let s = 1. - t;
+        let (y0, y1) = (self.values[i], self.values[i + 1]);
+        let (m0, m1) = (self.curvatures[i], self.curvatures[i + 1]);
+        s * y0 + t * y1 + self.step * self.step / 6. * ((s * s * s - s) * m0 + (t * t * t - t) * m1)
+    }
+}
+
+/// A function of redshift tabulated on a grid uniform in ln z and interpolated with a cubic
+/// spline, for evaluating it at many redshifts in a range without integrating each time.
+pub struct Table {
+    z_min: f64,
+    z_max: f64,
+    spline: Spline,
+}
+
+impl Table {
+    /// Tabulates f between two positive redshifts, halving the grid spacing until the spline
+    /// agrees with f to a relative tolerance halfway and a quarter of the way between every pair
+    /// of grid points. f itself has to be accurate to better than the tolerance. Returns None if
+    /// f isn't finite over the range or the tolerance can't be reached.
+    pub fn new<F: Fn(f64) -> f64 + Sync>(f: F, z_min: f64, z_max: f64, rtol: f64) -> Option<Table> {
+        if !(z_min > 0. && z_max.is_finite() && z_max >= z_min) {
+            return None;
+        }
+        let start = z_min.ln();
+        let span = (z_max.ln() - start).max(1e-6);
+        let evaluate = |points: Vec<f64>| -> Option<Vec<f64>> {
+            let values: Vec<f64> = points.into_par_iter().map(|x| f(x.exp())).collect();
+            values.iter().all(|v| v.is_finite()).then_some(values)
+        };
+        // Points at the given fractions of the way across each interval of a grid.
+        let offsets = |intervals: usize, fractions: &[f64]| -> Vec<f64> {
+            let step = span / intervals as f64;
+            (0..intervals)
+                .flat_map(|i| fractions.iter().map(move |t| start + (i as f64 + t) * step))
+                .collect()
+        };
+
+        let mut intervals = INITIAL_INTERVALS;
+        let mut values = evaluate(
+            (0..=intervals)
+                .map(|i| start + span * i as f64 / intervals as f64)
+                .collect(),
+        )?;
+        let mut midpoints = evaluate(offsets(intervals, &[0.5]))?;
+        loop {
+            let step = span / intervals as f64;
+            // The quarter points are the midpoints of the next grid.
+            let quarters = evaluate(offsets(intervals, &[0.25, 0.75]))?;
+            let spline = Spline::fit(start, step, values);
+            let scale = spline.values.iter().fold(0_f64, |max, v| max.max(v.abs()));
+            let checks = offsets(intervals, &[0.5])
+                .into_iter()
+                .zip(&midpoints)
+                .chain(offsets(intervals, &[0.25, 0.75]).into_iter().zip(&quarters));
+            let converged = checks.into_iter().all(|(x, exact)| {
+                let error = (spline.value(x) - exact).abs();
+                error <= rtol * exact.abs().max(1e-12 * scale)
+            });
+            if converged {
+                return Some(Table {
+                    z_min,
+                    z_max,
+                    spline,
+                });
+            }
+            if 2 * intervals > MAX_INTERVALS {
+                return None;
+            }
+            values = spline
+                .values
+                .into_iter()
+                .zip(midpoints.into_iter().map(Some).chain([None]))
+                .flat_map(|(value, midpoint)| std::iter::once(value).chain(midpoint))
+                .collect();
+            midpoints = quarters;
+            intervals *= 2;
+        }
+    }
+
+    /// Interpolated value at a redshift, or None outside the tabulated range.
+    pub fn get(&self, z: f64) -> Option<f64> {
+        (self.z_min..=self.z_max)
+            .contains(&z)
+            .then(|| self.spline.value(z.ln()))
+    }
+
+    /// Number of grid points in the table.
+    pub fn points(&self) -> usize {
+        self.spline.values.len()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::precise::Precise;
+    use cosmoxide::Cosmology;
+
+    #[test]
+    fn comoving_distance_within_tolerance() {
+        let cosmo = Cosmology {
+            omega_m: 0.3,
+            omega_k: 0.,
+            omega_l: 0.7,
+            h0: 70.,
+        };
+        let precise = Precise::new(&cosmo, 1e-13);
+        let distance = |z: f64| precise.comoving_distance(z).value;
+        for rtol in [1e-5, 1e-8] {
+            let table = Table::new(distance, 1e-3, 10., rtol).unwrap();
+            let worst = (0..10_000)
+                .map(|i| 1e-3 * 1e4_f64.powf(i as f64 / 9_999.))
+                .map(|z| {
+                    let exact = distance(z);
+                    (table.get(z).unwrap() - exact).abs() / exact
+                })
+                .fold(0., f64::max);
+            assert!(
+                worst <= 2. * rtol,
+                "relative error {:e} for tolerance {:e} with {} points",
+                worst,
+                rtol,
+                table.points()
+            );
+        }
+    }
+
+    #[test]
+    fn outside_range_is_not_interpolated() {
+        let table = Table::new(|z| z * z, 0.5, 2., 1e-8).unwrap();
+        assert_eq!(table.get(0.4), None);
+        assert_eq!(table.get(2.1), None);
+        assert!((table.get(1.5).unwrap() - 2.25).abs() < 1e-8);
+    }
+}
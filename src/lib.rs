@@ -0,0 +1,8 @@
+//! The numerical backend of `cosmo`: the background quantities, the adaptive quadrature they are
+//! computed to a tolerance with, and the spline tables that interpolate them. Shared by the
+//! binary, the reference tests and the benchmarks.
+
+pub mod background;
+pub mod interpolation;
+pub mod numerics;
+pub mod precise;
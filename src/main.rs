@@ -1,120 +1,626 @@
+use std::io;
+use std::path::Path;
+
 use clap::ArgMatches;
+use clap_complete::Shell;
 use colored::Colorize;
 use cosmoxide::Cosmology;
 
+use cosmo::{background, interpolation, numerics, precise};
+
+pub mod bao;
+pub mod catalog;
 pub mod cli;
+pub mod cmb;
+pub mod coords;
+pub mod expression;
+pub mod fits;
+pub mod figure;
+pub mod growth;
+pub mod halo;
+pub mod pairs;
+pub mod pdf;
+pub mod plot;
+pub mod power;
+pub mod preset;
+pub mod quantity;
+pub mod repl;
+pub mod sampling;
+pub mod sim;
+pub mod sky;
+pub mod tui;
+pub mod velocity;
+pub mod votable;
 
 use cli::cli;
+use cmb::EarlyUniverse;
+use halo::{MassFunctionModel, Overdensity};
+use pdf::RedshiftPdf;
+use plot::Series;
+use power::{LinearPower, TransferFunction};
+use precise::Precise;
+use quantity::Quantity;
+use sampling::ParameterDistribution;
+use velocity::VelocityConvention;
 
-fn try_parse_string_to_f64(string: &String) -> f64 {
-    match string.parse::<f64>() {
-        Ok(value) => value,
-        Err(_) => {
-            println!("Value: {:?} is not a valid number.", string);
-            std::process::exit(1)
-        }
+fn try_parse_string_to_f64(string: &String) -> Result<f64, String> {
+    string.parse::<f64>().map_err(|_| format!("Value: {:?} is not a valid number.", string))
+}
+
+fn parse_frame(matches: &ArgMatches) -> sky::Frame {
+    match matches.get_one::<String>("frame") {
+        Some(name) => sky::Frame::from_name(name).unwrap(),
+        None => sky::Frame::Equatorial,
     }
 }
 
-fn parse_cosmo_parameters(matches: &ArgMatches) -> Cosmology {
-    let omega_m = match matches.get_one::<String>("omega_matter") {
-        Some(om) => try_parse_string_to_f64(om),
-        None => 0.3,
+/// What redshift arguments are given as, from the global --from option.
+fn parse_input(matches: &ArgMatches) -> background::Input {
+    match matches.try_get_one::<String>("from") {
+        Ok(Some(name)) => background::Input::from_name(name).unwrap(),
+        _ => background::Input::Redshift,
+    }
+}
+
+/// Redshift from an argument given as the quantity chosen with --from.
+fn parse_redshift(matches: &ArgMatches, cosmo: &Cosmology, string: &String) -> Result<f64, String> {
+    to_redshift(matches, cosmo, try_parse_string_to_f64(string)?)
+}
+
+/// Checks the cosmology is valid and --from isn't given where the input isn't a redshift.
+fn check_input(matches: &ArgMatches) -> Result<(), String> {
+    let mut deepest = matches;
+    while let Some((_, sub_matches)) = deepest.subcommand() {
+        deepest = sub_matches;
+    }
+    // With --sample, -m and -H are distributions, read by parse_parameter_distribution.
+    let sampling = matches!(deepest.try_get_one::<usize>("n_samples"), Ok(Some(_)));
+    if deepest.try_get_one::<String>("omega_matter").is_ok() && !sampling {
+        parse_cosmology(deepest)?;
+    }
+    let inverse = deepest.try_get_one::<bool>("inverse").ok().flatten() == Some(&true);
+    let pdf = matches!(deepest.try_get_one::<String>("pdf"), Ok(Some(_)));
+    if parse_input(deepest) != background::Input::Redshift && (inverse || pdf) {
+        return Err("--from converts redshift inputs, so it can't be used with --inverse or --pdf.".to_string());
+    }
+    Ok(())
+}
+
+fn to_redshift(matches: &ArgMatches, cosmo: &Cosmology, value: f64) -> Result<f64, String> {
+    parse_input(matches).redshift(cosmo, value)
+}
+
+/// Cosmology from the -m, -l, -k and -H arguments, or why they don't give a supported one.
+fn parse_cosmology(matches: &ArgMatches) -> Result<Cosmology, String> {
+    let parse = |id: &str, default: f64| match matches.get_one::<String>(id) {
+        Some(string) => string.parse::<f64>().map_err(|_| format!("Value: {:?} is not a valid number.", string)),
+        None => Ok(default),
     };
-    let omega_l = match matches.get_one::<String>("omega_lambda") {
-        Some(ol) => try_parse_string_to_f64(ol),
-        None => 0.7,
+    let omega_m = parse("omega_matter", 0.3)?;
+    let omega_l = parse("omega_lambda", 0.7)?;
+    let omega_k = parse("omega_k", 0.)?;
+    let h0 = parse("hubble_constant", 70.)?;
+    if omega_k + omega_l + omega_m != 1. {
+        return Err("Chosen cosmology is not flat. Non-flat cosmology is not supported yet.".to_string());
+    }
+    Ok(Cosmology {
+        omega_m,
+        omega_k,
+        omega_l,
+        h0,
+    })
+}
+
+/// Combined redshift uncertainty from --zerr and the redshift induced by --vpec at z.
+fn parse_redshift_error(matches: &ArgMatches, z: f64) -> Result<Option<f64>, String> {
+    let z_err = matches.get_one::<String>("z_err").map(try_parse_string_to_f64).transpose()?;
+    let v_pec = matches.get_one::<String>("v_pec").map(try_parse_string_to_f64).transpose()?;
+    if z_err.is_none() && v_pec.is_none() {
+        return Ok(None);
+    }
+    let z_pec_err = (1. + z) * v_pec.unwrap_or(0.) / background::SPEED_OF_LIGHT;
+    Ok(Some((z_err.unwrap_or(0.).powi(2) + z_pec_err.powi(2)).sqrt()))
+}
+
+/// Linear propagation of a redshift uncertainty through f.
+fn propagate_error<F: Fn(f64) -> f64>(f: F, z: f64, z_err: Option<f64>) -> Option<f64> {
+    z_err.map(|sigma| numerics::derivative(f, z).abs() * sigma)
+}
+
+fn format_with_error(value: f64, error: Option<f64>, precision: Option<usize>) -> String {
+    match (error, precision) {
+        (Some(error), Some(precision)) => format!("{:.*} ± {:.*}", precision, value, precision, error),
+        (Some(error), None) => format!("{} ± {}", value, error),
+        (None, Some(precision)) => format!("{:.*}", precision, value),
+        (None, None) => format!("{}", value),
+    }
+}
+
+/// Parse either a plain number or a value with a Gaussian uncertainty written as value±sigma or
+/// value+-sigma.
+fn parse_value_with_sigma(string: &String) -> Result<(f64, f64), String> {
+    let parts: Vec<&str> = string.split(['±']).flat_map(|part| part.split("+-")).collect();
+    match parts.as_slice() {
+        [value] => Ok((try_parse_string_to_f64(&value.to_string())?, 0.)),
+        [value, sigma] => Ok((
+            try_parse_string_to_f64(&value.to_string())?,
+            try_parse_string_to_f64(&sigma.to_string())?,
+        )),
+        _ => Err(format!("Value: {:?} is not a valid number or value±sigma.", string)),
+    }
+}
+
+fn parse_parameter_distribution(matches: &ArgMatches) -> Result<ParameterDistribution, String> {
+    let (omega_m, sigma_m) = match matches.get_one::<String>("omega_matter") {
+        Some(om) => parse_value_with_sigma(om)?,
+        None => (0.3, 0.),
     };
     let omega_k = match matches.get_one::<String>("omega_k") {
-        Some(ok) => try_parse_string_to_f64(ok),
+        Some(ok) => try_parse_string_to_f64(ok)?,
         None => 0.,
     };
-    let h0 = match matches.get_one::<String>("hubble_constant") {
-        Some(h) => try_parse_string_to_f64(h),
-        None => 70.,
+    let (h0, sigma_h0) = match matches.get_one::<String>("hubble_constant") {
+        Some(h) => parse_value_with_sigma(h)?,
+        None => (70., 0.),
     };
-    if omega_k + omega_l + omega_m != 1. {
-        println!("Chosen cosmology is not flat. Non-flat cosmology is not supported yet.");
-        std::process::exit(1);
+    let omega_l = match matches.get_one::<String>("omega_lambda") {
+        Some(ol) => try_parse_string_to_f64(ol)?,
+        None => 1. - omega_m,
+    };
+    if omega_k != 0. || omega_k + omega_l + omega_m != 1. {
+        return Err("Chosen cosmology is not flat. Non-flat cosmology is not supported yet.".to_string());
     }
-    Cosmology {
-        omega_m,
+    let covariance = match matches.get_one::<String>("covariance") {
+        Some(path) => sampling::read_covariance(path)?,
+        None => [[sigma_m * sigma_m, 0.], [0., sigma_h0 * sigma_h0]],
+    };
+    Ok(ParameterDistribution {
+        mean: [omega_m, h0],
+        covariance,
         omega_k,
-        omega_l,
-        h0,
+    })
+}
+
+/// Evaluate the quantity of a subcommand for cosmologies drawn from the parameter uncertainties.
+fn run_sampling(name: &str, matches: &ArgMatches, n_samples: usize) -> Result<(), String> {
+    let quantity = quantity::find(name).unwrap();
+    let input = matches
+        .try_get_one::<String>("input")
+        .ok()
+        .flatten()
+        .or_else(|| matches.get_one::<String>("z"))
+        .unwrap();
+    let input = try_parse_string_to_f64(input)?;
+    let inverse = *matches
+        .try_get_one::<bool>("inverse")
+        .ok()
+        .flatten()
+        .unwrap_or(&false);
+    let seed = matches.get_one::<u64>("seed").copied();
+    if n_samples == 0 {
+        return Err("Number of samples must be at least 1.".to_string());
+    }
+    let cosmologies = parse_parameter_distribution(matches)?.draw(n_samples, seed)?;
+    let output = parse_output(quantity, matches)?;
+    let (label, unit) = match inverse {
+        true => ("Redshift", String::new()),
+        false => (output.label, format!(" {}", output.unit)),
+    };
+    // Ages and distances correspond to a different redshift in each cosmology.
+    let values = cosmologies
+        .iter()
+        .map(|cosmo| match inverse {
+            true => Ok(quantity.inverse.unwrap()(cosmo, input)),
+            false => Ok(output.apply((quantity.value)(cosmo, to_redshift(matches, cosmo, input)?))),
+        })
+        .collect::<Result<_, String>>()?;
+    let summary = sampling::summarise(values);
+    println!(
+        "{} (median): {}{}",
+        label,
+        format!("{:.4}", summary.median).bold().green(),
+        unit
+    );
+    println!(
+        "68% interval: [{}, {}]{}",
+        format!("{:.4}", summary.interval_68.0).bold().green(),
+        format!("{:.4}", summary.interval_68.1).bold().green(),
+        unit
+    );
+    println!(
+        "95% interval: [{}, {}]{}",
+        format!("{:.4}", summary.interval_95.0).bold().green(),
+        format!("{:.4}", summary.interval_95.1).bold().green(),
+        unit
+    );
+    Ok(())
+}
+
+/// Relative tolerance from --rtol, within what double precision can reach.
+fn parse_rtol(string: &String) -> Result<f64, String> {
+    match string.parse::<f64>() {
+        Ok(rtol) if (1e-14..1.).contains(&rtol) => Ok(rtol),
+        Ok(_) => Err("The relative tolerance must be between 1e-14 and 1.".to_string()),
+        Err(_) => Err(format!("Value: {:?} is not a valid number.", string)),
+    }
+}
+
+/// Value of a quantity by adaptive quadrature, or with --inverse the redshift at which it takes
+/// the input, with an estimate of its numerical error. Shared with the interactive session.
+fn precise_value(
+    quantity: &Quantity,
+    matches: &ArgMatches,
+    cosmo: &Cosmology,
+    input: f64,
+    rtol: f64,
+) -> Result<numerics::Estimate, String> {
+    let precise = Precise::new(cosmo, rtol);
+    let inverse = *matches.try_get_one::<bool>("inverse").ok().flatten().unwrap_or(&false);
+    if inverse {
+        return quantity
+            .precise_inverse
+            .and_then(|inverse| inverse(&precise, input))
+            .ok_or_else(|| format!("The {} never reaches {} {}.", quantity.label.to_lowercase(), input, quantity.unit));
+    }
+    let input_type = parse_input(matches);
+    input_type.check(cosmo, input)?;
+    let z = match input_type {
+        background::Input::Redshift => Some(numerics::Estimate::exact(input)),
+        background::Input::ScaleFactor => Some(numerics::Estimate::exact(1. / input - 1.)),
+        background::Input::Age => precise.inverse_age(input),
+        background::Input::LookbackTime => precise.inverse_lookback_time(input),
+        background::Input::ComovingDistance => precise.inverse_codist(input),
+    }
+    .ok_or_else(|| format!("No redshift was found for the input {}.", input))?;
+    let value = (quantity.precise)(&precise, z.value);
+    // The error of a converted input carries through to the value.
+    let slope = if z.error > 0. { numerics::derivative(|z| (quantity.precise)(&precise, z).value, z.value) } else { 0. };
+    Ok(numerics::Estimate { value: value.value, error: value.error + slope.abs() * z.error })
+}
+
+/// Evaluate the quantity of a subcommand to a relative tolerance and report its numerical error.
+fn run_precise(name: &str, matches: &ArgMatches, rtol: &String) -> Result<(), String> {
+    let quantity = quantity::find(name).unwrap();
+    let input = matches
+        .try_get_one::<String>("input")
+        .ok()
+        .flatten()
+        .or_else(|| matches.get_one::<String>("z"))
+        .unwrap();
+    let input = try_parse_string_to_f64(input)?;
+    let cosmo = parse_cosmology(matches)?;
+    let mut estimate = precise_value(quantity, matches, &cosmo, input, parse_rtol(rtol)?)?;
+    let inverse = *matches.try_get_one::<bool>("inverse").ok().flatten().unwrap_or(&false);
+    let (label, unit) = if inverse {
+        ("Redshift", String::new())
+    } else {
+        let output = parse_output(quantity, matches)?;
+        estimate = numerics::Estimate { value: output.apply(estimate.value), error: output.scale.abs() * estimate.error };
+        (output.label, output.unit)
+    };
+    let unit = if unit.is_empty() { String::new() } else { format!(" {}", unit) };
+    // Only the digits the error leaves significant are shown.
+    let precision = (estimate.error > 0. && estimate.error.is_finite())
+        .then(|| (1 - estimate.error.log10().floor() as i32).clamp(0, 17) as usize);
+    println!("{}: {}{}", label, format_with_error(estimate.value, None, precision).bold().green(), unit);
+    println!("Numerical error: {}{}", format!("{:.1e}", estimate.error).bold().green(), unit);
+    Ok(())
+}
+
+/// Prefix marking the units of an angular scale as physical or comoving, as in the angscale
+/// subcommands.
+fn angscale_frame(name: &str) -> &'static str {
+    match name {
+        "angscale_phys" => "p",
+        "angscale_co" => "c",
+        _ => "",
+    }
+}
+
+/// What --pdf, --rtol and --sample report for a subcommand: its quantity, or with --mag, --size
+/// or --mpc-per-arcmin the absolute magnitude, size or angular scale in Mpc/arcmin, each a linear
+/// function of it.
+struct Output {
+    label: &'static str,
+    unit: String,
+    offset: f64,
+    scale: f64,
+}
+
+impl Output {
+    fn apply(&self, value: f64) -> f64 {
+        self.offset + self.scale * value
+    }
+}
+
+fn parse_output(quantity: &Quantity, matches: &ArgMatches) -> Result<Output, String> {
+    let frame = angscale_frame(quantity.name);
+    let magnitude = matches.try_get_one::<String>("apparent_magnitude").ok().flatten();
+    let size = matches.try_get_one::<String>("size").ok().flatten();
+    let mpc = *matches.try_get_one::<bool>("mpc").ok().flatten().unwrap_or(&false);
+    Ok(if let Some(magnitude) = magnitude {
+        Output { label: "Absolute magnitude", unit: "mag".to_string(), offset: try_parse_string_to_f64(magnitude)?, scale: -1. }
+    } else if let Some(size) = size {
+        Output { label: "Size", unit: format!("{}kpc", frame), offset: 0., scale: try_parse_string_to_f64(size)? }
+    } else if mpc {
+        Output { label: quantity.label, unit: format!("{}Mpc/arcmin", frame), offset: 0., scale: 60. / 1e3 }
+    } else {
+        Output { label: quantity.label, unit: format!("{}{}", frame, quantity.unit), offset: 0., scale: 1. }
+    })
+}
+
+/// Propagate a photometric redshift PDF through the quantity of a subcommand.
+fn run_pdf(name: &str, matches: &ArgMatches, pdf: &String) -> Result<(), String> {
+    let is_gaussian = pdf.contains('±') || pdf.contains("+-");
+    let redshift_pdf = if is_gaussian && !std::path::Path::new(pdf).exists() {
+        let (mean, sigma) = parse_value_with_sigma(pdf)?;
+        RedshiftPdf::gaussian(mean, sigma)?
+    } else {
+        RedshiftPdf::from_file(pdf)?
+    };
+    let quantity = quantity::find(name).unwrap();
+    let cosmo = parse_cosmology(matches)?;
+    let output = parse_output(quantity, matches)?;
+    let (label, unit) = (output.label, &output.unit);
+    let function = |z| output.apply((quantity.value)(&cosmo, z));
+    let summary = redshift_pdf.summary(function);
+    println!(
+        "{} (mean): {} {}",
+        label,
+        format!("{:.4}", summary.mean).bold().green(),
+        unit
+    );
+    println!(
+        "{} (median): {} {}",
+        label,
+        format!("{:.4}", summary.median).bold().green(),
+        unit
+    );
+    println!(
+        "68% interval: [{}, {}] {}",
+        format!("{:.4}", summary.interval_68.0).bold().green(),
+        format!("{:.4}", summary.interval_68.1).bold().green(),
+        unit
+    );
+    println!(
+        "95% interval: [{}, {}] {}",
+        format!("{:.4}", summary.interval_95.0).bold().green(),
+        format!("{:.4}", summary.interval_95.1).bold().green(),
+        unit
+    );
+    if *matches.get_one::<bool>("pdf_table").unwrap_or(&false) {
+        println!();
+        println!("{:>12} {:>16} {:>16}", "z", format!("{} [{}]", "value", unit), "p(value)");
+        for (z, value, density) in redshift_pdf.transform(function) {
+            println!("{:>12.6} {:>16.6} {:>16.6e}", z, value, density);
+        }
+    }
+    Ok(())
+}
+
+fn parse_early_universe_parameters(matches: &ArgMatches) -> Result<EarlyUniverse, String> {
+    let omega_b = match matches.get_one::<String>("omega_baryon") {
+        Some(ob) => try_parse_string_to_f64(ob)?,
+        None => 0.049,
+    };
+    let t_cmb = match matches.get_one::<String>("t_cmb") {
+        Some(t) => try_parse_string_to_f64(t)?,
+        None => 2.7255,
+    };
+    let n_eff = match matches.get_one::<String>("n_eff") {
+        Some(n) => try_parse_string_to_f64(n)?,
+        None => 3.046,
+    };
+    Ok(EarlyUniverse {
+        omega_b,
+        t_cmb,
+        n_eff,
+    })
+}
+
+fn parse_linear_power<'a>(matches: &ArgMatches, cosmo: &'a Cosmology) -> Result<LinearPower<'a>, String> {
+    let early = parse_early_universe_parameters(matches)?;
+    let transfer = match matches.get_one::<String>("transfer") {
+        Some(name) => TransferFunction::from_name(name).unwrap(),
+        None => TransferFunction::EisensteinHu,
+    };
+    let n_s = match matches.get_one::<String>("n_s") {
+        Some(ns) => try_parse_string_to_f64(ns)?,
+        None => 0.965,
+    };
+    Ok(match matches.get_one::<String>("a_s") {
+        Some(a_s) => {
+            LinearPower::normalised_to_as(cosmo, early, transfer, n_s, try_parse_string_to_f64(a_s)?)
+        }
+        None => LinearPower::normalised_to_sigma8(cosmo, early, transfer, n_s, parse_sigma8(matches)?),
+    })
+}
+
+/// σ8 today from --sigma8.
+fn parse_sigma8(matches: &ArgMatches) -> Result<f64, String> {
+    match matches.get_one::<String>("sigma8") {
+        Some(s8) => try_parse_string_to_f64(s8),
+        None => Ok(0.8),
     }
 }
 
+/// Print the horizons at z, with the redshift uncertainty z_err propagated to those depending on
+/// it.
+fn print_horizons(cosmo: &Cosmology, z: f64, z_err: Option<f64>) {
+    let show = |f: &dyn Fn(f64) -> f64| format_with_error(f(z), propagate_error(f, z, z_err), Some(4)).bold().green();
+    println!(
+        "Particle horizon: {} cMpc ({} pMpc)",
+        show(&|z| background::particle_horizon(cosmo, z)),
+        show(&|z| background::particle_horizon(cosmo, z) / (1. + z))
+    );
+    println!(
+        "Event horizon: {} cMpc ({} pMpc)",
+        show(&|z| background::event_horizon(cosmo, z)),
+        show(&|z| background::event_horizon(cosmo, z) / (1. + z))
+    );
+    println!(
+        "Hubble radius c/H(z): {} pMpc",
+        show(&|z| background::hubble_radius(cosmo, z))
+    );
+    println!();
+    println!(
+        "Matter-Λ equality: z = {}",
+        format!("{:.4}", background::matter_lambda_equality(cosmo)).bold().green()
+    );
+    println!(
+        "Acceleration onset: z = {}",
+        format!("{:.4}", background::acceleration_onset(cosmo)).bold().green()
+    );
+}
+
+/// Print the growth quantities at z, with the redshift uncertainty z_err propagated.
+fn print_growth(cosmo: &Cosmology, z: f64, z_err: Option<f64>, sigma8: f64, early: bool) {
+    let show = |f: &dyn Fn(f64) -> f64| format_with_error(f(z), propagate_error(f, z, z_err), Some(4)).bold().green();
+    let growth_factor = |z| if early { growth::growth_factor_early(cosmo, z) } else { growth::growth_factor(cosmo, z) };
+    println!("Growth factor D(z): {}", show(&growth_factor));
+    println!("Growth rate f(z): {}", show(&|z| growth::growth_rate(cosmo, z)));
+    println!(
+        "Growth rate Ω_m(z)^{}: {}",
+        growth::GROWTH_INDEX,
+        show(&|z| growth::growth_rate_approx(cosmo, z))
+    );
+    println!(
+        "fσ8(z): {}",
+        show(&|z| growth::growth_rate(cosmo, z) * sigma8 * growth::growth_factor(cosmo, z))
+    );
+}
+
+/// The quantity, axes and curves requested by the plot subcommand.
+fn parse_plot(matches: &ArgMatches) -> Result<(&'static Quantity, plot::Axis, plot::Axis, Vec<Series>), String> {
+    let quantity = quantity::find(matches.get_one::<String>("quantity").unwrap()).unwrap();
+    let log_x = *matches.get_one::<bool>("log_x").unwrap_or(&false);
+    let log_y = *matches.get_one::<bool>("log_y").unwrap_or(&false);
+    let z_min = match matches.get_one::<String>("zmin") {
+        Some(z) => try_parse_string_to_f64(z)?,
+        None => if log_x { 0.01 } else { 0. },
+    };
+    let z_max = match matches.get_one::<String>("zmax") {
+        Some(z) => try_parse_string_to_f64(z)?,
+        None => 3.,
+    };
+    let n = match matches.get_one::<String>("points") {
+        Some(n) => try_parse_string_to_f64(n)? as usize,
+        None => 200,
+    };
+    if z_min < 0. || z_max <= z_min {
+        return Err("The redshift range must satisfy 0 <= zmin < zmax.".to_string());
+    }
+    if log_x && z_min <= 0. {
+        return Err("A logarithmic redshift axis needs zmin above 0.".to_string());
+    }
+    if n < 2 {
+        return Err("At least two points are needed to draw a plot.".to_string());
+    }
+    let x = plot::Axis { min: z_min, max: z_max, log: log_x };
+
+    let cosmo = parse_cosmology(matches)?;
+    let mut series = vec![Series {
+        label: format!("H0 = {}, Ωm = {}, ΩΛ = {}", cosmo.h0, cosmo.omega_m, cosmo.omega_l),
+        points: plot::sample(quantity, &cosmo, &x, n),
+    }];
+    if let Some(names) = matches.get_many::<String>("presets") {
+        for name in names {
+            let preset = preset::find(name).unwrap();
+            series.push(Series {
+                label: preset.name.to_string(),
+                points: plot::sample(quantity, &preset.cosmology(), &x, n),
+            });
+        }
+    }
+    let values = series.iter().flat_map(|curve| curve.points.iter().map(|(_, value)| *value));
+    let Some(y) = plot::Axis::spanning(values, log_y) else {
+        return Err("There are no values to plot on this axis.".to_string());
+    };
+    Ok((quantity, x, y, series))
+}
 
 fn main() {
     let matches = cli().get_matches();
+    if let Err(message) = run(&matches) {
+        println!("{}", message);
+        std::process::exit(1)
+    }
+}
+
+/// Runs a parsed command line, or returns why it can't. Shared with the interactive session,
+/// which reports the error and carries on.
+fn run(matches: &ArgMatches) -> Result<(), String> {
+    check_input(matches)?;
+    if let Some((name, sub_matches)) = matches.subcommand()
+        && let Ok(Some(n_samples)) = sub_matches.try_get_one::<usize>("n_samples")
+    {
+        return run_sampling(name, sub_matches, *n_samples);
+    }
+    if let Some((name, sub_matches)) = matches.subcommand()
+        && let Ok(Some(rtol)) = sub_matches.try_get_one::<String>("rtol")
+    {
+        return run_precise(name, sub_matches, rtol);
+    }
+    if let Some((name, sub_matches)) = matches.subcommand()
+        && let Ok(Some(pdf)) = sub_matches.try_get_one::<String>("pdf")
+    {
+        return run_pdf(name, sub_matches, pdf);
+    }
 
     match matches.subcommand() {
         Some(("all", sub_matches)) => {
-            let z = try_parse_string_to_f64(sub_matches.get_one::<String>("z").unwrap());
-            let cosmo = parse_cosmo_parameters(sub_matches);
-            println!("Redshift (z): {}", z.to_string().bold().green());
+            let cosmo = parse_cosmology(sub_matches)?;
+            let z = parse_redshift(sub_matches, &cosmo, sub_matches.get_one::<String>("z").unwrap())?;
+            let z_err = parse_redshift_error(sub_matches, z)?;
+            let show = |f: &dyn Fn(f64) -> f64| {
+                format_with_error(f(z), propagate_error(f, z, z_err), Some(4))
+                    .bold()
+                    .green()
+            };
             println!(
-                "Expansion factor (a): {}",
-                format!("{:.4}", (1. / (1. + z))).bold().green()
+                "Redshift (z): {}",
+                format_with_error(z, z_err, None).bold().green()
             );
+            println!("Expansion factor (a): {}", show(&|z| 1. / (1. + z)));
             println!();
             println!(
                 "Comoving distance: {} Mpc",
-                format!("{:.4}", cosmo.comoving_distance(z)).bold().green()
+                show(&|z| cosmo.comoving_distance(z))
             );
             println!(
                 "Luminosity distance: {} Mpc",
-                format!("{:.4}", cosmo.luminosity_distance(z))
-                    .bold()
-                    .green()
+                show(&|z| cosmo.luminosity_distance(z))
             );
             println!(
                 "Angular diameter distance: {} Mpc",
-                format!("{:.4}", cosmo.angular_diameter_distance(z))
-                    .bold()
-                    .green()
+                show(&|z| cosmo.angular_diameter_distance(z))
             );
             println!(
                 "Comoving transverse distance: {} Mpc",
-                format!("{:.4}", cosmo.comoving_transverse_distance(z))
-                    .bold()
-                    .green()
+                show(&|z| cosmo.comoving_transverse_distance(z))
             );
             println!(
                 "Distance Modulus: {} mag",
-                format!("{:.4}", cosmo.distance_modulus(z)).bold().green()
+                show(&|z| cosmo.distance_modulus(z))
             );
             println!(
                 "Physical angular scale: {} kpc/arcsec",
-                format!("{:.4}", cosmo.kpc_per_arcsecond_physical(z))
-                    .bold()
-                    .green()
+                show(&|z| cosmo.kpc_per_arcsecond_physical(z))
             );
             println!(
                 "Comoving angular scale: {} kpc/arcsec",
-                format!("{:.4}", cosmo.kpc_per_arcsecond_comoving(z))
-                    .bold()
-                    .green()
+                show(&|z| cosmo.kpc_per_arcsecond_comoving(z))
             );
             println!(
                 "Comoving Volume: {} Gpc³",
-                format!("{:.4}", (cosmo.comoving_volume(z) / 1e9))
-                    .bold()
-                    .green()
+                show(&|z| cosmo.comoving_volume(z) / 1e9)
             );
             println!();
-            println!("H(z): {}", format!("{:.4}", cosmo.h_at_z(z)).bold().green());
-            println!(
-                "Expansion rate: {}",
-                format!("{:.4}", cosmo.h_at_z(z) / (1. + z)).bold().green()
-            );
+            println!("H(z): {}", show(&|z| cosmo.h_at_z(z)));
+            println!("Expansion rate: {}", show(&|z| cosmo.h_at_z(z) / (1. + z)));
             println!();
-            println!("Age: {} Gyr", format!("{:.4}", cosmo.age(z)).bold().green());
-            println!(
-                "Look back time: {} Gyr",
-                format!("{:.4}", cosmo.look_back_time(z)).bold().green()
-            );
+            println!("Age: {} Gyr", show(&|z| cosmo.age(z)));
+            println!("Look back time: {} Gyr", show(&|z| cosmo.look_back_time(z)));
             println!(
                 "Universe Age Now: {} Gyr",
                 format!("{:.4}", cosmo.age(0.)).bold().green()
@@ -123,98 +629,884 @@ fn main() {
                 "Hubble Time: {} Gyr",
                 format!("{:.4}", cosmo.hubble_time()).bold().green()
             );
+            if *sub_matches.get_one::<bool>("verbose").unwrap_or(&false) {
+                println!();
+                print_horizons(&cosmo, z, z_err);
+                println!();
+                print_growth(&cosmo, z, z_err, parse_sigma8(sub_matches)?, false);
+            }
         }
 
         Some(("codist", sub_matches)) => {
-            let value = try_parse_string_to_f64(sub_matches.get_one::<String>("input").unwrap());
-            let cosmo = parse_cosmo_parameters(sub_matches);
+            let value = try_parse_string_to_f64(sub_matches.get_one::<String>("input").unwrap())?;
+            let cosmo = parse_cosmology(sub_matches)?;
             if *sub_matches.get_one::<bool>("inverse").unwrap_or(&false) {
                 println!("redshift: {}", format!("{}",cosmo.inverse_codist(value)).bold().green());
             } else {
-                println!("{} Mpc", format!("{}",cosmo.comoving_distance(value)).bold().green());
+                let z = to_redshift(sub_matches, &cosmo, value)?;
+                let error = propagate_error(|z| cosmo.comoving_distance(z), z, parse_redshift_error(sub_matches, z)?);
+                println!("{} Mpc", format_with_error(cosmo.comoving_distance(z), error, None).bold().green());
             }
         }
 
         Some(("lumdist", sub_matches)) => {
-            let value = try_parse_string_to_f64(sub_matches.get_one::<String>("input").unwrap());
-            let cosmo = parse_cosmo_parameters(sub_matches);
+            let value = try_parse_string_to_f64(sub_matches.get_one::<String>("input").unwrap())?;
+            let cosmo = parse_cosmology(sub_matches)?;
             if *sub_matches.get_one::<bool>("inverse").unwrap_or(&false) {
-                println!("redshift: {}", format!("{}", cosmo.inverse_codist(value)).bold().green());
+                let z = background::inverse_lumdist(&cosmo, value);
+                if z.is_nan() {
+                    return Err(format!(
+                        "The luminosity distance must be in [0, {:.4}] Mpc, up to z = {}.",
+                        cosmo.luminosity_distance(background::MAX_INVERSE_REDSHIFT),
+                        background::MAX_INVERSE_REDSHIFT
+                    ));
+                }
+                println!("redshift: {}", format!("{}", z).bold().green());
             } else {
-                println!("{} Mpc", format!("{}", cosmo.luminosity_distance(value)).bold().green());
+                let z = to_redshift(sub_matches, &cosmo, value)?;
+                let error = propagate_error(|z| cosmo.luminosity_distance(z), z, parse_redshift_error(sub_matches, z)?);
+                println!("{} Mpc", format_with_error(cosmo.luminosity_distance(z), error, None).bold().green());
             }
         }
 
         Some(("covol", sub_matches)) => {
-            let value = try_parse_string_to_f64(sub_matches.get_one::<String>("input").unwrap());
-            let cosmo = parse_cosmo_parameters(sub_matches);
+            let value = try_parse_string_to_f64(sub_matches.get_one::<String>("input").unwrap())?;
+            let cosmo = parse_cosmology(sub_matches)?;
             if *sub_matches.get_one::<bool>("inverse").unwrap_or(&false) {
                 println!("redshift: {}", format!("{}", cosmo.inverse_covol(value * 1e9)).bold().green());
             } else {
-                println!("{} Gpc³", format!("{}", cosmo.comoving_volume(value) / 1e9).bold().green());
+                let z = to_redshift(sub_matches, &cosmo, value)?;
+                let error = propagate_error(|z| cosmo.comoving_volume(z) / 1e9, z, parse_redshift_error(sub_matches, z)?);
+                println!("{} Gpc³", format_with_error(cosmo.comoving_volume(z) / 1e9, error, None).bold().green());
             }
         }
 
         Some(("lookback", sub_matches)) => {
-            let value = try_parse_string_to_f64(sub_matches.get_one::<String>("input").unwrap());
-            let cosmo = parse_cosmo_parameters(sub_matches);
+            let value = try_parse_string_to_f64(sub_matches.get_one::<String>("input").unwrap())?;
+            let cosmo = parse_cosmology(sub_matches)?;
             if *sub_matches.get_one::<bool>("inverse").unwrap_or(&false) {
                 println!("redshift: {}", format!("{}", cosmo.inverse_lookback_time(value)).bold().green());
             } else {
-                println!("{} Gyr", format!("{}", cosmo.look_back_time(value)).bold().green());
+                let z = to_redshift(sub_matches, &cosmo, value)?;
+                let error = propagate_error(|z| cosmo.look_back_time(z), z, parse_redshift_error(sub_matches, z)?);
+                println!("{} Gyr", format_with_error(cosmo.look_back_time(z), error, None).bold().green());
             }
         }
 
         Some(("age", sub_matches)) => {
-            let value = try_parse_string_to_f64(sub_matches.get_one::<String>("input").unwrap());
-            let cosmo = parse_cosmo_parameters(sub_matches);
+            let value = try_parse_string_to_f64(sub_matches.get_one::<String>("input").unwrap())?;
+            let cosmo = parse_cosmology(sub_matches)?;
             if *sub_matches.get_one::<bool>("inverse").unwrap_or(&false) {
                 println!("redshift: {}", format!("{}", cosmo.inverse_age(value)).bold().green());
             } else {
-                println!("{} Gyr", format!("{}", cosmo.age(value)).bold().green());
+                let z = to_redshift(sub_matches, &cosmo, value)?;
+                let error = propagate_error(|z| cosmo.age(z), z, parse_redshift_error(sub_matches, z)?);
+                println!("{} Gyr", format_with_error(cosmo.age(z), error, None).bold().green());
             }
         }
 
         Some(("distmod", sub_matches)) => {
-            let redshift= try_parse_string_to_f64(sub_matches.get_one::<String>("z").unwrap());
-            let cosmo = parse_cosmo_parameters(sub_matches);
-            println!("distance modulus = {}", format!("{}", cosmo.distance_modulus(redshift)).bold().green());
+            let cosmo = parse_cosmology(sub_matches)?;
+            let redshift = parse_redshift(sub_matches, &cosmo, sub_matches.get_one::<String>("z").unwrap())?;
+            let error = propagate_error(|z| cosmo.distance_modulus(z), redshift, parse_redshift_error(sub_matches, redshift)?);
+            println!("distance modulus = {}", format_with_error(cosmo.distance_modulus(redshift), error, None).bold().green());
+            if let Some(magnitude) = sub_matches.get_one::<String>("apparent_magnitude") {
+                let magnitude = try_parse_string_to_f64(magnitude)?;
+                println!(
+                    "absolute magnitude = {}",
+                    format_with_error(magnitude - cosmo.distance_modulus(redshift), error, None).bold().green()
+                );
+            }
         }
 
         Some(("angscale_phys", sub_matches)) => {
-            let cosmo = parse_cosmo_parameters(sub_matches);
-            let redshift: f64 =
-                try_parse_string_to_f64(sub_matches.get_one::<String>("z").unwrap());
+            let cosmo = parse_cosmology(sub_matches)?;
+            let redshift = parse_redshift(sub_matches, &cosmo, sub_matches.get_one::<String>("z").unwrap())?;
+            let z_err = parse_redshift_error(sub_matches, redshift)?;
+            let error = propagate_error(|z| cosmo.kpc_per_arcsecond_physical(z), redshift, z_err);
             if *sub_matches.get_one::<bool>("mpc").unwrap_or(&false) {
                 println!(
                     "Angular scale = {} pMpc/arcmin",
-                    format!("{}", cosmo.kpc_per_arcsecond_physical(redshift) * 60. / 1e3).bold().green()
+                    format_with_error(
+                        cosmo.kpc_per_arcsecond_physical(redshift) * 60. / 1e3,
+                        error.map(|e| e * 60. / 1e3),
+                        None
+                    )
+                    .bold()
+                    .green()
                 )
             } else {
                 println!(
                     "Angular scale = {} pkpc/arcsec",
-                    format!("{}", cosmo.kpc_per_arcsecond_physical(redshift)).bold().green()
+                    format_with_error(cosmo.kpc_per_arcsecond_physical(redshift), error, None).bold().green()
                 )
             }
+            if let Some(size) = sub_matches.get_one::<String>("size") {
+                let size = try_parse_string_to_f64(size)?;
+                println!(
+                    "Size = {} pkpc",
+                    format_with_error(
+                        cosmo.kpc_per_arcsecond_physical(redshift) * size,
+                        error.map(|e| e * size),
+                        None
+                    )
+                    .bold()
+                    .green()
+                );
+            }
         }
 
         Some(("angscale_co", sub_matches)) => {
-            let cosmo = parse_cosmo_parameters(sub_matches);
-            let redshift: f64 =
-                try_parse_string_to_f64(sub_matches.get_one::<String>("z").unwrap());
+            let cosmo = parse_cosmology(sub_matches)?;
+            let redshift = parse_redshift(sub_matches, &cosmo, sub_matches.get_one::<String>("z").unwrap())?;
+            let z_err = parse_redshift_error(sub_matches, redshift)?;
+            let error = propagate_error(|z| cosmo.kpc_per_arcsecond_comoving(z), redshift, z_err);
             if *sub_matches.get_one::<bool>("mpc").unwrap_or(&false) {
                 println!(
                     "Angular scale = {} cMpc/arcmin",
-                    format!("{}", cosmo.kpc_per_arcsecond_comoving(redshift) * 60. / 1e3).bold().green()
+                    format_with_error(
+                        cosmo.kpc_per_arcsecond_comoving(redshift) * 60. / 1e3,
+                        error.map(|e| e * 60. / 1e3),
+                        None
+                    )
+                    .bold()
+                    .green()
                 )
             } else {
                 println!(
                     "Angular scale = {} ckpc/arcsec",
-                    format!("{}", cosmo.kpc_per_arcsecond_comoving(redshift)).bold().green()
+                    format_with_error(cosmo.kpc_per_arcsecond_comoving(redshift), error, None).bold().green()
                 )
             }
+            if let Some(size) = sub_matches.get_one::<String>("size") {
+                let size = try_parse_string_to_f64(size)?;
+                println!(
+                    "Size = {} ckpc",
+                    format_with_error(
+                        cosmo.kpc_per_arcsecond_comoving(redshift) * size,
+                        error.map(|e| e * size),
+                        None
+                    )
+                    .bold()
+                    .green()
+                );
+            }
+        }
+
+        Some(("horizons", sub_matches)) => {
+            let cosmo = parse_cosmology(sub_matches)?;
+            let redshift = parse_redshift(sub_matches, &cosmo, sub_matches.get_one::<String>("z").unwrap())?;
+            print_horizons(&cosmo, redshift, parse_redshift_error(sub_matches, redshift)?);
+        }
+
+        Some(("cmb", sub_matches)) => {
+            let cosmo = parse_cosmology(sub_matches)?;
+            let early = parse_early_universe_parameters(sub_matches)?;
+            let z_star = early.z_recombination(&cosmo);
+            let z_drag = early.z_drag(&cosmo);
+            println!(
+                "Matter-radiation equality: z = {}",
+                format!("{:.4}", early.z_equality(&cosmo)).bold().green()
+            );
+            println!("Recombination: z = {}", format!("{:.4}", z_star).bold().green());
+            println!("Drag epoch: z = {}", format!("{:.4}", z_drag).bold().green());
+            println!();
+            println!(
+                "Sound horizon at recombination r_s(z*): {} Mpc",
+                format!("{:.4}", early.sound_horizon(&cosmo, z_star)).bold().green()
+            );
+            println!(
+                "Sound horizon at drag epoch r_d: {} Mpc",
+                format!("{:.4}", early.sound_horizon(&cosmo, z_drag)).bold().green()
+            );
+            println!();
+            println!(
+                "Shift parameter R: {}",
+                format!("{:.4}", early.shift_parameter(&cosmo)).bold().green()
+            );
+            println!(
+                "Acoustic scale ℓ_A: {}",
+                format!("{:.4}", early.acoustic_scale(&cosmo)).bold().green()
+            );
+        }
+
+        Some(("bao", sub_matches)) => {
+            let cosmo = parse_cosmology(sub_matches)?;
+            let mut redshifts: Vec<f64> = match sub_matches.get_many::<String>("z") {
+                Some(values) => values.map(|z| parse_redshift(sub_matches, &cosmo, z)).collect::<Result<_, _>>()?,
+                None => Vec::new(),
+            };
+            if let Some(survey) = sub_matches.get_one::<String>("survey") {
+                redshifts.extend_from_slice(bao::survey_redshifts(survey).unwrap());
+            }
+            let r_d = match sub_matches.get_one::<String>("r_drag") {
+                Some(rd) => try_parse_string_to_f64(rd)?,
+                None => {
+                    let early = parse_early_universe_parameters(sub_matches)?;
+                    early.sound_horizon(&cosmo, early.z_drag(&cosmo))
+                }
+            };
+            println!("r_d: {} Mpc", format!("{:.4}", r_d).bold().green());
+            println!();
+            println!(
+                "{:>8} {:>10} {:>10} {:>10} {:>10}",
+                "z", "D_M/r_d", "D_H/r_d", "D_V/r_d", "F_AP"
+            );
+            for z in redshifts {
+                println!(
+                    "{:>8} {} {} {} {}",
+                    z,
+                    format!("{:>10.4}", cosmo.comoving_transverse_distance(z) / r_d).bold().green(),
+                    format!("{:>10.4}", background::hubble_radius(&cosmo, z) / r_d).bold().green(),
+                    format!("{:>10.4}", bao::volume_averaged_distance(&cosmo, z) / r_d).bold().green(),
+                    format!("{:>10.4}", bao::alcock_paczynski(&cosmo, z)).bold().green()
+                );
+            }
+        }
+
+        Some(("growth", sub_matches)) => {
+            let cosmo = parse_cosmology(sub_matches)?;
+            let redshift = parse_redshift(sub_matches, &cosmo, sub_matches.get_one::<String>("z").unwrap())?;
+            let early = *sub_matches.get_one::<bool>("early").unwrap_or(&false);
+            print_growth(&cosmo, redshift, parse_redshift_error(sub_matches, redshift)?, parse_sigma8(sub_matches)?, early);
         }
 
+        Some(("power", sub_matches)) => {
+            let cosmo = parse_cosmology(sub_matches)?;
+            let redshift = match sub_matches.get_one::<String>("z") {
+                Some(z) => parse_redshift(sub_matches, &cosmo, z)?,
+                None => 0.,
+            };
+            let power = parse_linear_power(sub_matches, &cosmo)?;
+            let radii = sub_matches.get_many::<String>("radius");
+            let masses = sub_matches.get_many::<String>("mass");
+            if radii.is_none() && masses.is_none() {
+                let k_min = match sub_matches.get_one::<String>("k_min") {
+                    Some(k) => try_parse_string_to_f64(k)?,
+                    None => 1e-4,
+                };
+                let k_max = match sub_matches.get_one::<String>("k_max") {
+                    Some(k) => try_parse_string_to_f64(k)?,
+                    None => 10.,
+                };
+                let n_k = match sub_matches.get_one::<String>("n_k") {
+                    Some(n) => try_parse_string_to_f64(n)? as usize,
+                    None => 50,
+                };
+                println!("{:>14} {:>14}", "k [h/Mpc]", "P(k) [(Mpc/h)³]");
+                for i in 0..n_k {
+                    let fraction = if n_k > 1 { i as f64 / (n_k - 1) as f64 } else { 0. };
+                    let k = k_min * (k_max / k_min).powf(fraction);
+                    println!(
+                        "{:>14.6e} {}",
+                        k,
+                        format!("{:>14.6e}", power.power(k, redshift)).bold().green()
+                    );
+                }
+            }
+            if let Some(radii) = radii {
+                for radius in radii.map(try_parse_string_to_f64).collect::<Result<Vec<_>, _>>()? {
+                    println!(
+                        "σ(R = {} Mpc/h): {}",
+                        radius,
+                        format!("{:.4}", power.sigma_r(radius, redshift)).bold().green()
+                    );
+                }
+            }
+            if let Some(masses) = masses {
+                for mass in masses.map(try_parse_string_to_f64).collect::<Result<Vec<_>, _>>()? {
+                    println!(
+                        "σ(M = {:e} M_sun/h): {}",
+                        mass,
+                        format!("{:.4}", power.sigma_m(mass, redshift)).bold().green()
+                    );
+                }
+            }
+        }
+
+        Some(("halo", halo_matches)) => match halo_matches.subcommand() {
+            Some(("massfunc", sub_matches)) => {
+                let cosmo = parse_cosmology(sub_matches)?;
+                let redshift = match sub_matches.get_one::<String>("z") {
+                    Some(z) => parse_redshift(sub_matches, &cosmo, z)?,
+                    None => 0.,
+                };
+                let power = parse_linear_power(sub_matches, &cosmo)?;
+                let m_min = match sub_matches.get_one::<String>("m_min") {
+                    Some(m) => try_parse_string_to_f64(m)?,
+                    None => 1e10,
+                };
+                let m_max = match sub_matches.get_one::<String>("m_max") {
+                    Some(m) => try_parse_string_to_f64(m)?,
+                    None => 1e15,
+                };
+                let n_m = match sub_matches.get_one::<String>("n_m") {
+                    Some(n) => try_parse_string_to_f64(n)? as usize,
+                    None => 11,
+                };
+                println!(
+                    "{:>12} {:>10} {:>12} {:>12} {:>12}",
+                    "M [M_sun/h]", "σ(M)", "PS", "ST", "Tinker08"
+                );
+                for i in 0..n_m {
+                    let fraction = if n_m > 1 { i as f64 / (n_m - 1) as f64 } else { 0. };
+                    let mass = m_min * (m_max / m_min).powf(fraction);
+                    let models = [
+                        MassFunctionModel::PressSchechter,
+                        MassFunctionModel::ShethTormen,
+                        MassFunctionModel::Tinker08,
+                    ];
+                    let columns: Vec<String> = models
+                        .iter()
+                        .map(|model| {
+                            let dn = halo::mass_function(&power, *model, mass, redshift);
+                            format!("{:>12.4e}", dn).bold().green().to_string()
+                        })
+                        .collect();
+                    println!(
+                        "{:>12.4e} {:>10.4} {}",
+                        mass,
+                        power.sigma_m(mass, redshift),
+                        columns.join(" ")
+                    );
+                }
+            }
+
+            Some(("props", sub_matches)) => {
+                let mass = try_parse_string_to_f64(sub_matches.get_one::<String>("mass").unwrap())?;
+                let cosmo = parse_cosmology(sub_matches)?;
+                let redshift = match sub_matches.get_one::<String>("z") {
+                    Some(z) => parse_redshift(sub_matches, &cosmo, z)?,
+                    None => 0.,
+                };
+                for (i, definition) in [
+                    Overdensity::Critical200,
+                    Overdensity::Mean200,
+                    Overdensity::Virial,
+                ]
+                .iter()
+                .enumerate()
+                {
+                    if i > 0 {
+                        println!();
+                    }
+                    let name = definition.name();
+                    let radius = definition.radius(&cosmo, mass, redshift);
+                    let concentration = definition.concentration(mass, redshift);
+                    println!(
+                        "Δ_{} (w.r.t. critical): {}",
+                        name,
+                        format!("{:.4}", definition.delta_critical(&cosmo, redshift)).bold().green()
+                    );
+                    println!(
+                        "R_{}: {} pkpc/h",
+                        name,
+                        format!("{:.4}", radius * 1e3).bold().green()
+                    );
+                    println!(
+                        "c_{}: {}",
+                        name,
+                        format!("{:.4}", concentration).bold().green()
+                    );
+                    println!(
+                        "r_s: {} pkpc/h",
+                        format!("{:.4}", radius * 1e3 / concentration).bold().green()
+                    );
+                    println!(
+                        "V_{}: {} km/s",
+                        name,
+                        format!("{:.4}", definition.circular_velocity(&cosmo, mass, redshift)).bold().green()
+                    );
+                }
+            }
+
+            Some(("counts", sub_matches)) => {
+                let cosmo = parse_cosmology(sub_matches)?;
+                let m_min = try_parse_string_to_f64(sub_matches.get_one::<String>("m_min").unwrap())?;
+                let z_min = match sub_matches.get_one::<String>("z_min") {
+                    Some(z) => parse_redshift(sub_matches, &cosmo, z)?,
+                    None => 0.,
+                };
+                let z_max = parse_redshift(sub_matches, &cosmo, sub_matches.get_one::<String>("z_max").unwrap())?;
+                let area = match sub_matches.get_one::<String>("area") {
+                    Some(a) => try_parse_string_to_f64(a)?,
+                    None => 4. * std::f64::consts::PI * (180. / std::f64::consts::PI).powi(2),
+                };
+                let model = match sub_matches.get_one::<String>("model") {
+                    Some(name) => MassFunctionModel::from_name(name).unwrap(),
+                    None => MassFunctionModel::Tinker08,
+                };
+                let power = parse_linear_power(sub_matches, &cosmo)?;
+                println!(
+                    "Number of halos: {}",
+                    format!("{:.4}", halo::number_counts(&power, model, m_min, z_min, z_max, area))
+                        .bold()
+                        .green()
+                );
+            }
+
+            _ => println!("Command not recognized"),
+        },
+
+        Some(("velocity", velocity_matches)) => match velocity_matches.subcommand() {
+            Some(("convert", sub_matches)) => {
+                let value = try_parse_string_to_f64(sub_matches.get_one::<String>("input").unwrap())?;
+                if *sub_matches.get_one::<bool>("inverse").unwrap_or(&false) {
+                    let convention = match sub_matches.get_one::<String>("convention") {
+                        Some(name) => VelocityConvention::from_name(name).unwrap(),
+                        None => VelocityConvention::Relativistic,
+                    };
+                    println!("redshift: {}", format!("{}", convention.redshift(value)).bold().green());
+                } else {
+                    println!(
+                        "Relativistic: {} km/s",
+                        format!("{:.4}", VelocityConvention::Relativistic.velocity(value)).bold().green()
+                    );
+                    println!(
+                        "Optical (cz): {} km/s",
+                        format!("{:.4}", VelocityConvention::Optical.velocity(value)).bold().green()
+                    );
+                    println!(
+                        "Radio (cz/(1+z)): {} km/s",
+                        format!("{:.4}", VelocityConvention::Radio.velocity(value)).bold().green()
+                    );
+                }
+            }
+
+            Some(("combine", sub_matches)) => {
+                let value = try_parse_string_to_f64(sub_matches.get_one::<String>("input").unwrap())?;
+                let v_pec = try_parse_string_to_f64(sub_matches.get_one::<String>("v_pec").unwrap())?;
+                let z_pec = VelocityConvention::Relativistic.redshift(v_pec);
+                println!("Peculiar redshift: {}", format!("{}", z_pec).bold().green());
+                if *sub_matches.get_one::<bool>("inverse").unwrap_or(&false) {
+                    println!(
+                        "Cosmological redshift: {}",
+                        format!("{}", velocity::cosmological_redshift(value, z_pec)).bold().green()
+                    );
+                } else {
+                    println!(
+                        "Observed redshift: {}",
+                        format!("{}", velocity::combine_redshifts(value, z_pec)).bold().green()
+                    );
+                }
+            }
+
+            Some(("cmb", sub_matches)) => {
+                let value = try_parse_string_to_f64(sub_matches.get_one::<String>("input").unwrap())?;
+                let ra = try_parse_string_to_f64(sub_matches.get_one::<String>("ra").unwrap())?;
+                let dec = try_parse_string_to_f64(sub_matches.get_one::<String>("dec").unwrap())?;
+                println!(
+                    "Solar motion towards target: {} km/s",
+                    format!("{:.4}", velocity::solar_cmb_velocity(ra, dec)).bold().green()
+                );
+                if *sub_matches.get_one::<bool>("inverse").unwrap_or(&false) {
+                    println!(
+                        "Heliocentric redshift: {}",
+                        format!("{}", velocity::cmb_to_heliocentric(value, ra, dec)).bold().green()
+                    );
+                } else {
+                    println!(
+                        "CMB frame redshift: {}",
+                        format!("{}", velocity::heliocentric_to_cmb(value, ra, dec)).bold().green()
+                    );
+                }
+            }
+
+            _ => println!("Command not recognized"),
+        },
+
+        Some(("coords", coords_matches)) => match coords_matches.subcommand() {
+            Some(("cartesian", sub_matches)) => {
+                let cosmo = parse_cosmology(sub_matches)?;
+                let frame = parse_frame(sub_matches);
+                let value = try_parse_string_to_f64(sub_matches.get_one::<String>("input").unwrap())?;
+                let ra = try_parse_string_to_f64(sub_matches.get_one::<String>("ra").unwrap())?;
+                let dec = try_parse_string_to_f64(sub_matches.get_one::<String>("dec").unwrap())?;
+                if *sub_matches.get_one::<bool>("inverse").unwrap_or(&false) {
+                    let (ra, dec, z) = coords::sky_position(&cosmo, [value, ra, dec], frame);
+                    if z.is_nan() {
+                        return Err(format!(
+                            "The position must be within {:.4} Mpc, the comoving distance to z = {}.",
+                            cosmo.comoving_distance(background::MAX_INVERSE_REDSHIFT),
+                            background::MAX_INVERSE_REDSHIFT
+                        ));
+                    }
+                    println!("RA: {} deg", format!("{:.6}", ra).bold().green());
+                    println!("Dec: {} deg", format!("{:.6}", dec).bold().green());
+                    println!("redshift: {}", format!("{}", z).bold().green());
+                } else {
+                    let value = to_redshift(sub_matches, &cosmo, value)?;
+                    if value < 0. {
+                        return Err("Redshift must be positive.".to_string());
+                    }
+                    let [x, y, z] = coords::cartesian(&cosmo, value, ra, dec, frame);
+                    println!("X: {} Mpc", format!("{:.4}", x).bold().green());
+                    println!("Y: {} Mpc", format!("{:.4}", y).bold().green());
+                    println!("Z: {} Mpc", format!("{:.4}", z).bold().green());
+                }
+            }
+
+            Some(("separation", sub_matches)) => {
+                let cosmo = parse_cosmology(sub_matches)?;
+                let position = |suffix: &str| {
+                    let value = |name: &str| {
+                        try_parse_string_to_f64(sub_matches.get_one::<String>(&format!("{}{}", name, suffix)).unwrap())
+                    };
+                    let z = to_redshift(sub_matches, &cosmo, value("z")?)?;
+                    if z < 0. {
+                        return Err("Redshift must be positive.".to_string());
+                    }
+                    Ok(coords::cartesian(&cosmo, z, value("ra")?, value("dec")?, sky::Frame::Equatorial))
+                };
+                let separation = coords::separation(position("1")?, position("2")?);
+                println!("Comoving separation: {} Mpc", format!("{:.4}", separation.comoving).bold().green());
+                println!("Projected (r_p): {} Mpc", format!("{:.4}", separation.projected).bold().green());
+                println!("Line of sight (π): {} Mpc", format!("{:.4}", separation.line_of_sight).bold().green());
+            }
+
+            Some(("pair", sub_matches)) => {
+                let cosmo = parse_cosmology(sub_matches)?;
+                let object = |suffix: &str| {
+                    let value = |name: &str| {
+                        try_parse_string_to_f64(sub_matches.get_one::<String>(&format!("{}{}", name, suffix)).unwrap())
+                    };
+                    let z = to_redshift(sub_matches, &cosmo, value("z")?)?;
+                    if z < 0. {
+                        return Err("Redshift must be positive.".to_string());
+                    }
+                    Ok(pairs::Object { ra: value("ra")?, dec: value("dec")?, z })
+                };
+                let separation = pairs::separation(&cosmo, &object("1")?, &object("2")?);
+                println!("Angular separation: {} arcsec", format!("{:.4}", separation.angle).bold().green());
+                println!("Projected physical: {} kpc", format!("{:.4}", separation.physical).bold().green());
+                println!("Projected comoving: {} kpc", format!("{:.4}", separation.comoving).bold().green());
+                println!("Velocity difference: {} km/s", format!("{:.4}", separation.velocity).bold().green());
+            }
+
+            Some(("pairs", sub_matches)) => {
+                let cosmo = parse_cosmology(sub_matches)?;
+                let input = Path::new(sub_matches.get_one::<String>("input").unwrap());
+                let output = Path::new(sub_matches.get_one::<String>("output").unwrap());
+                let column = |id: &str, default: &str| match sub_matches.get_one::<String>(id) {
+                    Some(name) => name.clone(),
+                    None => default.to_string(),
+                };
+                let columns = catalog::ObjectColumns {
+                    z_column: column("z_column", "z"),
+                    ra_column: column("ra_column", "ra"),
+                    dec_column: column("dec_column", "dec"),
+                    id_column: sub_matches.get_one::<String>("id_column").cloned(),
+                };
+                let limits = pairs::Limits {
+                    projected: try_parse_string_to_f64(sub_matches.get_one::<String>("rp").unwrap())?,
+                    velocity: try_parse_string_to_f64(sub_matches.get_one::<String>("dv").unwrap())?,
+                    comoving: sub_matches.get_flag("comoving"),
+                };
+                if limits.projected <= 0. || limits.velocity <= 0. {
+                    return Err("The separation and velocity limits must be positive.".to_string());
+                }
+                let hdu = sub_matches.get_one::<usize>("hdu").copied();
+                let result = catalog::read_objects(input, &columns, hdu).and_then(|(objects, ids)| {
+                    let found = pairs::find(&cosmo, &objects, &limits);
+                    catalog::write_pairs(output, &found, ids.as_deref()).map(|_| found.len())
+                });
+                let count = result?;
+                println!("Wrote {} pairs to {}", count, output.display().to_string().bold().green());
+            }
+
+            _ => println!("Command not recognized"),
+        },
+
+        Some(("sim", sim_matches)) => match sim_matches.subcommand() {
+            Some(("box", sub_matches)) => {
+                let cosmo = parse_cosmology(sub_matches)?;
+                let z = parse_redshift(sub_matches, &cosmo, sub_matches.get_one::<String>("z").unwrap())?;
+                let side = try_parse_string_to_f64(sub_matches.get_one::<String>("side").unwrap())?;
+                if z < 0. || side <= 0. {
+                    return Err("The redshift and box side must be positive.".to_string());
+                }
+                let (near, far) = sim::box_redshift_range(&cosmo, z, side);
+                println!(
+                    "Comoving distance: {} Mpc/h",
+                    format!("{:.4}", cosmo.comoving_distance(z) * sim::little_h(&cosmo)).bold().green()
+                );
+                println!("Near face: z = {}", format!("{:.6}", near).bold().green());
+                println!("Far face: z = {}", format!("{:.6}", far).bold().green());
+                println!("Δz: {}", format!("{:.6}", far - near).bold().green());
+            }
+
+            Some(("lightcone", sub_matches)) => {
+                let cosmo = parse_cosmology(sub_matches)?;
+                let side = try_parse_string_to_f64(sub_matches.get_one::<String>("side").unwrap())?;
+                let z_max = parse_redshift(sub_matches, &cosmo, sub_matches.get_one::<String>("z_max").unwrap())?;
+                let area = match sub_matches.get_one::<String>("area") {
+                    Some(a) => try_parse_string_to_f64(a)?,
+                    None => 4. * std::f64::consts::PI * (180. / std::f64::consts::PI).powi(2),
+                };
+                if side <= 0. || z_max <= 0. || area <= 0. {
+                    return Err("The box side, redshift and area must be positive.".to_string());
+                }
+                let lightcone = sim::lightcone(&cosmo, side, z_max, area);
+                println!("Depth: {} Mpc/h", format!("{:.4}", lightcone.depth).bold().green());
+                println!("Volume: {} (Mpc/h)³", format!("{:.4e}", lightcone.volume).bold().green());
+                println!("Box volumes: {}", format!("{:.4}", lightcone.volume / side.powi(3)).bold().green());
+                println!("Boxes along the line of sight: {}", format!("{}", lightcone.along).bold().green());
+                println!("Replications: {}", format!("{}", lightcone.replications).bold().green());
+            }
+
+            Some(("snapshots", sub_matches)) => {
+                let cosmo = parse_cosmology(sub_matches)?;
+                let values: Vec<f64> = sub_matches.get_many::<String>("input").unwrap().map(try_parse_string_to_f64).collect::<Result<_, _>>()?;
+                if values.iter().any(|value| *value < 0.) {
+                    return Err("Redshifts and distances must be positive.".to_string());
+                }
+                if *sub_matches.get_one::<bool>("inverse").unwrap_or(&false) {
+                    println!("{:>12} {:>10} {:>10}", "D_C [Mpc/h]", "z", "a");
+                    for distance in values {
+                        let z = sim::redshift_at_distance(&cosmo, distance);
+                        println!(
+                            "{:>12} {} {}",
+                            distance,
+                            format!("{:>10.6}", z).bold().green(),
+                            format!("{:>10.6}", 1. / (1. + z)).bold().green()
+                        );
+                    }
+                } else {
+                    println!("{:>8} {:>10} {:>12} {:>12} {:>12}", "z", "a", "D_C [Mpc/h]", "inner", "outer");
+                    let redshifts: Vec<f64> = values.iter().map(|value| to_redshift(sub_matches, &cosmo, *value)).collect::<Result<_, _>>()?;
+                    for shell in sim::shells(&cosmo, &redshifts) {
+                        println!(
+                            "{:>8} {:>10.6} {} {} {}",
+                            shell.z,
+                            1. / (1. + shell.z),
+                            format!("{:>12.4}", shell.distance).bold().green(),
+                            format!("{:>12.4}", shell.inner).bold().green(),
+                            format!("{:>12.4}", shell.outer).bold().green()
+                        );
+                    }
+                }
+            }
+
+            Some(("outputs", sub_matches)) => {
+                let cosmo = parse_cosmology(sub_matches)?;
+                let lookback = sub_matches.get_flag("lookback");
+                let input = if lookback { background::Input::LookbackTime } else { background::Input::Age };
+                let times: Vec<f64> = sub_matches.get_many::<String>("times").unwrap().map(try_parse_string_to_f64).collect::<Result<_, _>>()?;
+                for &time in &times {
+                    input.check(&cosmo, time)?;
+                }
+                println!("{:>10} {:>10} {:>10}", if lookback { "t_L [Gyr]" } else { "t [Gyr]" }, "a", "z");
+                for time in times {
+                    let z = if lookback { cosmo.inverse_lookback_time(time) } else { cosmo.inverse_age(time) };
+                    println!(
+                        "{:>10} {} {}",
+                        time,
+                        format!("{:>10.6}", 1. / (1. + z)).bold().green(),
+                        format!("{:>10.6}", z).bold().green()
+                    );
+                }
+            }
+
+            _ => println!("Command not recognized"),
+        },
+
+        Some(("repl", sub_matches)) => {
+            repl::start(parse_cosmology(sub_matches)?);
+        },
+
+        Some(("catalog", sub_matches)) => {
+            let cosmo = parse_cosmology(sub_matches)?;
+            let input = Path::new(sub_matches.get_one::<String>("input").unwrap());
+            let output = Path::new(sub_matches.get_one::<String>("output").unwrap());
+            let column = |id: &str, default: &str| match sub_matches.get_one::<String>(id) {
+                Some(name) => name.clone(),
+                None => default.to_string(),
+            };
+            let hdu = sub_matches.get_one::<usize>("hdu").copied();
+            let mut columns = Vec::new();
+            for name in sub_matches.get_many::<String>("add").into_iter().flatten() {
+                match quantity::find(name) {
+                    Some(quantity) => columns.push(catalog::NewColumn { name: name.clone(), quantity }),
+                    None => {
+                        let names: Vec<&str> = quantity::QUANTITIES.iter().map(|quantity| quantity.name).collect();
+                        return Err(format!("Unknown quantity {:?}. Choose from {}.", name, names.join(", ")));
+                    }
+                }
+            }
+            if input == output {
+                return Err("The output file must differ from the input file.".to_string());
+            }
+            let rtol = sub_matches.get_one::<String>("interpolate").map(|rtol| {
+                let rtol = try_parse_string_to_f64(rtol)?;
+                if rtol <= 0. {
+                    return Err("The interpolation tolerance must be positive.".to_string());
+                }
+                Ok(rtol)
+            }).transpose()?;
+            if let Some(threads) = sub_matches.get_one::<String>("threads") {
+                let threads = try_parse_string_to_f64(threads)? as usize;
+                if rayon::ThreadPoolBuilder::new().num_threads(threads).build_global().is_err() {
+                    return Err(format!("Could not start {} threads.", threads));
+                }
+            }
+            let format = |path: &Path| {
+                catalog::Format::from_path(path).ok_or_else(|| {
+                    format!("Unknown catalogue format {}. Use .fits, .csv, .parquet, .arrow or .vot files.", path.display())
+                })
+            };
+            let positions = sub_matches.get_flag("xyz").then(|| catalog::Positions {
+                ra_column: column("ra_column", "ra"),
+                dec_column: column("dec_column", "dec"),
+                frame: parse_frame(sub_matches),
+            });
+            // Column names given to a comma separated option, which must name all of them.
+            let names = |id: &str, option: &str, defaults: &[String]| -> Result<Vec<String>, String> {
+                let names: Vec<String> = match sub_matches.get_many::<String>(id) {
+                    Some(names) => names.cloned().collect(),
+                    None => defaults.to_vec(),
+                };
+                if names.len() != defaults.len() {
+                    return Err(format!("Give {} comma separated column names to {}.", defaults.len(), option));
+                }
+                Ok(names)
+            };
+            let sky = sub_matches.get_flag("sky").then(|| -> Result<_, String> {
+                let frame = parse_frame(sub_matches);
+                let columns = names("xyz_columns", "--xyzcols", &catalog::cartesian_names(frame))?;
+                Ok(catalog::SkyPositions { columns: [columns[0].clone(), columns[1].clone(), columns[2].clone()], frame })
+            }).transpose()?;
+            let separations = sub_matches.get_flag("separation").then(|| -> Result<_, String> {
+                let defaults = ["ra1", "dec1", "z1", "ra2", "dec2", "z2"].map(String::from);
+                let columns = names("separation_columns", "--sepcols", &defaults)?;
+                Ok(catalog::Separations {
+                    first: [columns[0].clone(), columns[1].clone(), columns[2].clone()],
+                    second: [columns[3].clone(), columns[4].clone(), columns[5].clone()],
+                })
+            }).transpose()?;
+            let additions = catalog::Additions { z_column: column("z_column", "z"), quantities: columns, positions, sky, separations, rtol };
+            let (input_format, output_format) = (format(input)?, format(output)?);
+            let result = match (input_format, output_format) {
+                (catalog::Format::Fits, catalog::Format::Fits) => {
+                    catalog::process_fits(input, output, &additions, &cosmo, hdu)
+                }
+                (catalog::Format::Fits, _) | (_, catalog::Format::Fits) => {
+                    Err("FITS catalogues can only be written to FITS files.".to_string())
+                }
+                _ => catalog::process_table(input, input_format, output, output_format, &additions, &cosmo),
+            };
+            let rows = result?;
+            println!("Wrote {} rows to {}", rows, output.display().to_string().bold().green());
+        },
+
+        Some(("eval", sub_matches)) => {
+            let cosmo = parse_cosmology(sub_matches)?;
+            let input: Vec<&str> = sub_matches
+                .get_many::<String>("expression")
+                .unwrap()
+                .map(String::as_str)
+                .collect();
+            let (value, unit) = expression::evaluate(&input.join(" "), &cosmo)?;
+            println!("{} {}", expression::format_number(value).bold().green(), unit);
+        },
+
+        Some(("plot", sub_matches)) => {
+            let (quantity, x, y, series) = parse_plot(sub_matches)?;
+            if let Some(out) = sub_matches.get_one::<String>("out") {
+                let path = Path::new(out);
+                let y_label = format!("{} [{}]", quantity.label, quantity.unit);
+                let svg = figure::svg(&series, &x, &y, "Redshift z", &y_label);
+                let result = match path.extension().and_then(|extension| extension.to_str()) {
+                    Some("svg") => std::fs::write(path, svg).map_err(|error| error.to_string()),
+                    Some("png") => figure::write_png(&svg, path),
+                    _ => Err("Figures can be written as .svg or .png files.".to_string()),
+                };
+                result.map_err(|message| format!("Could not write {}: {}", out, message))?;
+                println!("Figure written to {}", out.bold().green());
+                return Ok(());
+            }
+            let width = match sub_matches.get_one::<String>("width") {
+                Some(width) => try_parse_string_to_f64(width)? as usize,
+                None => 72,
+            };
+            let height = match sub_matches.get_one::<String>("height") {
+                Some(height) => try_parse_string_to_f64(height)? as usize,
+                None => 20,
+            };
+            println!("{} ({}) against redshift", quantity.label.bold(), quantity.unit);
+            print!("{}", plot::braille(&series, &x, &y, width.max(10), height.max(3)));
+        },
+
+        Some(("completions", sub_matches)) => {
+            let shell = *sub_matches.get_one::<Shell>("shell").unwrap();
+            clap_complete::generate(shell, &mut cli(), "cosmo", &mut io::stdout());
+        },
+
+        Some(("man", sub_matches)) => {
+            if let Some(dir) = sub_matches.get_one::<String>("dir") {
+                std::fs::create_dir_all(dir)
+                    .and_then(|()| clap_mangen::generate_to(cli(), dir))
+                    .map_err(|error| format!("Could not write man pages to {}: {}", dir, error))?;
+                println!("Man pages written to {}", dir.bold().green());
+                return Ok(());
+            }
+            let mut command = cli();
+            command.build();
+            if let Some(path) = sub_matches.get_many::<String>("subcommand") {
+                for sub in path {
+                    command = command
+                        .find_subcommand(sub)
+                        .ok_or_else(|| format!("Unknown sub-command {:?}", sub))?
+                        .clone();
+                }
+            }
+            clap_mangen::Man::new(command)
+                .render(&mut io::stdout())
+                .map_err(|error| format!("Could not write the man page: {}", error))?;
+        },
+
+        Some(("tui", sub_matches)) => {
+            let cosmo = parse_cosmology(sub_matches)?;
+            let z = match sub_matches.get_one::<String>("z") {
+                Some(z) => parse_redshift(sub_matches, &cosmo, z)?,
+                None => 1.,
+            };
+            tui::start(z, cosmo);
+        },
+
         _ => println!("Command not recognized"),
     }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn subcommand(args: &[&str]) -> ArgMatches {
+        let matches = cli().try_get_matches_from(args).unwrap();
+        matches.subcommand().unwrap().1.clone()
+    }
+
+    #[test]
+    fn from_selects_the_input() {
+        assert_eq!(parse_input(&subcommand(&["cosmo", "codist", "1"])), background::Input::Redshift);
+        assert_eq!(parse_input(&subcommand(&["cosmo", "codist", "--from", "a", "0.5"])), background::Input::ScaleFactor);
+        // --from is global, so it may also come before the subcommand.
+        assert_eq!(parse_input(&subcommand(&["cosmo", "--from", "dc", "lumdist", "1000"])), background::Input::ComovingDistance);
+        assert!(cli().try_get_matches_from(["cosmo", "codist", "--from", "redshift", "1"]).is_err());
+    }
+
+    #[test]
+    fn from_converts_arguments() {
+        let matches = subcommand(&["cosmo", "codist", "--from", "a", "0.25"]);
+        let cosmo = parse_cosmology(&matches).unwrap();
+        assert_eq!(parse_redshift(&matches, &cosmo, &"0.25".to_string()), Ok(3.));
+        assert!(parse_redshift(&matches, &cosmo, &"2".to_string()).is_err());
+    }
+
+    #[test]
+    fn from_is_rejected_with_inverse() {
+        let matches = cli().try_get_matches_from(["cosmo", "codist", "--from", "a", "--inverse", "1000"]).unwrap();
+        assert!(check_input(&matches).is_err());
+    }
 }
@@ -0,0 +1,223 @@
+/// Composite Simpson's rule over [a, b] using n (rounded up to even) intervals.
+pub fn simpson<F: Fn(f64) -> f64>(f: F, a: f64, b: f64, n: usize) -> f64 {
+    let n = n + n % 2;
+    let h = (b - a) / n as f64;
+    let mut sum = f(a) + f(b);
+    for i in 1..n {
+        let weight = if i % 2 == 1 { 4. } else { 2. };
+        sum += weight * f(a + i as f64 * h);
+    }
+    sum * h / 3.
+}
+
+/// Numerical derivative df/dx, falling back to a forward difference near x = 0 so that f is
+/// never evaluated at negative x.
+pub fn derivative<F: Fn(f64) -> f64>(f: F, x: f64) -> f64 {
+    let step = 1e-4 * (1. + x.abs());
+    if x - step < 0. {
+        (f(x + step) - f(x)) / step
+    } else {
+        (f(x + step) - f(x - step)) / (2. * step)
+    }
+}
+
+/// A numerical result with an estimate of its absolute error.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Estimate {
+    pub value: f64,
+    pub error: f64,
+}
+
+impl Estimate {
+    pub fn exact(value: f64) -> Estimate {
+        Estimate { value, error: 0. }
+    }
+
+    pub fn scaled(self, factor: f64) -> Estimate {
+        Estimate {
+            value: self.value * factor,
+            error: self.error * factor.abs(),
+        }
+    }
+
+    /// A function g of the estimate, given g and its derivative at the value, with the error
+    /// propagated linearly.
+    pub fn map(self, value: f64, slope: f64) -> Estimate {
+        Estimate {
+            value,
+            error: self.error * slope.abs(),
+        }
+    }
+}
+
+/// Most intervals adaptive quadrature splits the range into before giving up on the tolerance.
+const MAX_INTERVALS: usize = 2000;
+
+/// Kronrod nodes on [0, 1] of the 15 point rule, every other one also being a node of the
+/// embedded 7 point Gauss rule, and their weights.
+const KRONROD_NODES: [f64; 8] = [
+    0.9914553711208126,
+    0.9491079123427585,
+    0.8648644233597691,
+    0.7415311855993945,
+    0.5860872354676911,
+    0.4058451513773972,
+    0.20778495500789848,
+    0.,
+];
+const KRONROD_WEIGHTS: [f64; 8] = [
+    0.022935322010529224,
+    0.06309209262997856,
+    0.10479001032225019,
+    0.14065325971552592,
+    0.1690047266392679,
+    0.19035057806478542,
+    0.20443294007529889,
+    0.20948214108472782,
+];
+const GAUSS_WEIGHTS: [f64; 4] = [
+    0.1294849661688697,
+    0.27970539148927664,
+    0.3818300505051189,
+    0.4179591836734694,
+];
+
+/// The 15 point Gauss-Kronrod rule over [a, b], with the error estimated from the difference to
+/// the embedded Gauss rule as in QUADPACK.
+fn kronrod<F: Fn(f64) -> f64>(f: &F, a: f64, b: f64) -> Estimate {
+    let centre = (a + b) / 2.;
+    let half = (b - a) / 2.;
+    let values: Vec<(f64, f64)> = KRONROD_NODES[..7]
+        .iter()
+        .map(|x| (f(centre - half * x), f(centre + half * x)))
+        .collect();
+    let middle = f(centre);
+    let mut kronrod = KRONROD_WEIGHTS[7] * middle;
+    let mut gauss = GAUSS_WEIGHTS[3] * middle;
+    let mut absolute = KRONROD_WEIGHTS[7] * middle.abs();
+    for (i, (left, right)) in values.iter().enumerate() {
+        kronrod += KRONROD_WEIGHTS[i] * (left + right);
+        absolute += KRONROD_WEIGHTS[i] * (left.abs() + right.abs());
+        if i % 2 == 1 {
+            gauss += GAUSS_WEIGHTS[i / 2] * (left + right);
+        }
+    }
+    let mean = kronrod / 2.;
+    let deviation = KRONROD_WEIGHTS[7] * (middle - mean).abs()
+        + values
+            .iter()
+            .enumerate()
+            .map(|(i, (left, right))| {
+                KRONROD_WEIGHTS[i] * ((left - mean).abs() + (right - mean).abs())
+            })
+            .sum::<f64>();
+    let (absolute, deviation) = (absolute * half.abs(), deviation * half.abs());
+    let mut error = ((kronrod - gauss) * half).abs();
+    if deviation != 0. && error != 0. {
+        error = deviation * (200. * error / deviation).powf(1.5).min(1.);
+    }
+    Estimate {
+        value: kronrod * half,
+        error: error.max(50. * f64::EPSILON * absolute),
+    }
+}
+
+/// Adaptive Gauss-Kronrod quadrature of f over [a, b], bisecting the interval with the largest
+/// error estimate until the total error estimate is within a relative tolerance of the integral.
+pub fn gauss_kronrod<F: Fn(f64) -> f64>(f: F, a: f64, b: f64, rtol: f64) -> Estimate {
+    let mut intervals = vec![(a, b, kronrod(&f, a, b))];
+    loop {
+        let (value, error) = intervals
+            .iter()
+            .fold((0., 0.), |(value, error), (_, _, estimate)| {
+                (value + estimate.value, error + estimate.error)
+            });
+        if error <= rtol * f64::abs(value) || intervals.len() >= MAX_INTERVALS {
+            return Estimate { value, error };
+        }
+        let worst = (0..intervals.len())
+            .max_by(|i, j| intervals[*i].2.error.total_cmp(&intervals[*j].2.error))
+            .unwrap();
+        let (start, end, _) = intervals[worst];
+        let middle = (start + end) / 2.;
+        if middle <= start || middle >= end {
+            return Estimate { value, error };
+        }
+        intervals[worst] = (start, middle, kronrod(&f, start, middle));
+        intervals.push((middle, end, kronrod(&f, middle, end)));
+    }
+}
+
+/// Most iterations of Brent's method.
+const MAX_ITERATIONS: usize = 200;
+
+/// Root of f in [a, b] by Brent's method, combining bisection with secant and inverse quadratic
+/// steps, to an absolute tolerance that may depend on the root. Returns the root and the width
+/// of the bracket it was found in, or None if f doesn't change sign over [a, b].
+pub fn brent<F, T>(f: F, a: f64, b: f64, tolerance: T) -> Option<Estimate>
+where
+    F: Fn(f64) -> f64,
+    T: Fn(f64) -> f64,
+{
+    let (mut a, mut b) = (a, b);
+    let (mut fa, mut fb) = (f(a), f(b));
+    if fa * fb > 0. || fa.is_nan() || fb.is_nan() {
+        return None;
+    }
+    let (mut c, mut fc) = (b, fb);
+    let mut d = b - a;
+    let mut e = d;
+    for _ in 0..MAX_ITERATIONS {
+        if fb * fc > 0. {
+            (c, fc) = (a, fa);
+            d = b - a;
+            e = d;
+        }
+        if fc.abs() < fb.abs() {
+            (a, fa) = (b, fb);
+            (b, fb) = (c, fc);
+            (c, fc) = (a, fa);
+        }
+        let tol = 2. * f64::EPSILON * b.abs() + tolerance(b) / 2.;
+        let half = (c - b) / 2.;
+        if half.abs() <= tol || fb == 0. {
+            return Some(Estimate {
+                value: b,
+                error: (c - b).abs(),
+            });
+        }
+        if e.abs() >= tol && fa.abs() > fb.abs() {
+            let s = fb / fa;
+            let (mut p, mut q) = if a == c {
+                (2. * half * s, 1. - s)
+            } else {
+                let (q, r) = (fa / fc, fb / fc);
+                (
+                    s * (2. * half * q * (q - r) - (b - a) * (r - 1.)),
+                    (q - 1.) * (r - 1.) * (s - 1.),
+                )
+            };
+            if p > 0. {
+                q = -q;
+            }
+            p = p.abs();
+            if 2. * p < (3. * half * q - (tol * q).abs()).min((e * q).abs()) {
+                e = d;
+                d = p / q;
+            } else {
+                d = half;
+                e = d;
+            }
+        } else {
+            d = half;
+            e = d;
+        }
+        (a, fa) = (b, fb);
+        b += if d.abs() > tol { d } else { tol.copysign(half) };
+        fb = f(b);
+    }
+    Some(Estimate {
+        value: b,
+        error: (c - b).abs(),
+    })
+}
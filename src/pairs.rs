@@ -0,0 +1,315 @@
+use cosmoxide::Cosmology;
+use rayon::prelude::*;
+
+use crate::background::SPEED_OF_LIGHT;
+use crate::interpolation::Table;
+use crate::numerics::Estimate;
+use crate::precise::Precise;
+use crate::sky::{angular_separation, unit_vector};
+
+/// Relative tolerance of the angular scale tables used when searching catalogues. The tables are
+/// filled by adaptive quadrature a hundred times more accurate.
+const ANGSCALE_RTOL: f64 = 1e-8;
+
+/// An object at a J2000 sky position in degrees and a redshift.
+#[derive(Debug, Clone, Copy)]
+pub struct Object {
+    pub ra: f64,
+    pub dec: f64,
+    pub z: f64,
+}
+
+/// Separation of two objects on the sky and in velocity.
+#[derive(Debug, Clone, Copy)]
+pub struct PairSeparation {
+    /// Angular separation in arcsec.
+    pub angle: f64,
+    /// Projected physical separation in kpc at the mean redshift.
+    pub physical: f64,
+    /// Projected comoving separation in kpc at the mean redshift.
+    pub comoving: f64,
+    /// Rest frame line of sight velocity difference in km/s, c |z1 - z2| / (1 + z̄).
+    pub velocity: f64,
+}
+
+/// Line of sight velocity difference in km/s between two redshifts, in the rest frame of their
+/// mean.
+pub fn velocity_difference(z1: f64, z2: f64) -> f64 {
+    SPEED_OF_LIGHT * (z1 - z2).abs() / (1. + (z1 + z2) / 2.)
+}
+
+fn separation_with<P, C>(a: &Object, b: &Object, physical: P, comoving: C) -> PairSeparation
+where
+    P: Fn(f64) -> f64,
+    C: Fn(f64) -> f64,
+{
+    let angle = 3600. * angular_separation(unit_vector(a.ra, a.dec), unit_vector(b.ra, b.dec));
+    let z = (a.z + b.z) / 2.;
+    PairSeparation {
+        angle,
+        physical: angle * physical(z),
+        comoving: angle * comoving(z),
+        velocity: velocity_difference(a.z, b.z),
+    }
+}
+
+/// Projected separations and velocity difference of two objects, scaling the angle between them
+/// by the angular scale at their mean redshift.
+pub fn separation(cosmo: &Cosmology, a: &Object, b: &Object) -> PairSeparation {
+    separation_with(
+        a,
+        b,
+        |z| cosmo.kpc_per_arcsecond_physical(z),
+        |z| cosmo.kpc_per_arcsecond_comoving(z),
+    )
+}
+
+/// Thresholds for a pair of objects to count as close.
+#[derive(Debug, Clone, Copy)]
+pub struct Limits {
+    /// Largest projected separation in kpc.
+    pub projected: f64,
+    /// Largest line of sight velocity difference in km/s.
+    pub velocity: f64,
+    /// Whether the projected separation is comoving rather than physical.
+    pub comoving: bool,
+}
+
+/// Two objects of a catalogue, by index, within the limits of each other.
+#[derive(Debug, Clone, Copy)]
+pub struct Pair {
+    pub first: usize,
+    pub second: usize,
+    pub separation: PairSeparation,
+}
+
+/// A k-d tree of points in three dimensions, for finding those within a distance of a point.
+struct KdTree<'a> {
+    points: &'a [[f64; 3]],
+    /// Point indices ordered so that each range has its median as the splitting node.
+    order: Vec<usize>,
+}
+
+impl<'a> KdTree<'a> {
+    fn new(points: &'a [[f64; 3]], indices: Vec<usize>) -> KdTree<'a> {
+        let mut order = indices;
+        KdTree::build(points, &mut order, 0);
+        KdTree { points, order }
+    }
+
+    fn build(points: &[[f64; 3]], order: &mut [usize], axis: usize) {
+        if order.len() <= 1 {
+            return;
+        }
+        let middle = order.len() / 2;
+        order.select_nth_unstable_by(middle, |a, b| points[*a][axis].total_cmp(&points[*b][axis]));
+        let (left, right) = order.split_at_mut(middle);
+        KdTree::build(points, left, (axis + 1) % 3);
+        KdTree::build(points, &mut right[1..], (axis + 1) % 3);
+    }
+
+    /// Calls found with the index of every point within a distance of the centre.
+    fn within<F: FnMut(usize)>(&self, centre: [f64; 3], distance: f64, found: &mut F) {
+        self.search(&self.order, 0, centre, distance, found);
+    }
+
+    fn search<F: FnMut(usize)>(
+        &self,
+        order: &[usize],
+        axis: usize,
+        centre: [f64; 3],
+        distance: f64,
+        found: &mut F,
+    ) {
+        if order.is_empty() {
+            return;
+        }
+        let middle = order.len() / 2;
+        let point = self.points[order[middle]];
+        let squared: f64 = (0..3).map(|i| (point[i] - centre[i]).powi(2)).sum();
+        if squared <= distance * distance {
+            found(order[middle]);
+        }
+        if centre[axis] - distance <= point[axis] {
+            self.search(&order[..middle], (axis + 1) % 3, centre, distance, found);
+        }
+        if centre[axis] + distance >= point[axis] {
+            self.search(
+                &order[middle + 1..],
+                (axis + 1) % 3,
+                centre,
+                distance,
+                found,
+            );
+        }
+    }
+}
+
+/// Every pair of objects within the projected separation and velocity limits, with the first
+/// index of each pair below the second. Objects without a positive redshift or a sky position are
+/// skipped. The sky positions are searched with a k-d tree, out to the angle that the projected
+/// limit subtends at the nearest mean redshift a pair within the velocity limit could have.
+pub fn find(cosmo: &Cosmology, objects: &[Object], limits: &Limits) -> Vec<Pair> {
+    let valid: Vec<usize> = (0..objects.len())
+        .filter(|i| {
+            let object = objects[*i];
+            object.z.is_finite() && object.z > 0. && object.ra.is_finite() && object.dec.is_finite()
+        })
+        .collect();
+    if valid.is_empty() {
+        return Vec::new();
+    }
+    let (z_min, z_max) = valid.iter().fold((f64::INFINITY, 0_f64), |(min, max), i| {
+        (min.min(objects[*i].z), max.max(objects[*i].z))
+    });
+    let precise = Precise::new(cosmo, ANGSCALE_RTOL / 100.);
+    let tabulate = |f: fn(&Precise, f64) -> Estimate| {
+        Table::new(|z| f(&precise, z).value, z_min, z_max, ANGSCALE_RTOL)
+    };
+    let physical_table = tabulate(Precise::kpc_per_arcsecond_physical);
+    let comoving_table = tabulate(Precise::kpc_per_arcsecond_comoving);
+    let physical = |z: f64| match physical_table.as_ref().and_then(|table| table.get(z)) {
+        Some(value) => value,
+        None => cosmo.kpc_per_arcsecond_physical(z),
+    };
+    let comoving = |z: f64| match comoving_table.as_ref().and_then(|table| table.get(z)) {
+        Some(value) => value,
+        None => cosmo.kpc_per_arcsecond_comoving(z),
+    };
+    let scale = |z: f64| {
+        if limits.comoving {
+            comoving(z)
+        } else {
+            physical(z)
+        }
+    };
+
+    let directions: Vec<[f64; 3]> = objects
+        .iter()
+        .map(|object| unit_vector(object.ra, object.dec))
+        .collect();
+    let tree = KdTree::new(&directions, valid.clone());
+    let beta = limits.velocity / SPEED_OF_LIGHT;
+
+    let mut pairs: Vec<Pair> = valid
+        .par_iter()
+        .flat_map_iter(|i| {
+            let object = objects[*i];
+            // Range of mean redshifts of partners within the velocity limit. The angular scale
+            // has a single maximum, so its smallest value over the range is at one end.
+            let below = beta * (1. + object.z) / (1. + beta / 2.);
+            let above = beta * (1. + object.z) / (1. - beta / 2.);
+            let smallest = scale((object.z - below / 2.).max(0.)).min(scale(object.z + above / 2.));
+            let angle = (limits.projected / smallest / 3600.)
+                .to_radians()
+                .min(std::f64::consts::PI);
+            let mut found = Vec::new();
+            tree.within(
+                directions[*i],
+                2. * (angle / 2.).sin() * (1. + 1e-9),
+                &mut |j| {
+                    if j <= *i {
+                        return;
+                    }
+                    let separation = separation_with(&object, &objects[j], physical, comoving);
+                    let projected = if limits.comoving {
+                        separation.comoving
+                    } else {
+                        separation.physical
+                    };
+                    if projected <= limits.projected && separation.velocity <= limits.velocity {
+                        found.push(Pair {
+                            first: *i,
+                            second: j,
+                            separation,
+                        });
+                    }
+                },
+            );
+            found
+        })
+        .collect();
+    pairs.sort_by_key(|pair| (pair.first, pair.second));
+    pairs
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::rngs::StdRng;
+    use rand::{Rng, SeedableRng};
+
+    /// Objects in a 2 degree field at redshifts between 0.1 and 0.2.
+    fn catalogue(n: usize) -> Vec<Object> {
+        let mut rng = StdRng::seed_from_u64(1);
+        (0..n)
+            .map(|_| Object {
+                ra: 150. + rng.random_range(-1. ..1.),
+                dec: 2. + rng.random_range(-1. ..1.),
+                z: rng.random_range(0.1..0.2),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn tree_finds_the_same_points_as_brute_force() {
+        let points: Vec<[f64; 3]> = catalogue(500)
+            .iter()
+            .map(|object| unit_vector(object.ra, object.dec))
+            .collect();
+        let tree = KdTree::new(&points, (0..points.len()).collect());
+        for centre in points.iter().step_by(25) {
+            let distance = 5e-3;
+            let mut found = Vec::new();
+            tree.within(*centre, distance, &mut |i| found.push(i));
+            found.sort();
+            let expected: Vec<usize> = (0..points.len())
+                .filter(|i| {
+                    let squared: f64 = (0..3).map(|k| (points[*i][k] - centre[k]).powi(2)).sum();
+                    squared <= distance * distance
+                })
+                .collect();
+            assert_eq!(found, expected);
+        }
+    }
+
+    #[test]
+    fn find_matches_brute_force() {
+        let cosmo = Cosmology {
+            omega_m: 0.3,
+            omega_k: 0.,
+            omega_l: 0.7,
+            h0: 70.,
+        };
+        let objects = catalogue(400);
+        for comoving in [false, true] {
+            let limits = Limits {
+                projected: 500.,
+                velocity: 3000.,
+                comoving,
+            };
+            let found: Vec<(usize, usize)> = find(&cosmo, &objects, &limits)
+                .iter()
+                .map(|pair| (pair.first, pair.second))
+                .collect();
+            assert!(!found.is_empty());
+            for i in 0..objects.len() {
+                for j in i + 1..objects.len() {
+                    let separation = separation(&cosmo, &objects[i], &objects[j]);
+                    let projected = match comoving {
+                        true => separation.comoving,
+                        false => separation.physical,
+                    };
+                    // The tables and cosmoxide differ slightly, so pairs at the limit may go
+                    // either way.
+                    if (projected / limits.projected - 1.).abs() < 1e-4 {
+                        continue;
+                    }
+                    let close =
+                        projected <= limits.projected && separation.velocity <= limits.velocity;
+                    assert_eq!(found.contains(&(i, j)), close, "pair ({}, {})", i, j);
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,150 @@
+use crate::numerics::derivative;
+use crate::sampling::{Summary, summarise_weighted};
+
+/// Number of points the redshift PDF is interpolated onto before being transformed.
+const GRID_POINTS: usize = 2001;
+
+/// A redshift probability distribution sampled on a grid of increasing redshifts.
+pub struct RedshiftPdf {
+    pub z: Vec<f64>,
+    pub p: Vec<f64>,
+}
+
+impl RedshiftPdf {
+    /// Read a PDF from a file with z and p(z) columns separated by whitespace or commas.
+    /// Blank lines and lines starting with '#' are skipped.
+    pub fn from_file(path: &str) -> Result<RedshiftPdf, String> {
+        let contents = std::fs::read_to_string(path)
+            .map_err(|error| format!("Could not read PDF file {:?}: {}", path, error))?;
+        let mut rows: Vec<(f64, f64)> = Vec::new();
+        for line in contents.lines() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let columns: Vec<&str> = line
+                .split(|c: char| c.is_whitespace() || c == ',')
+                .filter(|token| !token.is_empty())
+                .collect();
+            if columns.len() < 2 {
+                return Err(format!("Line {:?} does not have z and p(z) columns.", line));
+            }
+            let parse = |token: &str| {
+                token
+                    .parse::<f64>()
+                    .map_err(|_| format!("Value: {:?} is not a valid number.", token))
+            };
+            rows.push((parse(columns[0])?, parse(columns[1])?));
+        }
+        rows.sort_by(|a, b| a.0.total_cmp(&b.0));
+        RedshiftPdf {
+            z: rows.iter().map(|row| row.0).collect(),
+            p: rows.iter().map(|row| row.1).collect(),
+        }
+        .normalised()
+    }
+
+    /// Gaussian PDF truncated at z = 0. A truncated grid starts one step above z = 0, where
+    /// distances vanish and magnitudes diverge.
+    pub fn gaussian(mean: f64, sigma: f64) -> Result<RedshiftPdf, String> {
+        if sigma <= 0. {
+            return Err("The redshift uncertainty of a Gaussian PDF must be positive.".to_string());
+        }
+        let z_max = mean + 5. * sigma;
+        if z_max <= 0. {
+            return Err("A Gaussian redshift PDF must extend above z = 0.".to_string());
+        }
+        let z: Vec<f64> = if mean - 5. * sigma > 0. {
+            let z_min = mean - 5. * sigma;
+            (0..GRID_POINTS)
+                .map(|i| z_min + (z_max - z_min) * i as f64 / (GRID_POINTS - 1) as f64)
+                .collect()
+        } else {
+            (1..=GRID_POINTS)
+                .map(|i| z_max * i as f64 / GRID_POINTS as f64)
+                .collect()
+        };
+        let p = z
+            .iter()
+            .map(|z| (-0.5 * ((z - mean) / sigma).powi(2)).exp())
+            .collect();
+        RedshiftPdf { z, p }.normalised()
+    }
+
+    fn normalised(self) -> Result<RedshiftPdf, String> {
+        if self.z.len() < 2 {
+            return Err("A redshift PDF needs at least two points.".to_string());
+        }
+        if self.z[0] < 0. || self.p.iter().any(|p| *p < 0.) {
+            return Err("Redshifts and probabilities of a PDF must not be negative.".to_string());
+        }
+        let norm: f64 = self.trapezoid_weights().iter().sum();
+        if norm <= 0. {
+            return Err("The redshift PDF integrates to zero.".to_string());
+        }
+        Ok(RedshiftPdf {
+            z: self.z,
+            p: self.p.iter().map(|p| p / norm).collect(),
+        })
+    }
+
+    /// Probability mass associated with each grid point.
+    fn trapezoid_weights(&self) -> Vec<f64> {
+        let n = self.z.len();
+        (0..n)
+            .map(|i| {
+                let left = if i > 0 { self.z[i] - self.z[i - 1] } else { 0. };
+                let right = if i + 1 < n {
+                    self.z[i + 1] - self.z[i]
+                } else {
+                    0.
+                };
+                self.p[i] * (left + right) / 2.
+            })
+            .collect()
+    }
+
+    /// Linearly interpolate onto a fine uniform grid.
+    fn refined(&self) -> RedshiftPdf {
+        let (z_min, z_max) = (self.z[0], self.z[self.z.len() - 1]);
+        let mut upper = 1;
+        let mut z = Vec::with_capacity(GRID_POINTS);
+        let mut p = Vec::with_capacity(GRID_POINTS);
+        for i in 0..GRID_POINTS {
+            let zi = z_min + (z_max - z_min) * i as f64 / (GRID_POINTS - 1) as f64;
+            while upper < self.z.len() - 1 && self.z[upper] < zi {
+                upper += 1;
+            }
+            let (z0, z1) = (self.z[upper - 1], self.z[upper]);
+            let t = if z1 > z0 { (zi - z0) / (z1 - z0) } else { 0. };
+            z.push(zi);
+            p.push(self.p[upper - 1] + t * (self.p[upper] - self.p[upper - 1]));
+        }
+        RedshiftPdf { z, p }
+    }
+
+    /// Summary statistics of f(z) for z drawn from the PDF. Points where f isn't finite, such as
+    /// a distance modulus at z = 0, are left out.
+    pub fn summary<F: Fn(f64) -> f64>(&self, f: F) -> Summary {
+        let fine = self.refined();
+        let (values, weights) = fine
+            .z
+            .iter()
+            .map(|z| f(*z))
+            .zip(fine.trapezoid_weights())
+            .filter(|(value, _)| value.is_finite())
+            .unzip();
+        summarise_weighted(values, weights)
+    }
+
+    /// The PDF of f at each redshift of the PDF, p(f) = p(z) / |df/dz|, as (z, f(z), p(f)) rows.
+    /// Points where f isn't finite are left out.
+    pub fn transform<F: Fn(f64) -> f64>(&self, f: F) -> Vec<(f64, f64, f64)> {
+        self.z
+            .iter()
+            .zip(self.p.iter())
+            .map(|(z, p)| (*z, f(*z), p / derivative(&f, *z).abs()))
+            .filter(|(_, value, _)| value.is_finite())
+            .collect()
+    }
+}
@@ -0,0 +1,204 @@
+use colored::{Color, Colorize};
+use cosmoxide::Cosmology;
+
+use crate::quantity::Quantity;
+
+/// Colours given to successive curves.
+pub const COLOURS: [Color; 6] = [
+    Color::Green,
+    Color::Cyan,
+    Color::Magenta,
+    Color::Yellow,
+    Color::Red,
+    Color::Blue,
+];
+
+/// Braille dot bits indexed by [row][column] within a character cell.
+const BRAILLE_DOTS: [[u32; 2]; 4] = [[0x01, 0x08], [0x02, 0x10], [0x04, 0x20], [0x40, 0x80]];
+
+/// A linear or logarithmic plot axis.
+#[derive(Debug, Clone, Copy)]
+pub struct Axis {
+    pub min: f64,
+    pub max: f64,
+    pub log: bool,
+}
+
+impl Axis {
+    /// Axis spanning the given values, ignoring those which cannot be shown on a log axis.
+    pub fn spanning<I: Iterator<Item = f64>>(values: I, log: bool) -> Option<Axis> {
+        let (min, max) = values
+            .filter(|v| v.is_finite() && (!log || *v > 0.))
+            .fold((f64::INFINITY, f64::NEG_INFINITY), |(min, max), v| {
+                (min.min(v), max.max(v))
+            });
+        if min > max {
+            return None;
+        }
+        let (min, max) = if min == max {
+            if log {
+                (min / 2., max * 2.)
+            } else {
+                (min - 1., max + 1.)
+            }
+        } else {
+            (min, max)
+        };
+        Some(Axis { min, max, log })
+    }
+
+    /// Fractional position of a value along the axis, or None if it can't be shown.
+    pub fn position(&self, value: f64) -> Option<f64> {
+        if !value.is_finite() || (self.log && value <= 0.) {
+            return None;
+        }
+        Some(if self.log {
+            (value.ln() - self.min.ln()) / (self.max.ln() - self.min.ln())
+        } else {
+            (value - self.min) / (self.max - self.min)
+        })
+    }
+
+    /// Value at a fractional position along the axis.
+    pub fn value_at(&self, fraction: f64) -> f64 {
+        if self.log {
+            (self.min.ln() + fraction * (self.max.ln() - self.min.ln())).exp()
+        } else {
+            self.min + fraction * (self.max - self.min)
+        }
+    }
+}
+
+/// A labelled curve of (z, value) points.
+pub struct Series {
+    pub label: String,
+    pub points: Vec<(f64, f64)>,
+}
+
+/// Samples a quantity at n redshifts spaced evenly along the redshift axis.
+pub fn sample(quantity: &Quantity, cosmo: &Cosmology, x: &Axis, n: usize) -> Vec<(f64, f64)> {
+    (0..n)
+        .map(|i| {
+            let z = x.value_at(i as f64 / (n - 1) as f64);
+            (z, (quantity.value)(cosmo, z))
+        })
+        .collect()
+}
+
+/// Short label for an axis tick.
+pub fn tick_label(value: f64) -> String {
+    let magnitude = value.abs();
+    if magnitude != 0. && !(1e-2..1e5).contains(&magnitude) {
+        format!("{:.1e}", value)
+    } else if magnitude >= 100. {
+        format!("{:.0}", value)
+    } else {
+        format!("{:.2}", value)
+    }
+}
+
+/// Draws the series as Braille characters on a grid of width by height character cells, with
+/// tick labels on both axes and a legend underneath.
+pub fn braille(series: &[Series], x: &Axis, y: &Axis, width: usize, height: usize) -> String {
+    let (columns, rows) = (2 * width, 4 * height);
+    let mut dots = vec![vec![0_u32; width]; height];
+    let mut colours: Vec<Vec<Option<usize>>> = vec![vec![None; width]; height];
+    let mut set = |column: i64, row: i64, series: usize| {
+        if column < 0 || row < 0 || column >= columns as i64 || row >= rows as i64 {
+            return;
+        }
+        let (column, row) = (column as usize, row as usize);
+        dots[row / 4][column / 2] |= BRAILLE_DOTS[row % 4][column % 2];
+        colours[row / 4][column / 2] = Some(series);
+    };
+
+    for (index, curve) in series.iter().enumerate() {
+        let pixels: Vec<Option<(f64, f64)>> = curve
+            .points
+            .iter()
+            .map(|(z, value)| {
+                let column = x.position(*z)? * (columns - 1) as f64;
+                let row = (1. - y.position(*value)?) * (rows - 1) as f64;
+                Some((column, row))
+            })
+            .collect();
+        for pair in pixels.windows(2) {
+            match pair {
+                [Some((c0, r0)), Some((c1, r1))] => {
+                    let steps = (c1 - c0).abs().max((r1 - r0).abs()).ceil().max(1.) as usize;
+                    for step in 0..=steps {
+                        let t = step as f64 / steps as f64;
+                        set(
+                            (c0 + t * (c1 - c0)).round() as i64,
+                            (r0 + t * (r1 - r0)).round() as i64,
+                            index,
+                        );
+                    }
+                }
+                [Some((c, r)), None] | [None, Some((c, r))] => {
+                    set(c.round() as i64, r.round() as i64, index)
+                }
+                _ => {}
+            }
+        }
+    }
+
+    let labels = [
+        tick_label(y.max),
+        tick_label(y.value_at(0.5)),
+        tick_label(y.min),
+    ];
+    let margin = labels.iter().map(|label| label.len()).max().unwrap_or(0);
+    let mut output = String::new();
+    for row in 0..height {
+        let label = if row == 0 {
+            &labels[0]
+        } else if row == height / 2 {
+            &labels[1]
+        } else if row == height - 1 {
+            &labels[2]
+        } else {
+            ""
+        };
+        output.push_str(&format!("{:>margin$} ┤", label));
+        for column in 0..width {
+            let character = char::from_u32(0x2800 + dots[row][column]).unwrap_or(' ');
+            match colours[row][column] {
+                Some(index) => output.push_str(
+                    &character
+                        .to_string()
+                        .color(COLOURS[index % COLOURS.len()])
+                        .to_string(),
+                ),
+                None => output.push(' '),
+            }
+        }
+        output.push('\n');
+    }
+    output.push_str(&format!("{:>margin$} └{}\n", "", "─".repeat(width)));
+    let (left, middle, right) = (
+        tick_label(x.min),
+        tick_label(x.value_at(0.5)),
+        tick_label(x.max),
+    );
+    let gap = (width / 2).saturating_sub(left.len() + middle.len() / 2);
+    let rest = width.saturating_sub(left.len() + gap + middle.len() + right.len());
+    output.push_str(&format!(
+        "{:>margin$}  {}{}{}{}{}\n",
+        "",
+        left,
+        " ".repeat(gap),
+        middle,
+        " ".repeat(rest),
+        right
+    ));
+    for (index, curve) in series.iter().enumerate() {
+        output.push_str(&format!(
+            "{:>margin$}  {} {}\n",
+            "",
+            "━━".color(COLOURS[index % COLOURS.len()]),
+            curve.label
+        ));
+    }
+    output
+}
@@ -0,0 +1,312 @@
+use std::f64::consts::{E, PI};
+
+use cosmoxide::Cosmology;
+
+use crate::background::hubble_distance;
+use crate::cmb::EarlyUniverse;
+use crate::growth::{growth_factor, growth_factor_early};
+use crate::numerics::simpson;
+
+const INTEGRATION_STEPS: usize = 4000;
+
+/// Pivot scale of the primordial power spectrum in 1/Mpc.
+const K_PIVOT: f64 = 0.05;
+
+/// Critical density today in (M_sun/h) / (Mpc/h)³.
+pub const RHO_CRIT: f64 = 2.775e11;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TransferFunction {
+    /// Eisenstein & Hu (1998) including baryon acoustic oscillations.
+    EisensteinHu,
+    /// Eisenstein & Hu (1998) zero-baryon shape without oscillations.
+    NoWiggle,
+    /// Bardeen, Bond, Kaiser & Szalay (1986) with the Sugiyama (1995) baryon correction.
+    Bbks,
+}
+
+impl TransferFunction {
+    pub fn from_name(name: &str) -> Option<TransferFunction> {
+        match name {
+            "eh" => Some(TransferFunction::EisensteinHu),
+            "nowiggle" => Some(TransferFunction::NoWiggle),
+            "bbks" => Some(TransferFunction::Bbks),
+            _ => None,
+        }
+    }
+}
+
+/// Linear matter power spectrum. Wavenumbers are in h/Mpc and powers in (Mpc/h)³.
+pub struct LinearPower<'a> {
+    cosmo: &'a Cosmology,
+    early: EarlyUniverse,
+    transfer: TransferFunction,
+    n_s: f64,
+    amplitude: f64,
+}
+
+impl<'a> LinearPower<'a> {
+    /// Power spectrum with amplitude set by σ8 today.
+    pub fn normalised_to_sigma8(
+        cosmo: &'a Cosmology,
+        early: EarlyUniverse,
+        transfer: TransferFunction,
+        n_s: f64,
+        sigma8: f64,
+    ) -> LinearPower<'a> {
+        let mut power = LinearPower {
+            cosmo,
+            early,
+            transfer,
+            n_s,
+            amplitude: 1.,
+        };
+        power.amplitude = (sigma8 / power.sigma_r(8., 0.)).powi(2);
+        power
+    }
+
+    /// Power spectrum with amplitude set by the primordial curvature amplitude A_s at k = 0.05/Mpc.
+    pub fn normalised_to_as(
+        cosmo: &'a Cosmology,
+        early: EarlyUniverse,
+        transfer: TransferFunction,
+        n_s: f64,
+        a_s: f64,
+    ) -> LinearPower<'a> {
+        // P(k) = 8π²/25 A_s (k/k_p)^(n_s-1) (c/H0)⁴ k T² D² / Ω_m² in Mpc units with D → a at
+        // early times, converted to h/Mpc units.
+        let h = cosmo.h0 / 100.;
+        let d0 = growth_factor_early(cosmo, 0.);
+        let amplitude = 8. * PI * PI / 25. * a_s * hubble_distance(cosmo).powi(4) * d0 * d0
+            / cosmo.omega_m.powi(2)
+            * K_PIVOT.powf(1. - n_s)
+            * h.powf(n_s + 3.);
+        LinearPower {
+            cosmo,
+            early,
+            transfer,
+            n_s,
+            amplitude,
+        }
+    }
+
+    /// Transfer function T(k) for k in h/Mpc.
+    pub fn transfer(&self, k: f64) -> f64 {
+        match self.transfer {
+            TransferFunction::EisensteinHu => self.eisenstein_hu(k),
+            TransferFunction::NoWiggle => self.no_wiggle(k),
+            TransferFunction::Bbks => self.bbks(k),
+        }
+    }
+
+    fn power_today(&self, k: f64) -> f64 {
+        self.amplitude * k.powf(self.n_s) * self.transfer(k).powi(2)
+    }
+
+    /// Linear power spectrum P(k, z) for k in h/Mpc in units of (Mpc/h)³.
+    pub fn power(&self, k: f64, z: f64) -> f64 {
+        self.power_today(k) * growth_factor(self.cosmo, z).powi(2)
+    }
+
+    /// RMS linear density fluctuation in spheres of radius r in Mpc/h.
+    pub fn sigma_r(&self, r: f64, z: f64) -> f64 {
+        let integrand = |ln_k: f64| {
+            let k = ln_k.exp();
+            let x = k * r;
+            let window = 3. * (x.sin() - x * x.cos()) / x.powi(3);
+            k.powi(3) * self.power_today(k) * window * window
+        };
+        let variance = simpson(integrand, 1e-5_f64.ln(), (200. / r).ln(), INTEGRATION_STEPS);
+        growth_factor(self.cosmo, z) * (variance / (2. * PI * PI)).sqrt()
+    }
+
+    pub fn cosmology(&self) -> &'a Cosmology {
+        self.cosmo
+    }
+
+    /// Lagrangian radius in Mpc/h enclosing mass m in M_sun/h.
+    pub fn mass_to_radius(&self, m: f64) -> f64 {
+        (3. * m / (4. * PI * self.cosmo.omega_m * RHO_CRIT)).cbrt()
+    }
+
+    /// RMS linear density fluctuation on mass scale m in M_sun/h.
+    pub fn sigma_m(&self, m: f64, z: f64) -> f64 {
+        self.sigma_r(self.mass_to_radius(m), z)
+    }
+
+    fn bbks(&self, k: f64) -> f64 {
+        let h = self.cosmo.h0 / 100.;
+        let omega_m = self.cosmo.omega_m;
+        let omega_b = self.early.omega_b;
+        let shape = omega_m * h * (-omega_b - (2. * h).sqrt() * omega_b / omega_m).exp();
+        let q = k / shape;
+        (1. + 2.34 * q).ln() / (2.34 * q)
+            * (1. + 3.89 * q + (16.1 * q).powi(2) + (5.46 * q).powi(3) + (6.71 * q).powi(4))
+                .powf(-0.25)
+    }
+
+    fn no_wiggle(&self, k: f64) -> f64 {
+        let h = self.cosmo.h0 / 100.;
+        let theta = self.early.t_cmb / 2.7;
+        let omh2 = self.cosmo.omega_m * h * h;
+        let ombh2 = self.early.omega_b * h * h;
+        let f_b = self.early.omega_b / self.cosmo.omega_m;
+        let k_mpc = k * h;
+
+        let s = 44.5 * (9.83 / omh2).ln() / (1. + 10. * ombh2.powf(0.75)).sqrt();
+        let alpha_gamma =
+            1. - 0.328 * (431. * omh2).ln() * f_b + 0.38 * (22.3 * omh2).ln() * f_b * f_b;
+        let gamma_eff = self.cosmo.omega_m
+            * h
+            * (alpha_gamma + (1. - alpha_gamma) / (1. + (0.43 * k_mpc * s).powi(4)));
+        let q = k * theta * theta / gamma_eff;
+        let l0 = (2. * E + 1.8 * q).ln();
+        let c0 = 14.2 + 731. / (1. + 62.5 * q);
+        l0 / (l0 + c0 * q * q)
+    }
+
+    fn eisenstein_hu(&self, k: f64) -> f64 {
+        let h = self.cosmo.h0 / 100.;
+        let theta = self.early.t_cmb / 2.7;
+        let omh2 = self.cosmo.omega_m * h * h;
+        let ombh2 = self.early.omega_b * h * h;
+        let f_b = self.early.omega_b / self.cosmo.omega_m;
+        let f_c = 1. - f_b;
+        let k_mpc = k * h;
+
+        let z_eq = 2.50e4 * omh2 * theta.powi(-4);
+        let k_eq = 7.46e-2 * omh2 * theta.powi(-2);
+        let z_d = self.early.z_drag(self.cosmo);
+        let baryon_ratio = |z: f64| 31.5 * ombh2 * theta.powi(-4) * (z / 1e3).powi(-1);
+        let r_d = baryon_ratio(z_d);
+        let r_eq = baryon_ratio(z_eq);
+        let s = 2. / (3. * k_eq)
+            * (6. / r_eq).sqrt()
+            * (((1. + r_d).sqrt() + (r_d + r_eq).sqrt()) / (1. + r_eq.sqrt())).ln();
+        let k_silk = 1.6 * ombh2.powf(0.52) * omh2.powf(0.73) * (1. + (10.4 * omh2).powf(-0.95));
+        let q = k_mpc / (13.41 * k_eq);
+
+        let t0 = |alpha: f64, beta: f64| {
+            let l = (E + 1.8 * beta * q).ln();
+            let c = 14.2 / alpha + 386. / (1. + 69.9 * q.powf(1.08));
+            l / (l + c * q * q)
+        };
+
+        // Cold dark matter.
+        let a1 = (46.9 * omh2).powf(0.670) * (1. + (32.1 * omh2).powf(-0.532));
+        let a2 = (12.0 * omh2).powf(0.424) * (1. + (45.0 * omh2).powf(-0.582));
+        let alpha_c = a1.powf(-f_b) * a2.powf(-f_b.powi(3));
+        let b1 = 0.944 / (1. + (458. * omh2).powf(-0.708));
+        let b2 = (0.395 * omh2).powf(-0.0266);
+        let beta_c = 1. / (1. + b1 * (f_c.powf(b2) - 1.));
+        let f = 1. / (1. + (k_mpc * s / 5.4).powi(4));
+        let t_c = f * t0(1., beta_c) + (1. - f) * t0(alpha_c, beta_c);
+
+        // Baryons.
+        let y = (1. + z_eq) / (1. + z_d);
+        let g = y
+            * (-6. * (1. + y).sqrt()
+                + (2. + 3. * y) * (((1. + y).sqrt() + 1.) / ((1. + y).sqrt() - 1.)).ln());
+        let alpha_b = 2.07 * k_eq * s * (1. + r_d).powf(-0.75) * g;
+        let beta_node = 8.41 * omh2.powf(0.435);
+        let s_tilde = s / (1. + (beta_node / (k_mpc * s)).powi(3)).cbrt();
+        let beta_b = 0.5 + f_b + (3. - 2. * f_b) * ((17.2 * omh2).powi(2) + 1.).sqrt();
+        let x = k_mpc * s_tilde;
+        let j0 = if x.abs() < 1e-8 { 1. } else { x.sin() / x };
+        let t_b = (t0(1., 1.) / (1. + (k_mpc * s / 5.2).powi(2))
+            + alpha_b / (1. + (beta_b / (k_mpc * s)).powi(3))
+                * (-(k_mpc / k_silk).powf(1.4)).exp())
+            * j0;
+
+        f_b * t_b + f_c * t_c
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Planck 2018 TT,TE,EE+lowE+lensing+BAO.
+    const PLANCK18: Cosmology = Cosmology {
+        omega_m: 0.30966,
+        omega_k: 0.,
+        omega_l: 0.69034,
+        h0: 67.66,
+    };
+
+    const EARLY: EarlyUniverse = EarlyUniverse {
+        omega_b: 0.04897,
+        t_cmb: 2.7255,
+        n_eff: 3.046,
+    };
+
+    const N_S: f64 = 0.9665;
+
+    const TRANSFER_FUNCTIONS: [TransferFunction; 3] = [
+        TransferFunction::EisensteinHu,
+        TransferFunction::NoWiggle,
+        TransferFunction::Bbks,
+    ];
+
+    #[test]
+    fn sigma8_from_primordial_amplitude() {
+        // Planck finds σ8 = 0.810 from A_s = 2.105e-9 with 0.06 eV neutrinos, which suppress it
+        // by about 2 per cent.
+        let power = LinearPower::normalised_to_as(
+            &PLANCK18,
+            EARLY,
+            TransferFunction::EisensteinHu,
+            N_S,
+            2.105e-9,
+        );
+        let sigma8 = power.sigma_r(8., 0.);
+        assert!((sigma8 / 0.8102 - 1.).abs() < 0.03, "{}", sigma8);
+    }
+
+    #[test]
+    fn sigma8_normalisation() {
+        for transfer in TRANSFER_FUNCTIONS {
+            let power = LinearPower::normalised_to_sigma8(&PLANCK18, EARLY, transfer, N_S, 0.8102);
+            assert!((power.sigma_r(8., 0.) - 0.8102).abs() < 1e-10);
+        }
+    }
+
+    #[test]
+    fn transfer_functions_tend_to_one_on_large_scales() {
+        for transfer in TRANSFER_FUNCTIONS {
+            let power = LinearPower::normalised_to_sigma8(&PLANCK18, EARLY, transfer, N_S, 0.8102);
+            let t = power.transfer(1e-5);
+            assert!((t - 1.).abs() < 1e-3, "{:?}: {}", transfer, t);
+        }
+    }
+
+    #[test]
+    fn power_grows_with_growth_factor() {
+        let power = LinearPower::normalised_to_sigma8(
+            &PLANCK18,
+            EARLY,
+            TransferFunction::EisensteinHu,
+            N_S,
+            0.8102,
+        );
+        let growth = growth_factor(&PLANCK18, 1.);
+        for k in [1e-3, 0.1, 1.] {
+            assert!((power.power(k, 1.) / power.power(k, 0.) - growth * growth).abs() < 1e-12);
+        }
+        assert!((power.sigma_r(8., 1.) / power.sigma_r(8., 0.) - growth).abs() < 1e-12);
+    }
+
+    #[test]
+    fn eight_megaparsec_sphere() {
+        // 4π/3 (8 Mpc/h)³ Ω_m ρ_crit.
+        let power = LinearPower::normalised_to_sigma8(
+            &PLANCK18,
+            EARLY,
+            TransferFunction::EisensteinHu,
+            N_S,
+            0.8102,
+        );
+        let mass = 4. / 3. * PI * 512. * PLANCK18.omega_m * RHO_CRIT;
+        assert!((power.mass_to_radius(mass) - 8.).abs() < 1e-12);
+    }
+}
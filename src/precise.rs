@@ -0,0 +1,219 @@
+use std::f64::consts::PI;
+
+use cosmoxide::Cosmology;
+
+use crate::background::SPEED_OF_LIGHT;
+use crate::numerics::{Estimate, brent, derivative, gauss_kronrod};
+
+/// Kilometres in a megaparsec.
+const MPC_KM: f64 = 3.085677581491367e19;
+
+/// Seconds in a gigayear of Julian years.
+const GYR_S: f64 = 3.15576e16;
+
+/// Radians in an arcsecond.
+const ARCSEC: f64 = PI / 180. / 3600.;
+
+/// Smallest u = a^(1/2) searched for an inverse, at a redshift of 1e12.
+const U_MIN: f64 = 1e-6;
+
+/// Background quantities computed by adaptive Gauss-Kronrod quadrature to a relative tolerance,
+/// each with an estimate of its numerical error, and their inverses by Brent's method.
+///
+/// The integrals are taken over u = a^(1/2) = (1 + z)^(-1/2), which makes the integrands smooth
+/// from the big bang to today.
+pub struct Precise {
+    omega_m: f64,
+    omega_k: f64,
+    omega_l: f64,
+    h0: f64,
+    rtol: f64,
+}
+
+impl Precise {
+    pub fn new(cosmo: &Cosmology, rtol: f64) -> Precise {
+        Precise {
+            omega_m: cosmo.omega_m,
+            omega_k: cosmo.omega_k,
+            omega_l: cosmo.omega_l,
+            h0: cosmo.h0,
+            rtol,
+        }
+    }
+
+    /// Hubble distance c/H0 in Mpc.
+    fn hubble_distance(&self) -> f64 {
+        SPEED_OF_LIGHT / self.h0
+    }
+
+    /// Hubble time 1/H0 in Gyr.
+    fn hubble_time(&self) -> f64 {
+        MPC_KM / self.h0 / GYR_S
+    }
+
+    /// 1 / (u³ E) as a function of u, the common factor of the integrands.
+    fn inverse_e(&self, u: f64) -> f64 {
+        let u2 = u * u;
+        1. / (self.omega_m + self.omega_k * u2 + self.omega_l * u2 * u2 * u2).sqrt()
+    }
+
+    /// Integral of f over [a, b], with the error from rounding the limits included.
+    fn integrate<F: Fn(f64) -> f64>(&self, f: F, a: f64, b: f64) -> Estimate {
+        let rounding = f64::EPSILON * ((a * f(a)).abs() + (b * f(b)).abs());
+        let integral = gauss_kronrod(f, a, b, self.rtol);
+        Estimate {
+            value: integral.value,
+            error: integral.error + rounding,
+        }
+    }
+
+    /// Line of sight comoving distance in Mpc at u, dD_C = 2 D_H du / (u³ E).
+    fn comoving_distance_at(&self, u: f64) -> Estimate {
+        self.integrate(|u| 2. * self.inverse_e(u), u, 1.)
+            .scaled(self.hubble_distance())
+    }
+
+    /// Transverse comoving distance in Mpc at u.
+    fn transverse_distance_at(&self, u: f64) -> Estimate {
+        let distance = self.comoving_distance_at(u);
+        let d_h = self.hubble_distance();
+        let curvature = self.omega_k.abs().sqrt();
+        let x = curvature * distance.value / d_h;
+        if self.omega_k > 0. {
+            distance.map(d_h / curvature * x.sinh(), x.cosh())
+        } else if self.omega_k < 0. {
+            distance.map(d_h / curvature * x.sin(), x.cos())
+        } else {
+            distance
+        }
+    }
+
+    fn luminosity_distance_at(&self, u: f64) -> Estimate {
+        self.transverse_distance_at(u).scaled(1. / (u * u))
+    }
+
+    /// Comoving volume in Mpc³ over the full sky within u.
+    fn comoving_volume_at(&self, u: f64) -> Estimate {
+        let distance = self.transverse_distance_at(u);
+        let d_h = self.hubble_distance();
+        let d_m = distance.value;
+        let slope = 4. * PI * d_m * d_m / (1. + self.omega_k * (d_m / d_h).powi(2)).sqrt();
+        let curvature = self.omega_k.abs().sqrt();
+        let volume = if self.omega_k == 0. {
+            4. / 3. * PI * d_m.powi(3)
+        } else {
+            let x = d_m / d_h;
+            let root = (1. + self.omega_k * x * x).sqrt();
+            let arc = if self.omega_k > 0. {
+                (curvature * x).asinh()
+            } else {
+                (curvature * x).asin()
+            };
+            2. * PI * d_h.powi(3) / self.omega_k * (x * root - arc / curvature)
+        };
+        distance.map(volume, slope)
+    }
+
+    /// Look back time in Gyr to u, dt = 2 t_H u² du / (u³ E).
+    fn look_back_time_at(&self, u: f64) -> Estimate {
+        self.integrate(|u| 2. * u * u * self.inverse_e(u), u, 1.)
+            .scaled(self.hubble_time())
+    }
+
+    /// Age of the universe in Gyr at u.
+    fn age_at(&self, u: f64) -> Estimate {
+        self.integrate(|u| 2. * u * u * self.inverse_e(u), 0., u)
+            .scaled(self.hubble_time())
+    }
+
+    pub fn comoving_distance(&self, z: f64) -> Estimate {
+        self.comoving_distance_at(scale(z))
+    }
+
+    pub fn transverse_distance(&self, z: f64) -> Estimate {
+        self.transverse_distance_at(scale(z))
+    }
+
+    pub fn luminosity_distance(&self, z: f64) -> Estimate {
+        self.luminosity_distance_at(scale(z))
+    }
+
+    pub fn angular_diameter_distance(&self, z: f64) -> Estimate {
+        self.transverse_distance(z).scaled(1. / (1. + z))
+    }
+
+    pub fn comoving_volume(&self, z: f64) -> Estimate {
+        self.comoving_volume_at(scale(z))
+    }
+
+    pub fn look_back_time(&self, z: f64) -> Estimate {
+        self.look_back_time_at(scale(z))
+    }
+
+    pub fn age(&self, z: f64) -> Estimate {
+        self.age_at(scale(z))
+    }
+
+    pub fn distance_modulus(&self, z: f64) -> Estimate {
+        let distance = self.luminosity_distance(z);
+        distance.map(
+            5. * distance.value.log10() + 25.,
+            5. / (distance.value * std::f64::consts::LN_10),
+        )
+    }
+
+    pub fn kpc_per_arcsecond_physical(&self, z: f64) -> Estimate {
+        self.angular_diameter_distance(z).scaled(1e3 * ARCSEC)
+    }
+
+    pub fn kpc_per_arcsecond_comoving(&self, z: f64) -> Estimate {
+        self.transverse_distance(z).scaled(1e3 * ARCSEC)
+    }
+
+    /// Hubble parameter in km/s/Mpc, which has no integral to approximate.
+    pub fn h_at_z(&self, z: f64) -> Estimate {
+        let u = scale(z);
+        Estimate::exact(self.h0 / (u * u * u * self.inverse_e(u)))
+    }
+
+    /// Redshift at which a function of u takes a value, with its numerical error from both the
+    /// root and the error of the function there. None if the value is never reached.
+    fn invert<F: Fn(f64) -> Estimate>(&self, f: F, value: f64) -> Option<Estimate> {
+        // Tolerance in u for a relative error in z of rtol / 2, since z = 1/u² - 1.
+        let tolerance = |u: f64| self.rtol * u * (1. - u * u).abs() / 4.;
+        let root = brent(|u| f(u).value - value, U_MIN, 1., tolerance)?;
+        let u = root.value;
+        let slope = derivative(|u| f(u).value, u);
+        let error = root.error + f(u).error / slope.abs();
+        Some(Estimate {
+            value: 1. / (u * u) - 1.,
+            error: 2. * error / (u * u * u),
+        })
+    }
+
+    pub fn inverse_codist(&self, distance: f64) -> Option<Estimate> {
+        self.invert(|u| self.comoving_distance_at(u), distance)
+    }
+
+    pub fn inverse_lumdist(&self, distance: f64) -> Option<Estimate> {
+        self.invert(|u| self.luminosity_distance_at(u), distance)
+    }
+
+    /// Redshift enclosing a comoving volume in Mpc³ over the full sky.
+    pub fn inverse_covol(&self, volume: f64) -> Option<Estimate> {
+        self.invert(|u| self.comoving_volume_at(u), volume)
+    }
+
+    pub fn inverse_lookback_time(&self, time: f64) -> Option<Estimate> {
+        self.invert(|u| self.look_back_time_at(u), time)
+    }
+
+    pub fn inverse_age(&self, time: f64) -> Option<Estimate> {
+        self.invert(|u| self.age_at(u), time)
+    }
+}
+
+/// u = a^(1/2) at a redshift.
+fn scale(z: f64) -> f64 {
+    1. / (1. + z).sqrt()
+}
@@ -0,0 +1,53 @@
+use cosmoxide::Cosmology;
+
+/// A named flat cosmology from the literature.
+pub struct Preset {
+    pub name: &'static str,
+    pub omega_m: f64,
+    pub h0: f64,
+}
+
+pub const PRESETS: &[Preset] = &[
+    Preset {
+        name: "default",
+        omega_m: 0.3,
+        h0: 70.,
+    },
+    Preset {
+        name: "planck13",
+        omega_m: 0.30712,
+        h0: 67.77,
+    },
+    Preset {
+        name: "planck15",
+        omega_m: 0.3075,
+        h0: 67.74,
+    },
+    Preset {
+        name: "planck18",
+        omega_m: 0.30966,
+        h0: 67.66,
+    },
+    Preset {
+        name: "wmap9",
+        omega_m: 0.2865,
+        h0: 69.32,
+    },
+];
+
+impl Preset {
+    pub fn cosmology(&self) -> Cosmology {
+        Cosmology {
+            omega_m: self.omega_m,
+            omega_k: 0.,
+            omega_l: 1. - self.omega_m,
+            h0: self.h0,
+        }
+    }
+}
+
+pub fn find(name: &str) -> Option<&'static Preset> {
+    PRESETS
+        .iter()
+        .find(|preset| preset.name.eq_ignore_ascii_case(name))
+}
@@ -0,0 +1,142 @@
+use cosmoxide::Cosmology;
+
+use crate::background;
+use crate::numerics::Estimate;
+use crate::precise::Precise;
+
+/// A redshift dependent quantity, keyed by the name of its subcommand.
+pub struct Quantity {
+    pub name: &'static str,
+    /// Short names used for catalogue columns and expressions.
+    pub aliases: &'static [&'static str],
+    pub label: &'static str,
+    pub unit: &'static str,
+    /// IVOA Unified Content Descriptor of the quantity, for VOTable fields.
+    pub ucd: &'static str,
+    pub value: fn(&Cosmology, f64) -> f64,
+    /// Redshift at a given value of the quantity, where an inverse exists.
+    pub inverse: Option<fn(&Cosmology, f64) -> f64>,
+    /// Value by adaptive quadrature, with an estimate of its numerical error.
+    pub precise: fn(&Precise, f64) -> Estimate,
+    /// Redshift and its numerical error at a given value of the quantity, by root finding.
+    pub precise_inverse: Option<fn(&Precise, f64) -> Option<Estimate>>,
+}
+
+pub const QUANTITIES: &[Quantity] = &[
+    Quantity {
+        name: "codist",
+        aliases: &["dc"],
+        label: "Comoving distance",
+        unit: "Mpc",
+        ucd: "pos.distance",
+        value: |cosmo, z| cosmo.comoving_distance(z),
+        inverse: Some(|cosmo, d| cosmo.inverse_codist(d)),
+        precise: |precise, z| precise.comoving_distance(z),
+        precise_inverse: Some(|precise, d| precise.inverse_codist(d)),
+    },
+    Quantity {
+        name: "lumdist",
+        aliases: &["dl"],
+        label: "Luminosity distance",
+        unit: "Mpc",
+        ucd: "pos.distance;pos.lumDistance",
+        value: |cosmo, z| cosmo.luminosity_distance(z),
+        inverse: Some(background::inverse_lumdist),
+        precise: |precise, z| precise.luminosity_distance(z),
+        precise_inverse: Some(|precise, d| precise.inverse_lumdist(d)),
+    },
+    Quantity {
+        name: "angdist",
+        aliases: &["da"],
+        label: "Angular diameter distance",
+        unit: "Mpc",
+        ucd: "pos.distance",
+        value: |cosmo, z| cosmo.angular_diameter_distance(z),
+        inverse: None,
+        precise: |precise, z| precise.angular_diameter_distance(z),
+        precise_inverse: None,
+    },
+    Quantity {
+        name: "covol",
+        aliases: &[],
+        label: "Comoving volume",
+        unit: "Gpc³",
+        ucd: "phys.volume",
+        value: |cosmo, z| cosmo.comoving_volume(z) / 1e9,
+        inverse: Some(|cosmo, v| cosmo.inverse_covol(v * 1e9)),
+        precise: |precise, z| precise.comoving_volume(z).scaled(1e-9),
+        precise_inverse: Some(|precise, v| precise.inverse_covol(v * 1e9)),
+    },
+    Quantity {
+        name: "lookback",
+        aliases: &["tl"],
+        label: "Look back time",
+        unit: "Gyr",
+        ucd: "time.interval",
+        value: |cosmo, z| cosmo.look_back_time(z),
+        inverse: Some(|cosmo, t| cosmo.inverse_lookback_time(t)),
+        precise: |precise, z| precise.look_back_time(z),
+        precise_inverse: Some(|precise, t| precise.inverse_lookback_time(t)),
+    },
+    Quantity {
+        name: "age",
+        aliases: &[],
+        label: "Age",
+        unit: "Gyr",
+        ucd: "time.age",
+        value: |cosmo, z| cosmo.age(z),
+        inverse: Some(|cosmo, t| cosmo.inverse_age(t)),
+        precise: |precise, z| precise.age(z),
+        precise_inverse: Some(|precise, t| precise.inverse_age(t)),
+    },
+    Quantity {
+        name: "distmod",
+        aliases: &["mu"],
+        label: "Distance modulus",
+        unit: "mag",
+        ucd: "phot.mag.distMod",
+        value: |cosmo, z| cosmo.distance_modulus(z),
+        inverse: None,
+        precise: |precise, z| precise.distance_modulus(z),
+        precise_inverse: None,
+    },
+    Quantity {
+        name: "angscale_phys",
+        aliases: &["angscale"],
+        label: "Physical angular scale",
+        unit: "kpc/arcsec",
+        ucd: "phys.size;arith.ratio",
+        value: |cosmo, z| cosmo.kpc_per_arcsecond_physical(z),
+        inverse: None,
+        precise: |precise, z| precise.kpc_per_arcsecond_physical(z),
+        precise_inverse: None,
+    },
+    Quantity {
+        name: "angscale_co",
+        aliases: &[],
+        label: "Comoving angular scale",
+        unit: "kpc/arcsec",
+        ucd: "phys.size;arith.ratio",
+        value: |cosmo, z| cosmo.kpc_per_arcsecond_comoving(z),
+        inverse: None,
+        precise: |precise, z| precise.kpc_per_arcsecond_comoving(z),
+        precise_inverse: None,
+    },
+    Quantity {
+        name: "hz",
+        aliases: &["H"],
+        label: "H(z)",
+        unit: "km/s/Mpc",
+        ucd: "phys.veloc.expansion",
+        value: |cosmo, z| cosmo.h_at_z(z),
+        inverse: None,
+        precise: |precise, z| precise.h_at_z(z),
+        precise_inverse: None,
+    },
+];
+
+pub fn find(name: &str) -> Option<&'static Quantity> {
+    QUANTITIES
+        .iter()
+        .find(|quantity| quantity.name == name || quantity.aliases.contains(&name))
+}
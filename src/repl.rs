@@ -0,0 +1,486 @@
+use std::collections::HashMap;
+use std::path::PathBuf;
+
+use clap::{ArgMatches, Command};
+use colored::Colorize;
+use cosmoxide::Cosmology;
+use rustyline::completion::{Completer, Pair};
+use rustyline::error::ReadlineError;
+use rustyline::highlight::Highlighter;
+use rustyline::hint::Hinter;
+use rustyline::history::DefaultHistory;
+use rustyline::validate::Validator;
+use rustyline::{Context, Editor, Helper};
+
+use crate::cli::cli;
+use crate::{
+    check_input, expression, parse_cosmology, parse_input, parse_rtol, precise_value, preset,
+    quantity, run,
+};
+
+const BUILTINS: &[&str] = &["set", "preset", "show", "vars", "help", "exit", "quit"];
+const PARAMETERS: &[&str] = &["H0", "Om", "OL"];
+
+const HELP: &str = "\
+Any cosmo subcommand can be run without the leading 'cosmo', e.g. 'codist 0.5' or 'age -i 1'.
+The session cosmology is used unless overridden with -m, -l, -k or -H.
+
+  set <H0|Om|OL> <value>  Change a parameter. Omega lambda follows Omega matter to stay flat.
+  preset <name>           Adopt a preset cosmology. 'preset' alone lists them.
+  show                    Print the session cosmology.
+  vars                    List stored results.
+  <name> = <command>      Store the result of a command as $<name>.
+  exit, quit              Leave the session.
+
+Results of single value subcommands and eval are stored as $1, $2, ... with the latest also as $_,
+and can be used in place of any number.";
+
+struct ReplHelper {
+    command: Command,
+}
+
+impl ReplHelper {
+    fn names(command: &Command) -> Vec<String> {
+        command
+            .get_subcommands()
+            .flat_map(|sub| {
+                std::iter::once(sub.get_name().to_string())
+                    .chain(sub.get_all_aliases().map(|alias| alias.to_string()))
+            })
+            .collect()
+    }
+}
+
+impl Completer for ReplHelper {
+    type Candidate = Pair;
+
+    fn complete(
+        &self,
+        line: &str,
+        pos: usize,
+        _ctx: &Context<'_>,
+    ) -> rustyline::Result<(usize, Vec<Pair>)> {
+        let before = &line[..pos];
+        let start = before.rfind(char::is_whitespace).map_or(0, |i| i + 1);
+        let word = &before[start..];
+        let previous: Vec<&str> = before[..start].split_whitespace().collect();
+        let candidates: Vec<String> = match previous.as_slice() {
+            [] => {
+                let mut names = ReplHelper::names(&self.command);
+                names.extend(BUILTINS.iter().map(|name| name.to_string()));
+                names
+            }
+            ["set"] => PARAMETERS.iter().map(|name| name.to_string()).collect(),
+            ["preset"] => preset::PRESETS
+                .iter()
+                .map(|preset| preset.name.to_string())
+                .collect(),
+            [name] => match self.command.find_subcommand(name) {
+                Some(sub) => ReplHelper::names(sub),
+                None => Vec::new(),
+            },
+            _ => Vec::new(),
+        };
+        let mut matches: Vec<Pair> = candidates
+            .into_iter()
+            .filter(|candidate| candidate.starts_with(word))
+            .map(|candidate| Pair {
+                display: candidate.clone(),
+                replacement: candidate,
+            })
+            .collect();
+        matches.sort_by(|a, b| a.display.cmp(&b.display));
+        matches.dedup_by(|a, b| a.display == b.display);
+        Ok((start, matches))
+    }
+}
+
+impl Hinter for ReplHelper {
+    type Hint = String;
+}
+
+impl Highlighter for ReplHelper {}
+
+impl Validator for ReplHelper {}
+
+impl Helper for ReplHelper {}
+
+struct Session {
+    cosmo: Cosmology,
+    results: Vec<f64>,
+    named: HashMap<String, f64>,
+}
+
+impl Session {
+    fn lookup(&self, name: &str) -> Option<f64> {
+        if name == "_" {
+            return self.results.last().copied();
+        }
+        if let Ok(index) = name.parse::<usize>() {
+            return index
+                .checked_sub(1)
+                .and_then(|i| self.results.get(i).copied());
+        }
+        self.named.get(name).copied()
+    }
+
+    fn show(&self) {
+        println!(
+            "H0 = {} km/s/Mpc, Om = {}, OL = {}, Ok = {}",
+            self.cosmo.h0.to_string().bold().green(),
+            self.cosmo.omega_m.to_string().bold().green(),
+            self.cosmo.omega_l.to_string().bold().green(),
+            self.cosmo.omega_k.to_string().bold().green()
+        );
+    }
+
+    fn set(&mut self, parameter: &str, value: &str) {
+        let Ok(value) = value.parse::<f64>() else {
+            println!("Value: {:?} is not a valid number.", value);
+            return;
+        };
+        match parameter {
+            "H0" | "h0" => self.cosmo.h0 = value,
+            "Om" | "om" | "omega_m" => {
+                self.cosmo.omega_m = value;
+                self.cosmo.omega_l = 1. - self.cosmo.omega_k - value;
+            }
+            "OL" | "ol" | "omega_l" => {
+                self.cosmo.omega_l = value;
+                self.cosmo.omega_m = 1. - self.cosmo.omega_k - value;
+            }
+            "Ok" | "ok" | "omega_k" => {
+                println!("Non-flat cosmology is not supported yet.");
+                return;
+            }
+            _ => {
+                println!(
+                    "Unknown parameter {:?}. Use one of H0, Om or OL.",
+                    parameter
+                );
+                return;
+            }
+        }
+        self.show();
+    }
+
+    fn vars(&self) {
+        for (i, value) in self.results.iter().enumerate() {
+            println!("${} = {}", i + 1, value);
+        }
+        let mut named: Vec<_> = self.named.iter().collect();
+        named.sort_by(|a, b| a.0.cmp(b.0));
+        for (name, value) in named {
+            println!("${} = {}", name, value);
+        }
+    }
+
+    /// Command line for a subcommand, filling in the session cosmology where it is accepted and
+    /// not given explicitly. The session options go before any '--', after which everything is
+    /// positional.
+    fn argv(&self, tokens: &[String]) -> Vec<String> {
+        let (tokens, positional) = tokens.split_at(
+            tokens
+                .iter()
+                .position(|token| token == "--")
+                .unwrap_or(tokens.len()),
+        );
+        let command = cli();
+        let mut deepest = &command;
+        for token in tokens {
+            match deepest.find_subcommand(token) {
+                Some(sub) => deepest = sub,
+                None => break,
+            }
+        }
+        let mut argv = vec!["cosmo".to_string()];
+        argv.extend(tokens.iter().cloned());
+        if deepest
+            .get_arguments()
+            .any(|arg| arg.get_id() == "omega_matter")
+        {
+            let given = |short: &str, long: &str| {
+                tokens
+                    .iter()
+                    .any(|token| token.starts_with(short) || token.starts_with(long))
+            };
+            let value = |short: &str, long: &str| {
+                tokens.iter().enumerate().find_map(|(i, token)| {
+                    let value = if token == short || token == long {
+                        tokens.get(i + 1).map(String::as_str)
+                    } else if let Some(value) = token.strip_prefix(long) {
+                        value.strip_prefix('=')
+                    } else {
+                        token
+                            .strip_prefix(short)
+                            .map(|value| value.trim_start_matches('='))
+                    };
+                    value.and_then(|value| value.parse::<f64>().ok())
+                })
+            };
+            // Like 'set', an overridden Omega matter or lambda carries the other along so the
+            // cosmology stays flat.
+            let omega_k = value("-k", "--omega-k").unwrap_or(self.cosmo.omega_k);
+            let (omega_m, omega_l) = match (value("-m", "--omega-m"), value("-l", "--omega-l")) {
+                (Some(omega_m), None) => (omega_m, 1. - omega_k - omega_m),
+                (None, Some(omega_l)) => (1. - omega_k - omega_l, omega_l),
+                (None, None) if omega_k != self.cosmo.omega_k => {
+                    (self.cosmo.omega_m, 1. - omega_k - self.cosmo.omega_m)
+                }
+                _ => (self.cosmo.omega_m, self.cosmo.omega_l),
+            };
+            let parameters = [
+                ("-m", "--omega-m", omega_m),
+                ("-l", "--omega-l", omega_l),
+                ("-k", "--omega-k", omega_k),
+                ("-H", "--hubble-const", self.cosmo.h0),
+            ];
+            for (short, long, value) in parameters {
+                if !given(short, long) {
+                    argv.push(short.to_string());
+                    argv.push(value.to_string());
+                }
+            }
+        }
+        argv.extend(positional.iter().cloned());
+        argv
+    }
+
+    /// The value of a single value subcommand, or None for any other subcommand.
+    fn result(matches: &ArgMatches) -> Option<Result<f64, String>> {
+        let (name, sub_matches) = matches.subcommand()?;
+        if name == "eval" {
+            let input: Vec<&str> = sub_matches
+                .get_many::<String>("expression")?
+                .map(String::as_str)
+                .collect();
+            return Some(parse_cosmology(sub_matches).and_then(|cosmo| {
+                expression::evaluate(&input.join(" "), &cosmo).map(|(value, _)| value)
+            }));
+        }
+        let quantity = quantity::find(name)?;
+        if let Ok(Some(_)) = sub_matches.try_get_one::<usize>("n_samples") {
+            return None;
+        }
+        if let Ok(Some(_)) = sub_matches.try_get_one::<String>("pdf") {
+            return None;
+        }
+        let input = sub_matches
+            .try_get_one::<String>("input")
+            .ok()
+            .flatten()
+            .or_else(|| sub_matches.get_one::<String>("z"))?;
+        let Ok(input) = input.parse::<f64>() else {
+            return Some(Err(format!("Value: {:?} is not a valid number.", input)));
+        };
+        let inverse = *sub_matches
+            .try_get_one::<bool>("inverse")
+            .ok()
+            .flatten()
+            .unwrap_or(&false);
+        let cosmo = match parse_cosmology(sub_matches) {
+            Ok(cosmo) => cosmo,
+            Err(message) => return Some(Err(message)),
+        };
+        if let Ok(Some(rtol)) = sub_matches.try_get_one::<String>("rtol") {
+            return Some(
+                parse_rtol(rtol)
+                    .and_then(|rtol| precise_value(quantity, sub_matches, &cosmo, input, rtol))
+                    .map(|estimate| estimate.value),
+            );
+        }
+        Some(match (inverse, quantity.inverse) {
+            (true, Some(inverse)) => Ok(inverse(&cosmo, input)),
+            _ => parse_input(sub_matches)
+                .redshift(&cosmo, input)
+                .map(|z| (quantity.value)(&cosmo, z)),
+        })
+    }
+
+    fn evaluate(&mut self, line: &str) {
+        let mut tokens: Vec<String> = line.split_whitespace().map(String::from).collect();
+        let target = if tokens.len() > 2 && tokens[1] == "=" {
+            let name = tokens[0].trim_start_matches('$').to_string();
+            tokens.drain(0..2);
+            Some(name)
+        } else {
+            None
+        };
+        for token in tokens.iter_mut() {
+            if let Some(name) = token.strip_prefix('$') {
+                match self.lookup(name) {
+                    Some(value) => *token = value.to_string(),
+                    None => {
+                        println!("Unknown variable: {}", token);
+                        return;
+                    }
+                }
+            }
+        }
+        match tokens
+            .iter()
+            .map(String::as_str)
+            .collect::<Vec<_>>()
+            .as_slice()
+        {
+            [] => {}
+            ["help"] => println!("{}", HELP),
+            ["show"] => self.show(),
+            ["vars"] => self.vars(),
+            ["set", parameter, value] => self.set(parameter, value),
+            ["set", ..] => println!("Usage: set <H0|Om|OL> <value>"),
+            ["preset"] => {
+                for preset in preset::PRESETS {
+                    println!(
+                        "{}: H0 = {}, Om = {}",
+                        preset.name, preset.h0, preset.omega_m
+                    );
+                }
+            }
+            ["preset", name] => match preset::find(name) {
+                Some(preset) => {
+                    self.cosmo = preset.cosmology();
+                    self.show();
+                }
+                None => println!("Unknown preset {:?}. Type 'preset' for a list.", name),
+            },
+            ["repl", ..] => println!("Already in a cosmo session."),
+            _ => {
+                let matches = match cli().try_get_matches_from(self.argv(&tokens)) {
+                    Ok(matches) => matches,
+                    Err(error) => {
+                        let _ = error.print();
+                        return;
+                    }
+                };
+                if let Err(message) = check_input(&matches) {
+                    println!("{}", message);
+                    return;
+                }
+                let result = match Session::result(&matches) {
+                    Some(Ok(value)) => Some(value),
+                    Some(Err(message)) => {
+                        println!("{}", message);
+                        return;
+                    }
+                    None => None,
+                };
+                if let Err(message) = run(&matches) {
+                    println!("{}", message);
+                    return;
+                }
+                match (result, target) {
+                    (Some(value), target) => {
+                        self.results.push(value);
+                        let mut stored = format!("${}", self.results.len());
+                        if let Some(name) = target {
+                            stored = format!("{} ${}", stored, name);
+                            self.named.insert(name, value);
+                        }
+                        println!("{}", stored.dimmed());
+                    }
+                    (None, Some(_)) => println!("This command has no single value to store."),
+                    (None, None) => {}
+                }
+            }
+        }
+    }
+}
+
+fn history_path() -> Option<PathBuf> {
+    std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".cosmo_history"))
+}
+
+pub fn start(cosmo: Cosmology) {
+    let mut editor = match Editor::<ReplHelper, DefaultHistory>::new() {
+        Ok(editor) => editor,
+        Err(error) => {
+            println!("Could not start the interactive session: {}", error);
+            std::process::exit(1)
+        }
+    };
+    editor.set_helper(Some(ReplHelper { command: cli() }));
+    if let Some(path) = history_path() {
+        let _ = editor.load_history(&path);
+    }
+    let mut session = Session {
+        cosmo,
+        results: Vec::new(),
+        named: HashMap::new(),
+    };
+    println!("Type 'help' for help and 'exit' to leave.");
+    session.show();
+    loop {
+        match editor.readline("cosmo> ") {
+            Ok(line) => {
+                let line = line.trim();
+                if line.is_empty() {
+                    continue;
+                }
+                let _ = editor.add_history_entry(line);
+                if line == "exit" || line == "quit" {
+                    break;
+                }
+                session.evaluate(line);
+            }
+            Err(ReadlineError::Interrupted) => continue,
+            Err(ReadlineError::Eof) => break,
+            Err(error) => {
+                println!("{}", error);
+                break;
+            }
+        }
+    }
+    if let Some(path) = history_path() {
+        let _ = editor.save_history(&path);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn session() -> Session {
+        Session {
+            cosmo: Cosmology {
+                omega_m: 0.25,
+                omega_k: 0.,
+                omega_l: 0.75,
+                h0: 68.,
+            },
+            results: Vec::new(),
+            named: HashMap::new(),
+        }
+    }
+
+    fn tokens(line: &str) -> Vec<String> {
+        line.split_whitespace().map(String::from).collect()
+    }
+
+    #[test]
+    fn session_cosmology_is_filled_in() {
+        let argv = session().argv(&tokens("codist 0.5 -m 0.4"));
+        assert_eq!(argv, tokens("cosmo codist 0.5 -m 0.4 -l 0.6 -k 0 -H 68"));
+        let matches = cli().try_get_matches_from(argv).unwrap();
+        let cosmo = parse_cosmology(matches.subcommand().unwrap().1).unwrap();
+        assert_eq!((cosmo.omega_m, cosmo.omega_l, cosmo.h0), (0.4, 0.6, 68.));
+    }
+
+    #[test]
+    fn session_options_go_before_positional_values() {
+        let argv = session().argv(&tokens("coords cartesian -- -1 10 10"));
+        assert_eq!(
+            argv,
+            tokens("cosmo coords cartesian -m 0.25 -l 0.75 -k 0 -H 68 -- -1 10 10")
+        );
+        assert!(cli().try_get_matches_from(argv).is_ok());
+    }
+
+    #[test]
+    fn commands_without_a_cosmology_are_unchanged() {
+        assert_eq!(
+            session().argv(&tokens("velocity convert 0.1")),
+            tokens("cosmo velocity convert 0.1")
+        );
+    }
+}
@@ -0,0 +1,221 @@
+use cosmoxide::Cosmology;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use rand_distr::StandardNormal;
+
+/// Most draws made per requested cosmology before giving up on the non-positive ones.
+const MAX_DRAWS_PER_SAMPLE: usize = 100;
+
+/// Gaussian distribution of (Ω_m, H0) with Ω_Λ fixed by flatness.
+pub struct ParameterDistribution {
+    pub mean: [f64; 2],
+    pub covariance: [[f64; 2]; 2],
+    pub omega_k: f64,
+}
+
+impl ParameterDistribution {
+    /// Lower triangular L with L Lᵀ equal to the covariance.
+    fn cholesky(&self) -> Result<[[f64; 2]; 2], String> {
+        let [[a, b], [_, d]] = self.covariance;
+        if a < 0. || d < 0. {
+            return Err("Covariance matrix has negative variances.".to_string());
+        }
+        let l11 = a.sqrt();
+        let l21 = if l11 > 0. { b / l11 } else { 0. };
+        let l22_squared = d - l21 * l21;
+        if l22_squared < 0. {
+            return Err("Covariance matrix is not positive semi-definite.".to_string());
+        }
+        Ok([[l11, 0.], [l21, l22_squared.sqrt()]])
+    }
+
+    /// Draw n cosmologies, redrawing any with non-positive Ω_m or H0. Fails if the means aren't
+    /// positive, or if too few draws are accepted to reach n.
+    pub fn draw(&self, n: usize, seed: Option<u64>) -> Result<Vec<Cosmology>, String> {
+        if self.mean[0] <= 0. || self.mean[1] <= 0. {
+            return Err("Omega matter and the Hubble constant must be positive.".to_string());
+        }
+        let l = self.cholesky()?;
+        let mut rng = match seed {
+            Some(seed) => StdRng::seed_from_u64(seed),
+            None => StdRng::from_os_rng(),
+        };
+        let max_draws = n.saturating_mul(MAX_DRAWS_PER_SAMPLE);
+        let mut cosmologies = Vec::with_capacity(n);
+        let mut draws = 0;
+        while cosmologies.len() < n {
+            if draws == max_draws {
+                return Err(format!(
+                    "Only {} of {} draws had positive omega matter and Hubble constant. \
+                     Reduce the uncertainties.",
+                    cosmologies.len(),
+                    draws
+                ));
+            }
+            draws += 1;
+            let n1: f64 = rng.sample(StandardNormal);
+            let n2: f64 = rng.sample(StandardNormal);
+            let omega_m = self.mean[0] + l[0][0] * n1;
+            let h0 = self.mean[1] + l[1][0] * n1 + l[1][1] * n2;
+            if omega_m <= 0. || h0 <= 0. {
+                continue;
+            }
+            cosmologies.push(Cosmology {
+                omega_m,
+                omega_k: self.omega_k,
+                omega_l: 1. - self.omega_k - omega_m,
+                h0,
+            });
+        }
+        Ok(cosmologies)
+    }
+}
+
+/// Read a 2x2 covariance matrix of (Ω_m, H0) from a whitespace or comma separated text file.
+pub fn read_covariance(path: &str) -> Result<[[f64; 2]; 2], String> {
+    let contents = std::fs::read_to_string(path)
+        .map_err(|error| format!("Could not read covariance file {:?}: {}", path, error))?;
+    let values: Vec<f64> = contents
+        .split(|c: char| c.is_whitespace() || c == ',')
+        .filter(|token| !token.is_empty())
+        .map(|token| {
+            token
+                .parse::<f64>()
+                .map_err(|_| format!("Value: {:?} is not a valid number.", token))
+        })
+        .collect::<Result<_, _>>()?;
+    if values.len() != 4 {
+        return Err(format!(
+            "Covariance file {:?} must contain a 2x2 matrix for (omega_m, H0), found {} values.",
+            path,
+            values.len()
+        ));
+    }
+    Ok([[values[0], values[1]], [values[2], values[3]]])
+}
+
+/// Mean, median and central 68% and 95% intervals of a set of samples.
+pub struct Summary {
+    pub mean: f64,
+    pub median: f64,
+    pub interval_68: (f64, f64),
+    pub interval_95: (f64, f64),
+}
+
+fn percentile(sorted: &[f64], fraction: f64) -> f64 {
+    let position = fraction * (sorted.len() - 1) as f64;
+    let lower = position.floor() as usize;
+    let upper = position.ceil() as usize;
+    sorted[lower] + (sorted[upper] - sorted[lower]) * (position - lower as f64)
+}
+
+pub fn summarise(mut values: Vec<f64>) -> Summary {
+    values.sort_by(|a, b| a.total_cmp(b));
+    Summary {
+        mean: values.iter().sum::<f64>() / values.len() as f64,
+        median: percentile(&values, 0.5),
+        interval_68: (percentile(&values, 0.15865), percentile(&values, 0.84135)),
+        interval_95: (percentile(&values, 0.025), percentile(&values, 0.975)),
+    }
+}
+
+/// Summary of weighted samples. Percentiles interpolate the cumulative weight, taking each sample
+/// to sit at the middle of its weight.
+pub fn summarise_weighted(values: Vec<f64>, weights: Vec<f64>) -> Summary {
+    let mut samples: Vec<(f64, f64)> = values.into_iter().zip(weights).collect();
+    samples.sort_by(|a, b| a.0.total_cmp(&b.0));
+    let total: f64 = samples.iter().map(|sample| sample.1).sum();
+    let mut cumulative = Vec::with_capacity(samples.len());
+    let mut running = 0.;
+    for (_, weight) in &samples {
+        cumulative.push((running + weight / 2.) / total);
+        running += weight;
+    }
+    let percentile = |fraction: f64| {
+        let upper = cumulative.partition_point(|c| *c < fraction);
+        if upper == 0 {
+            return samples[0].0;
+        }
+        if upper == samples.len() {
+            return samples[samples.len() - 1].0;
+        }
+        let (c0, c1) = (cumulative[upper - 1], cumulative[upper]);
+        let (v0, v1) = (samples[upper - 1].0, samples[upper].0);
+        v0 + (v1 - v0) * (fraction - c0) / (c1 - c0)
+    };
+    Summary {
+        mean: samples
+            .iter()
+            .map(|(value, weight)| value * weight)
+            .sum::<f64>()
+            / total,
+        median: percentile(0.5),
+        interval_68: (percentile(0.15865), percentile(0.84135)),
+        interval_95: (percentile(0.025), percentile(0.975)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn distribution() -> ParameterDistribution {
+        ParameterDistribution {
+            mean: [0.3, 70.],
+            covariance: [[1e-4, 5e-3], [5e-3, 1.]],
+            omega_k: 0.,
+        }
+    }
+
+    fn parameters(cosmologies: &[Cosmology]) -> Vec<(f64, f64, f64)> {
+        cosmologies
+            .iter()
+            .map(|cosmo| (cosmo.omega_m, cosmo.omega_l, cosmo.h0))
+            .collect()
+    }
+
+    #[test]
+    fn seeded_draws_are_reproducible() {
+        let first = distribution().draw(100, Some(42)).unwrap();
+        let second = distribution().draw(100, Some(42)).unwrap();
+        let other = distribution().draw(100, Some(43)).unwrap();
+        assert_eq!(parameters(&first), parameters(&second));
+        assert_ne!(parameters(&first), parameters(&other));
+        assert!(
+            first
+                .iter()
+                .all(|cosmo| cosmo.omega_m + cosmo.omega_l + cosmo.omega_k == 1.)
+        );
+    }
+
+    #[test]
+    fn non_positive_means_are_rejected() {
+        for mean in [[0.3, 0.], [-0.3, 70.]] {
+            let distribution = ParameterDistribution {
+                mean,
+                ..distribution()
+            };
+            assert!(distribution.draw(10, Some(1)).is_err());
+        }
+    }
+
+    #[test]
+    fn rarely_accepted_draws_fail() {
+        // Perfectly anticorrelated about a mean near zero, Ω_m and H0 are hardly ever both
+        // positive.
+        let distribution = ParameterDistribution {
+            mean: [1e-9, 1e-9],
+            covariance: [[1., -1.], [-1., 1.]],
+            omega_k: 0.,
+        };
+        let message = distribution.draw(10, Some(1)).err().unwrap();
+        assert!(message.contains("of 1000 draws"), "{}", message);
+    }
+
+    #[test]
+    fn summary_of_uniform_samples() {
+        let summary = summarise((0..=100).map(f64::from).collect());
+        assert_eq!(summary.median, 50.);
+        assert_eq!(summary.interval_95, (2.5, 97.5));
+    }
+}
@@ -0,0 +1,163 @@
+use std::f64::consts::PI;
+
+use cosmoxide::Cosmology;
+use rayon::prelude::*;
+
+/// Depth of box subdivision when deciding whether a replicated box reaches into a lightcone.
+const SUBDIVISIONS: u32 = 3;
+
+/// Little h of a cosmology, for converting simulation lengths in Mpc/h.
+pub fn little_h(cosmo: &Cosmology) -> f64 {
+    cosmo.h0 / 100.
+}
+
+/// Redshifts at the near and far faces of a box of comoving side L in Mpc/h centred on redshift
+/// z, along the line of sight.
+pub fn box_redshift_range(cosmo: &Cosmology, z: f64, side: f64) -> (f64, f64) {
+    let distance = cosmo.comoving_distance(z);
+    let half = side / little_h(cosmo) / 2.;
+    (
+        cosmo.inverse_codist((distance - half).max(0.)),
+        cosmo.inverse_codist(distance + half),
+    )
+}
+
+/// How many copies of a simulation box it takes to fill a lightcone.
+#[derive(Debug, Clone, Copy)]
+pub struct Lightcone {
+    /// Comoving volume of the lightcone in (Mpc/h)³.
+    pub volume: f64,
+    /// Comoving depth of the lightcone in Mpc/h.
+    pub depth: f64,
+    /// Boxes placed end to end along the line of sight.
+    pub along: usize,
+    /// Boxes the lightcone reaches into.
+    pub replications: usize,
+}
+
+/// Whether any of a box, given by its low corner and side, is within a distance of the origin
+/// and an angle of the x axis. Boxes are split into octants until the answer is clear or the
+/// subdivisions run out, at which point the bounding sphere decides.
+fn reaches(corner: [f64; 3], side: f64, distance: f64, angle: f64, depth: u32) -> bool {
+    let centre = corner.map(|c| c + side / 2.);
+    let radius = 3_f64.sqrt() * side / 2.;
+    let length = (centre[0] * centre[0] + centre[1] * centre[1] + centre[2] * centre[2]).sqrt();
+    if length - radius > distance {
+        return false;
+    }
+    if length > radius {
+        let off_axis = (centre[0] / length).clamp(-1., 1.).acos();
+        if off_axis - (radius / length).asin() > angle {
+            return false;
+        }
+    }
+    if depth == 0 {
+        return true;
+    }
+    let half = side / 2.;
+    (0..8).any(|octant| {
+        let offset = [octant & 1, (octant >> 1) & 1, (octant >> 2) & 1];
+        let corner = [
+            corner[0] + half * offset[0] as f64,
+            corner[1] + half * offset[1] as f64,
+            corner[2] + half * offset[2] as f64,
+        ];
+        reaches(corner, half, distance, angle, depth - 1)
+    })
+}
+
+/// Replications of a box of side L in Mpc/h filling a lightcone out to z_max over a circular
+/// field of an area in deg², with the observer at a corner of a box and looking along its x axis.
+pub fn lightcone(cosmo: &Cosmology, side: f64, z_max: f64, area: f64) -> Lightcone {
+    let h = little_h(cosmo);
+    let full_sky = 4. * PI * (180. / PI).powi(2);
+    let fraction = (area / full_sky).min(1.);
+    let depth = cosmo.comoving_distance(z_max) * h;
+    // Half opening angle of a cone covering the area.
+    let angle = (1. - 2. * fraction).clamp(-1., 1.).acos();
+
+    let reach = (depth / side).ceil() as i64;
+    // A cone narrower than a hemisphere only reaches boxes in front of the observer, and no
+    // further to the side than its opening angle allows.
+    let narrow = angle < PI / 2.;
+    let first = if narrow { 0 } else { -reach };
+    let width = |i: i64| {
+        if narrow {
+            let extent = (i + 1) as f64 * side * angle.tan();
+            ((extent / side).ceil() as i64).min(reach)
+        } else {
+            reach
+        }
+    };
+    let replications = (first..reach)
+        .into_par_iter()
+        .map(|i| {
+            let width = width(i);
+            let mut count = 0;
+            for j in -width..width {
+                for k in -width..width {
+                    let corner = [i as f64 * side, j as f64 * side, k as f64 * side];
+                    if reaches(corner, side, depth, angle, SUBDIVISIONS) {
+                        count += 1;
+                    }
+                }
+            }
+            count
+        })
+        .sum();
+    Lightcone {
+        volume: cosmo.comoving_volume(z_max) * fraction * h.powi(3),
+        depth,
+        along: reach as usize,
+        replications,
+    }
+}
+
+/// The shell of a lightcone taken from one snapshot, reaching halfway to the neighbouring
+/// snapshots in comoving distance.
+#[derive(Debug, Clone, Copy)]
+pub struct Shell {
+    pub z: f64,
+    /// Comoving distance of the snapshot redshift in Mpc/h.
+    pub distance: f64,
+    /// Comoving distances of the inner and outer edges of the shell in Mpc/h.
+    pub inner: f64,
+    pub outer: f64,
+}
+
+/// Lightcone shells of snapshots at the given redshifts, sorted by redshift. The nearest shell
+/// starts at the observer and the furthest ends as far beyond its snapshot as it starts before.
+pub fn shells(cosmo: &Cosmology, redshifts: &[f64]) -> Vec<Shell> {
+    let h = little_h(cosmo);
+    let mut redshifts = redshifts.to_vec();
+    redshifts.sort_by(f64::total_cmp);
+    let distances: Vec<f64> = redshifts
+        .iter()
+        .map(|z| cosmo.comoving_distance(*z) * h)
+        .collect();
+    let n = distances.len();
+    let edges: Vec<f64> = (0..=n)
+        .map(|i| match i {
+            0 => 0.,
+            _ if i == n => {
+                let last = distances[n - 1];
+                let previous = if n > 1 { distances[n - 2] } else { 0. };
+                last + (last - previous) / 2.
+            }
+            _ => (distances[i - 1] + distances[i]) / 2.,
+        })
+        .collect();
+    (0..n)
+        .map(|i| Shell {
+            z: redshifts[i],
+            distance: distances[i],
+            inner: edges[i],
+            outer: edges[i + 1],
+        })
+        .collect()
+}
+
+/// Redshift at a comoving distance in Mpc/h.
+pub fn redshift_at_distance(cosmo: &Cosmology, distance: f64) -> f64 {
+    cosmo.inverse_codist(distance / little_h(cosmo))
+}
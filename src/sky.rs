@@ -0,0 +1,113 @@
+/// Rotation from J2000 equatorial to galactic cartesian coordinates.
+const EQUATORIAL_TO_GALACTIC: [[f64; 3]; 3] = [
+    [-0.0548755604, -0.8734370902, -0.4838350155],
+    [0.4941094279, -0.4448296300, 0.7469822445],
+    [-0.8676661490, -0.1980763734, 0.4559837762],
+];
+
+/// Rotation from galactic to supergalactic cartesian coordinates (de Vaucouleurs et al. 1991).
+const GALACTIC_TO_SUPERGALACTIC: [[f64; 3]; 3] = [
+    [-0.7357425748, 0.6772612964, 0.0000000000],
+    [-0.0745537783, -0.0809914713, 0.9939225904],
+    [0.6731453021, 0.7312711658, 0.1100812622],
+];
+
+/// Celestial coordinate frames.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Frame {
+    /// J2000 right ascension and declination.
+    Equatorial,
+    Galactic,
+    Supergalactic,
+}
+
+impl Frame {
+    pub fn from_name(name: &str) -> Option<Frame> {
+        match name {
+            "equatorial" => Some(Frame::Equatorial),
+            "galactic" => Some(Frame::Galactic),
+            "supergalactic" => Some(Frame::Supergalactic),
+            _ => None,
+        }
+    }
+
+    /// Cartesian vector in this frame from a J2000 equatorial one.
+    pub fn from_equatorial(&self, vector: [f64; 3]) -> [f64; 3] {
+        match self {
+            Frame::Equatorial => vector,
+            Frame::Galactic => rotate(&EQUATORIAL_TO_GALACTIC, vector),
+            Frame::Supergalactic => rotate(
+                &GALACTIC_TO_SUPERGALACTIC,
+                rotate(&EQUATORIAL_TO_GALACTIC, vector),
+            ),
+        }
+    }
+
+    /// J2000 equatorial cartesian vector from one in this frame.
+    pub fn to_equatorial(&self, vector: [f64; 3]) -> [f64; 3] {
+        match self {
+            Frame::Equatorial => vector,
+            Frame::Galactic => rotate(&transpose(&EQUATORIAL_TO_GALACTIC), vector),
+            Frame::Supergalactic => rotate(
+                &transpose(&EQUATORIAL_TO_GALACTIC),
+                rotate(&transpose(&GALACTIC_TO_SUPERGALACTIC), vector),
+            ),
+        }
+    }
+}
+
+/// Cartesian unit vector for a longitude and latitude in degrees.
+pub fn unit_vector(lon: f64, lat: f64) -> [f64; 3] {
+    let (lon, lat) = (lon.to_radians(), lat.to_radians());
+    [lat.cos() * lon.cos(), lat.cos() * lon.sin(), lat.sin()]
+}
+
+/// Longitude and latitude in degrees of a cartesian vector.
+pub fn lon_lat(vector: [f64; 3]) -> (f64, f64) {
+    let [x, y, z] = vector;
+    let lon = y.atan2(x).to_degrees().rem_euclid(360.);
+    let lat = z.atan2((x * x + y * y).sqrt()).to_degrees();
+    (lon, lat)
+}
+
+fn rotate(matrix: &[[f64; 3]; 3], vector: [f64; 3]) -> [f64; 3] {
+    let mut out = [0.; 3];
+    for (row, value) in matrix.iter().zip(out.iter_mut()) {
+        *value = row[0] * vector[0] + row[1] * vector[1] + row[2] * vector[2];
+    }
+    out
+}
+
+/// Inverse of a rotation matrix.
+fn transpose(matrix: &[[f64; 3]; 3]) -> [[f64; 3]; 3] {
+    let mut out = [[0.; 3]; 3];
+    for (i, row) in matrix.iter().enumerate() {
+        for (j, value) in row.iter().enumerate() {
+            out[j][i] = *value;
+        }
+    }
+    out
+}
+
+/// Galactic coordinates (l, b) in degrees for equatorial (RA, Dec) in degrees.
+pub fn equatorial_to_galactic(ra: f64, dec: f64) -> (f64, f64) {
+    lon_lat(rotate(&EQUATORIAL_TO_GALACTIC, unit_vector(ra, dec)))
+}
+
+pub fn dot(a: [f64; 3], b: [f64; 3]) -> f64 {
+    a[0] * b[0] + a[1] * b[1] + a[2] * b[2]
+}
+
+pub fn cross(a: [f64; 3], b: [f64; 3]) -> [f64; 3] {
+    [
+        a[1] * b[2] - a[2] * b[1],
+        a[2] * b[0] - a[0] * b[2],
+        a[0] * b[1] - a[1] * b[0],
+    ]
+}
+
+/// Angle in degrees between two directions, accurate for small and large angles alike.
+pub fn angular_separation(a: [f64; 3], b: [f64; 3]) -> f64 {
+    let normal = cross(a, b);
+    dot(normal, normal).sqrt().atan2(dot(a, b)).to_degrees()
+}
@@ -0,0 +1,311 @@
+use std::io;
+
+use cosmoxide::Cosmology;
+use ratatui::DefaultTerminal;
+use ratatui::Frame;
+use ratatui::crossterm::event::{self, Event, KeyCode, KeyEventKind, KeyModifiers};
+use ratatui::layout::{Constraint, Layout, Rect};
+use ratatui::style::{Style, Stylize};
+use ratatui::symbols::Marker;
+use ratatui::text::{Line, Span};
+use ratatui::widgets::{Axis, Block, Chart, Dataset, GraphType, Paragraph};
+
+use crate::preset::PRESETS;
+
+/// Number of redshifts sampled for each plot.
+const PLOT_POINTS: usize = 200;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Field {
+    Redshift,
+    HubbleConstant,
+    OmegaMatter,
+}
+
+const FIELDS: [Field; 3] = [Field::Redshift, Field::HubbleConstant, Field::OmegaMatter];
+
+impl Field {
+    fn label(&self) -> &'static str {
+        match self {
+            Field::Redshift => "Redshift (z)",
+            Field::HubbleConstant => "H0 (km/s/Mpc)",
+            Field::OmegaMatter => "Omega matter",
+        }
+    }
+
+    fn step(&self) -> f64 {
+        match self {
+            Field::Redshift => 0.05,
+            Field::HubbleConstant => 0.5,
+            Field::OmegaMatter => 0.01,
+        }
+    }
+}
+
+struct App {
+    z: f64,
+    cosmo: Cosmology,
+    selected: usize,
+    preset: Option<usize>,
+}
+
+impl App {
+    fn value(&self, field: Field) -> f64 {
+        match field {
+            Field::Redshift => self.z,
+            Field::HubbleConstant => self.cosmo.h0,
+            Field::OmegaMatter => self.cosmo.omega_m,
+        }
+    }
+
+    /// Changes the selected field by a number of steps, keeping the universe flat.
+    fn adjust(&mut self, steps: f64) {
+        let field = FIELDS[self.selected];
+        let value = self.value(field) + steps * field.step();
+        match field {
+            Field::Redshift => self.z = value.max(0.),
+            Field::HubbleConstant => self.cosmo.h0 = value.max(1.),
+            Field::OmegaMatter => {
+                self.cosmo.omega_m = value.clamp(0.01, 1. - self.cosmo.omega_k);
+                self.cosmo.omega_l = 1. - self.cosmo.omega_k - self.cosmo.omega_m;
+            }
+        }
+        self.preset = None;
+    }
+
+    fn next_preset(&mut self) {
+        let index = self.preset.map_or(0, |i| (i + 1) % PRESETS.len());
+        self.cosmo = PRESETS[index].cosmology();
+        self.preset = Some(index);
+    }
+
+    fn run(&mut self, terminal: &mut DefaultTerminal) -> io::Result<()> {
+        loop {
+            terminal.draw(|frame| self.draw(frame))?;
+            let Event::Key(key) = event::read()? else {
+                continue;
+            };
+            if key.kind != KeyEventKind::Press {
+                continue;
+            }
+            let scale = if key.modifiers.contains(KeyModifiers::SHIFT) {
+                10.
+            } else {
+                1.
+            };
+            match key.code {
+                KeyCode::Char('q') | KeyCode::Esc => return Ok(()),
+                KeyCode::Up | KeyCode::Char('k') => {
+                    self.selected = (self.selected + FIELDS.len() - 1) % FIELDS.len()
+                }
+                KeyCode::Down | KeyCode::Char('j') | KeyCode::Tab => {
+                    self.selected = (self.selected + 1) % FIELDS.len()
+                }
+                KeyCode::Right | KeyCode::Char('l') | KeyCode::Char('+') => self.adjust(scale),
+                KeyCode::Left | KeyCode::Char('h') | KeyCode::Char('-') => self.adjust(-scale),
+                KeyCode::Char('p') => self.next_preset(),
+                _ => {}
+            }
+        }
+    }
+
+    fn draw(&self, frame: &mut Frame) {
+        let [left, right] =
+            Layout::horizontal([Constraint::Percentage(45), Constraint::Percentage(55)])
+                .areas(frame.area());
+        let [parameters, summary] =
+            Layout::vertical([Constraint::Length(7), Constraint::Min(0)]).areas(left);
+        let [distances, ages] =
+            Layout::vertical([Constraint::Percentage(50), Constraint::Percentage(50)]).areas(right);
+
+        self.draw_parameters(frame, parameters);
+        self.draw_summary(frame, summary);
+
+        let z_max = (2. * self.z).max(3.);
+        let cosmo = &self.cosmo;
+        draw_plot(
+            frame,
+            distances,
+            "Distances",
+            "Mpc",
+            z_max,
+            self.z,
+            &[
+                ("D_C", &|z| cosmo.comoving_distance(z)),
+                ("D_A", &|z| cosmo.angular_diameter_distance(z)),
+            ],
+        );
+        draw_plot(
+            frame,
+            ages,
+            "Age and look back time",
+            "Gyr",
+            z_max,
+            self.z,
+            &[
+                ("age", &|z| cosmo.age(z)),
+                ("lookback", &|z| cosmo.look_back_time(z)),
+            ],
+        );
+    }
+
+    fn draw_parameters(&self, frame: &mut Frame, area: Rect) {
+        let mut lines: Vec<Line> = FIELDS
+            .iter()
+            .enumerate()
+            .map(|(i, field)| {
+                let value = format!("{:.4}", self.value(*field));
+                if i == self.selected {
+                    Line::from(vec![
+                        Span::raw(format!("> {}: ", field.label())).bold(),
+                        Span::raw(value).bold().black().on_green(),
+                    ])
+                } else {
+                    Line::from(vec![
+                        Span::raw(format!("  {}: ", field.label())),
+                        Span::raw(value).green(),
+                    ])
+                }
+            })
+            .collect();
+        lines.push(Line::from(format!(
+            "  Omega lambda: {:.4}",
+            self.cosmo.omega_l
+        )));
+        if let Some(index) = self.preset {
+            lines.push(Line::from(format!("  Preset: {}", PRESETS[index].name)));
+        }
+        let help = " ↑↓ select  ←→ change (shift ×10)  p preset  q quit ";
+        frame.render_widget(
+            Paragraph::new(lines).block(Block::bordered().title(" cosmo ").title_bottom(help)),
+            area,
+        );
+    }
+
+    fn draw_summary(&self, frame: &mut Frame, area: Rect) {
+        let cosmo = &self.cosmo;
+        let z = self.z;
+        let rows: Vec<(&str, f64, &str)> = vec![
+            ("Expansion factor (a)", 1. / (1. + z), ""),
+            ("Comoving distance", cosmo.comoving_distance(z), "Mpc"),
+            ("Luminosity distance", cosmo.luminosity_distance(z), "Mpc"),
+            (
+                "Angular diameter distance",
+                cosmo.angular_diameter_distance(z),
+                "Mpc",
+            ),
+            (
+                "Comoving transverse distance",
+                cosmo.comoving_transverse_distance(z),
+                "Mpc",
+            ),
+            ("Distance Modulus", cosmo.distance_modulus(z), "mag"),
+            (
+                "Physical angular scale",
+                cosmo.kpc_per_arcsecond_physical(z),
+                "kpc/arcsec",
+            ),
+            (
+                "Comoving angular scale",
+                cosmo.kpc_per_arcsecond_comoving(z),
+                "kpc/arcsec",
+            ),
+            ("Comoving Volume", cosmo.comoving_volume(z) / 1e9, "Gpc³"),
+            ("H(z)", cosmo.h_at_z(z), "km/s/Mpc"),
+            ("Expansion rate", cosmo.h_at_z(z) / (1. + z), "km/s/Mpc"),
+            ("Age", cosmo.age(z), "Gyr"),
+            ("Look back time", cosmo.look_back_time(z), "Gyr"),
+            ("Universe Age Now", cosmo.age(0.), "Gyr"),
+            ("Hubble Time", cosmo.hubble_time(), "Gyr"),
+        ];
+        let lines: Vec<Line> = rows
+            .into_iter()
+            .map(|(label, value, unit)| {
+                Line::from(vec![
+                    Span::raw(format!("{}: ", label)),
+                    Span::raw(format!("{:.4}", value)).bold().green(),
+                    Span::raw(format!(" {}", unit)),
+                ])
+            })
+            .collect();
+        frame.render_widget(
+            Paragraph::new(lines).block(Block::bordered().title(" Summary ")),
+            area,
+        );
+    }
+}
+
+/// Line plot of each curve between z = 0 and z_max, with the current redshift marked.
+fn draw_plot(
+    frame: &mut Frame,
+    area: Rect,
+    title: &str,
+    unit: &str,
+    z_max: f64,
+    z: f64,
+    curves: &[(&str, &dyn Fn(f64) -> f64)],
+) {
+    let points: Vec<Vec<(f64, f64)>> = curves
+        .iter()
+        .map(|(_, f)| {
+            (0..PLOT_POINTS)
+                .map(|i| {
+                    let z = z_max * i as f64 / (PLOT_POINTS - 1) as f64;
+                    (z, f(z))
+                })
+                .collect()
+        })
+        .collect();
+    let markers: Vec<Vec<(f64, f64)>> = curves.iter().map(|(_, f)| vec![(z, f(z))]).collect();
+    let y_max = points.iter().flatten().map(|(_, y)| *y).fold(0., f64::max);
+
+    let colours = [Style::new().cyan(), Style::new().magenta()];
+    let mut datasets = Vec::new();
+    for (i, (name, _)) in curves.iter().enumerate() {
+        datasets.push(
+            Dataset::default()
+                .name(*name)
+                .marker(Marker::Braille)
+                .graph_type(GraphType::Line)
+                .style(colours[i % colours.len()])
+                .data(&points[i]),
+        );
+        datasets.push(
+            Dataset::default()
+                .marker(Marker::Dot)
+                .graph_type(GraphType::Scatter)
+                .style(Style::new().yellow().bold())
+                .data(&markers[i]),
+        );
+    }
+
+    let chart = Chart::new(datasets)
+        .block(Block::bordered().title(format!(" {} ", title)))
+        .x_axis(Axis::default().title("z").bounds([0., z_max]).labels([
+            "0".to_string(),
+            format!("{:.1}", z_max / 2.),
+            format!("{:.1}", z_max),
+        ]))
+        .y_axis(Axis::default().title(unit).bounds([0., y_max]).labels([
+            "0".to_string(),
+            format!("{:.0}", y_max / 2.),
+            format!("{:.0}", y_max),
+        ]));
+    frame.render_widget(chart, area);
+}
+
+pub fn start(z: f64, cosmo: Cosmology) {
+    let mut app = App {
+        z,
+        cosmo,
+        selected: 0,
+        preset: None,
+    };
+    let mut terminal = ratatui::init();
+    let result = app.run(&mut terminal);
+    ratatui::restore();
+    if let Err(error) = result {
+        println!("{}", error);
+        std::process::exit(1)
+    }
+}
@@ -0,0 +1,131 @@
+use crate::background::SPEED_OF_LIGHT;
+use crate::sky::{dot, equatorial_to_galactic, unit_vector};
+
+/// Speed of the Sun with respect to the CMB in km/s (Planck 2018).
+pub const CMB_DIPOLE_SPEED: f64 = 369.82;
+
+/// Galactic longitude and latitude in degrees of the CMB dipole apex (Planck 2018).
+pub const CMB_DIPOLE_APEX: (f64, f64) = (264.021, 48.253);
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum VelocityConvention {
+    /// v = c ((1+z)² - 1) / ((1+z)² + 1)
+    Relativistic,
+    /// v = cz
+    Optical,
+    /// v = cz / (1+z)
+    Radio,
+}
+
+impl VelocityConvention {
+    pub fn from_name(name: &str) -> Option<VelocityConvention> {
+        match name {
+            "relativistic" => Some(VelocityConvention::Relativistic),
+            "optical" => Some(VelocityConvention::Optical),
+            "radio" => Some(VelocityConvention::Radio),
+            _ => None,
+        }
+    }
+
+    /// Recession velocity in km/s for redshift z.
+    pub fn velocity(&self, z: f64) -> f64 {
+        match self {
+            VelocityConvention::Relativistic => {
+                let zp1_squared = (1. + z).powi(2);
+                SPEED_OF_LIGHT * (zp1_squared - 1.) / (zp1_squared + 1.)
+            }
+            VelocityConvention::Optical => SPEED_OF_LIGHT * z,
+            VelocityConvention::Radio => SPEED_OF_LIGHT * z / (1. + z),
+        }
+    }
+
+    /// Redshift for a recession velocity in km/s.
+    pub fn redshift(&self, velocity: f64) -> f64 {
+        let beta = velocity / SPEED_OF_LIGHT;
+        match self {
+            VelocityConvention::Relativistic => ((1. + beta) / (1. - beta)).sqrt() - 1.,
+            VelocityConvention::Optical => beta,
+            VelocityConvention::Radio => beta / (1. - beta),
+        }
+    }
+}
+
+/// Observed redshift from cosmological and peculiar redshifts, (1+z_obs) = (1+z_cos)(1+z_pec).
+pub fn combine_redshifts(z_cos: f64, z_pec: f64) -> f64 {
+    (1. + z_cos) * (1. + z_pec) - 1.
+}
+
+/// Cosmological redshift from the observed and peculiar redshifts.
+pub fn cosmological_redshift(z_obs: f64, z_pec: f64) -> f64 {
+    (1. + z_obs) / (1. + z_pec) - 1.
+}
+
+/// Component of the Sun's velocity with respect to the CMB towards (RA, Dec) in degrees, in km/s.
+pub fn solar_cmb_velocity(ra: f64, dec: f64) -> f64 {
+    let (l, b) = equatorial_to_galactic(ra, dec);
+    let apex = unit_vector(CMB_DIPOLE_APEX.0, CMB_DIPOLE_APEX.1);
+    CMB_DIPOLE_SPEED * dot(unit_vector(l, b), apex)
+}
+
+/// Redshift in the CMB rest frame for a heliocentric redshift towards (RA, Dec) in degrees.
+pub fn heliocentric_to_cmb(z_helio: f64, ra: f64, dec: f64) -> f64 {
+    let z_sun = -solar_cmb_velocity(ra, dec) / SPEED_OF_LIGHT;
+    cosmological_redshift(z_helio, z_sun)
+}
+
+/// Heliocentric redshift for a CMB frame redshift towards (RA, Dec) in degrees.
+pub fn cmb_to_heliocentric(z_cmb: f64, ra: f64, dec: f64) -> f64 {
+    let z_sun = -solar_cmb_velocity(ra, dec) / SPEED_OF_LIGHT;
+    combine_redshifts(z_cmb, z_sun)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CONVENTIONS: [VelocityConvention; 3] = [
+        VelocityConvention::Relativistic,
+        VelocityConvention::Optical,
+        VelocityConvention::Radio,
+    ];
+
+    #[test]
+    fn velocities_at_unit_redshift() {
+        for (convention, beta) in CONVENTIONS.into_iter().zip([0.6, 1., 0.5]) {
+            assert!((convention.velocity(1.) - beta * SPEED_OF_LIGHT).abs() < 1e-9);
+        }
+    }
+
+    #[test]
+    fn redshift_inverts_velocity() {
+        for convention in CONVENTIONS {
+            for z in [1e-3, 0.1, 1., 5.] {
+                let recovered = convention.redshift(convention.velocity(z));
+                assert!(
+                    (recovered - z).abs() < 1e-12 * z.max(1.),
+                    "{:?}",
+                    convention
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn combined_redshifts() {
+        assert!((combine_redshifts(0.1, 0.01) - 0.111).abs() < 1e-15);
+        assert!((cosmological_redshift(0.111, 0.01) - 0.1).abs() < 1e-15);
+    }
+
+    #[test]
+    fn cmb_dipole() {
+        // The apex is at (RA, Dec) = (167.942, -6.944) in Planck 2018.
+        let towards = solar_cmb_velocity(167.942, -6.944);
+        assert!((towards - CMB_DIPOLE_SPEED).abs() < 1e-3, "{}", towards);
+        let away = solar_cmb_velocity(167.942 - 180., 6.944);
+        assert!((away + CMB_DIPOLE_SPEED).abs() < 1e-3, "{}", away);
+        // The Sun's motion blueshifts galaxies towards the apex, so their CMB frame redshift is higher.
+        let z_cmb = heliocentric_to_cmb(0.01, 167.942, -6.944);
+        assert!(z_cmb > 0.01);
+        assert!((cmb_to_heliocentric(z_cmb, 167.942, -6.944) - 0.01).abs() < 1e-15);
+    }
+}
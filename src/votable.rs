@@ -0,0 +1,339 @@
+use std::collections::HashMap;
+use std::io::Write;
+use std::path::Path;
+use std::sync::Arc;
+
+use arrow::array::{
+    ArrayRef, BooleanBuilder, Float64Builder, Int64Builder, RecordBatch, StringBuilder,
+};
+use arrow::datatypes::{DataType, Field, Schema, SchemaRef};
+use arrow::util::display::array_value_to_string;
+
+const NAMESPACE: &str = "http://www.ivoa.net/xml/VOTable/v1.3";
+
+/// A FIELD of a VOTable, with the column values as they appear in the TD cells.
+struct Column {
+    field: Field,
+    null: Option<String>,
+    cells: Vec<String>,
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+/// Arrow type holding a VOTable datatype. Arrays other than strings are kept as their text.
+fn data_type(datatype: &str, arraysize: Option<&str>) -> DataType {
+    match (datatype, arraysize) {
+        ("boolean", None) => DataType::Boolean,
+        ("unsignedByte" | "short" | "int" | "long", None) => DataType::Int64,
+        ("float" | "double", None) => DataType::Float64,
+        _ => DataType::Utf8,
+    }
+}
+
+/// VOTable datatype and arraysize of an Arrow type.
+fn datatype(data_type: &DataType) -> (&'static str, Option<&'static str>) {
+    match data_type {
+        DataType::Boolean => ("boolean", None),
+        DataType::Int8 | DataType::Int16 | DataType::UInt8 => ("short", None),
+        DataType::Int32 | DataType::UInt16 => ("int", None),
+        DataType::Int64 | DataType::UInt32 | DataType::UInt64 => ("long", None),
+        DataType::Float16 | DataType::Float32 => ("float", None),
+        DataType::Float64 => ("double", None),
+        _ => ("char", Some("*")),
+    }
+}
+
+fn array(column: &Column) -> Result<ArrayRef, String> {
+    let name = column.field.name();
+    let cells = column.cells.iter().map(|cell| {
+        let cell = cell.trim();
+        if cell.is_empty() || Some(cell) == column.null.as_deref() {
+            None
+        } else {
+            Some(cell)
+        }
+    });
+    Ok(match column.field.data_type() {
+        DataType::Boolean => {
+            let mut builder = BooleanBuilder::new();
+            for cell in cells {
+                builder.append_option(match cell.map(|cell| cell.to_ascii_uppercase()) {
+                    Some(cell) if matches!(cell.as_str(), "T" | "TRUE" | "1") => Some(true),
+                    Some(cell) if matches!(cell.as_str(), "F" | "FALSE" | "0") => Some(false),
+                    _ => None,
+                });
+            }
+            Arc::new(builder.finish())
+        }
+        DataType::Int64 => {
+            let mut builder = Int64Builder::new();
+            for cell in cells {
+                builder.append_option(match cell {
+                    Some(cell) => Some(
+                        cell.parse::<i64>()
+                            .map_err(|_| format!("Invalid integer {:?} in {}.", cell, name))?,
+                    ),
+                    None => None,
+                });
+            }
+            Arc::new(builder.finish())
+        }
+        DataType::Float64 => {
+            let mut builder = Float64Builder::new();
+            for cell in cells {
+                builder.append_option(match cell {
+                    Some(cell) => Some(
+                        cell.parse::<f64>()
+                            .map_err(|_| format!("Invalid number {:?} in {}.", cell, name))?,
+                    ),
+                    None => None,
+                });
+            }
+            Arc::new(builder.finish())
+        }
+        _ => {
+            let mut builder = StringBuilder::new();
+            for cell in cells {
+                builder.append_option(cell);
+            }
+            Arc::new(builder.finish())
+        }
+    })
+}
+
+/// Reads the first table of a VOTable with TABLEDATA serialisation. Units and UCDs are kept in
+/// the field metadata.
+pub fn read(path: &Path) -> Result<RecordBatch, String> {
+    let text = std::fs::read_to_string(path).map_err(|error| error.to_string())?;
+    parse(&text)
+}
+
+/// Reads the first table of a VOTable document. Empty cells and cells equal to the null value
+/// given in a field's VALUES are null.
+fn parse(text: &str) -> Result<RecordBatch, String> {
+    let document = roxmltree::Document::parse(text).map_err(|error| error.to_string())?;
+    let table = document
+        .descendants()
+        .find(|node| node.has_tag_name("TABLE"))
+        .ok_or("The VOTable has no TABLE.")?;
+
+    let mut columns = Vec::new();
+    for node in table.children().filter(|node| node.has_tag_name("FIELD")) {
+        let name = node
+            .attribute("name")
+            .or(node.attribute("ID"))
+            .unwrap_or("")
+            .to_string();
+        let name = if name.is_empty() {
+            format!("col{}", columns.len() + 1)
+        } else {
+            name
+        };
+        let datatype = node
+            .attribute("datatype")
+            .ok_or(format!("FIELD {} has no datatype.", name))?;
+        let mut metadata = HashMap::new();
+        for key in ["unit", "ucd"] {
+            if let Some(value) = node.attribute(key) {
+                metadata.insert(key.to_string(), value.to_string());
+            }
+        }
+        let null = node
+            .children()
+            .find(|child| child.has_tag_name("VALUES"))
+            .and_then(|values| values.attribute("null"))
+            .map(str::to_string);
+        columns.push(Column {
+            field: Field::new(name, data_type(datatype, node.attribute("arraysize")), true)
+                .with_metadata(metadata),
+            null,
+            cells: Vec::new(),
+        });
+    }
+    if columns.is_empty() {
+        return Err("The VOTable has no FIELD.".to_string());
+    }
+
+    if let Some(data) = table
+        .children()
+        .find(|node| node.has_tag_name("DATA"))
+        .and_then(|data| data.first_element_child())
+    {
+        if !data.has_tag_name("TABLEDATA") {
+            return Err(format!(
+                "Only TABLEDATA VOTables can be read, not {}.",
+                data.tag_name().name()
+            ));
+        }
+        for row in data.children().filter(|node| node.has_tag_name("TR")) {
+            let mut cells = row.children().filter(|node| node.has_tag_name("TD"));
+            for column in &mut columns {
+                let cell = cells.next().map(|cell| cell.text().unwrap_or(""));
+                column.cells.push(cell.unwrap_or("").to_string());
+            }
+        }
+    }
+
+    let arrays = columns.iter().map(array).collect::<Result<Vec<_>, _>>()?;
+    let schema = Schema::new(
+        columns
+            .into_iter()
+            .map(|column| column.field)
+            .collect::<Vec<_>>(),
+    );
+    RecordBatch::try_new(Arc::new(schema), arrays).map_err(|error| error.to_string())
+}
+
+/// Writes batches of rows as the TABLEDATA of a single table VOTable, with a FIELD for each
+/// column carrying the unit and UCD from its metadata.
+pub struct Writer<W: Write> {
+    output: W,
+}
+
+impl<W: Write> Writer<W> {
+    pub fn try_new(mut output: W, schema: &SchemaRef) -> Result<Writer<W>, String> {
+        let mut header = format!(
+            "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<VOTABLE version=\"1.3\" xmlns=\"{}\">\n<RESOURCE>\n<TABLE>\n",
+            NAMESPACE
+        );
+        for field in schema.fields() {
+            let (datatype, arraysize) = datatype(field.data_type());
+            header.push_str(&format!(
+                "<FIELD name=\"{}\" datatype=\"{}\"",
+                escape(field.name()),
+                datatype
+            ));
+            if let Some(arraysize) = arraysize {
+                header.push_str(&format!(" arraysize=\"{}\"", arraysize));
+            }
+            for key in ["unit", "ucd"] {
+                if let Some(value) = field.metadata().get(key) {
+                    header.push_str(&format!(" {}=\"{}\"", key, escape(value)));
+                }
+            }
+            header.push_str("/>\n");
+        }
+        header.push_str("<DATA>\n<TABLEDATA>\n");
+        output
+            .write_all(header.as_bytes())
+            .map_err(|error| error.to_string())?;
+        Ok(Writer { output })
+    }
+
+    pub fn write(&mut self, batch: &RecordBatch) -> Result<(), String> {
+        let mut rows = String::new();
+        for row in 0..batch.num_rows() {
+            rows.push_str("<TR>");
+            for column in batch.columns() {
+                let cell = if column.is_null(row) {
+                    String::new()
+                } else {
+                    array_value_to_string(column, row).map_err(|error| error.to_string())?
+                };
+                rows.push_str(&format!("<TD>{}</TD>", escape(&cell)));
+            }
+            rows.push_str("</TR>\n");
+        }
+        self.output
+            .write_all(rows.as_bytes())
+            .map_err(|error| error.to_string())
+    }
+
+    pub fn finish(&mut self) -> Result<(), String> {
+        self.output
+            .write_all(b"</TABLEDATA>\n</DATA>\n</TABLE>\n</RESOURCE>\n</VOTABLE>\n")
+            .map_err(|error| error.to_string())?;
+        self.output.flush().map_err(|error| error.to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use arrow::array::{Array, BooleanArray, Float64Array, Int64Array, StringArray};
+
+    fn field(name: &str, data_type: DataType, unit: &str, ucd: &str) -> Field {
+        Field::new(name, data_type, true).with_metadata(HashMap::from([
+            ("unit".to_string(), unit.to_string()),
+            ("ucd".to_string(), ucd.to_string()),
+        ]))
+    }
+
+    #[test]
+    fn round_trip_keeps_units_ucds_and_nulls() {
+        let schema = Arc::new(Schema::new(vec![
+            field("z", DataType::Float64, "", "src.redshift"),
+            field("codist", DataType::Float64, "Mpc", "pos.distance"),
+            field("id", DataType::Int64, "", "meta.id"),
+            field("name", DataType::Utf8, "", "meta.id;meta.main"),
+            field("flag", DataType::Boolean, "", "meta.code"),
+        ]));
+        let columns: Vec<ArrayRef> = vec![
+            Arc::new(Float64Array::from(vec![Some(0.5), Some(1.)])),
+            Arc::new(Float64Array::from(vec![Some(1888.6), None])),
+            Arc::new(Int64Array::from(vec![None, Some(7)])),
+            Arc::new(StringArray::from(vec![Some("A & B <1>"), None])),
+            Arc::new(BooleanArray::from(vec![Some(true), None])),
+        ];
+        let batch = RecordBatch::try_new(schema.clone(), columns).unwrap();
+        let mut writer = Writer::try_new(Vec::new(), &schema).unwrap();
+        writer.write(&batch).unwrap();
+        writer.finish().unwrap();
+
+        let read = parse(&String::from_utf8(writer.output).unwrap()).unwrap();
+        assert_eq!(read.schema(), schema);
+        assert_eq!(read.columns(), batch.columns());
+    }
+
+    #[test]
+    fn values_null_and_padding_are_read() {
+        let text = r#"<?xml version="1.0"?>
+<VOTABLE version="1.3" xmlns="http://www.ivoa.net/xml/VOTable/v1.3">
+<RESOURCE><TABLE>
+<FIELD name="id" datatype="int"><VALUES null="-999"/></FIELD>
+<FIELD name="name" datatype="char" arraysize="*"><VALUES null="N/A"/></FIELD>
+<FIELD name="z" datatype="double" ucd="src.redshift"/>
+<DATA><TABLEDATA>
+<TR><TD> -999 </TD><TD> N/A </TD><TD>0.1</TD></TR>
+<TR><TD>3</TD><TD> NGC 1 </TD><TD></TD></TR>
+</TABLEDATA></DATA>
+</TABLE></RESOURCE>
+</VOTABLE>"#;
+        let batch = parse(text).unwrap();
+        let ids = batch
+            .column(0)
+            .as_any()
+            .downcast_ref::<Int64Array>()
+            .unwrap();
+        assert!(ids.is_null(0));
+        assert_eq!(ids.value(1), 3);
+        let names = batch
+            .column(1)
+            .as_any()
+            .downcast_ref::<StringArray>()
+            .unwrap();
+        assert!(names.is_null(0));
+        assert_eq!(names.value(1), "NGC 1");
+        let z = batch
+            .column(2)
+            .as_any()
+            .downcast_ref::<Float64Array>()
+            .unwrap();
+        assert_eq!(z.value(0), 0.1);
+        assert!(z.is_null(1));
+        assert_eq!(
+            batch
+                .schema()
+                .field(2)
+                .metadata()
+                .get("ucd")
+                .map(String::as_str),
+            Some("src.redshift")
+        );
+    }
+}
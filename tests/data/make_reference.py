@@ -0,0 +1,87 @@
+"""Reference values of background quantities for tests/reference.rs.
+
+The values follow the definitions of astropy's FlatLambdaCDM with Tcmb0 = 0 (no radiation) and
+its units: c = 299792.458 km/s, a megaparsec of 3.0856775814913673e19 km and a gigayear of Julian
+years. They are integrated with mpmath to 30 significant figures, so that they are exact at double
+precision rather than limited by the tolerance of astropy's own quadrature. With astropy
+installed, the table is also compared with astropy and the largest relative difference printed.
+
+    python tests/data/make_reference.py > tests/data/reference.csv
+
+With --astropy the same table is written from astropy itself, for the astropy test of
+tests/reference.rs:
+
+    python tests/data/make_reference.py --astropy > tests/data/astropy.csv
+"""
+
+import sys
+
+from mpmath import mp, mpf, quad, sqrt, log10, pi, inf
+
+mp.dps = 30
+
+C = mpf("299792.458")
+MPC_KM = mpf("3.0856775814913673e19")
+GYR_S = mpf("3.15576e16")
+ARCSEC = pi / 180 / 3600
+
+COSMOLOGIES = [(mpf("70"), mpf("0.3")), (mpf("67.66"), mpf("0.30966")), (mpf("70"), mpf("1"))]
+REDSHIFTS = ["0", "0.001", "0.01", "0.1", "0.5", "1", "2", "3", "5", "10", "100", "1000"]
+COLUMNS = ["h0", "omega_m", "z", "codist", "lumdist", "angdist", "covol", "lookback", "age",
+           "distmod", "angscale_phys", "angscale_co", "hz"]
+
+
+def row(h0, om, z):
+    ol = 1 - om
+    e = lambda z: sqrt(om * (1 + z) ** 3 + ol)
+    points = [mpf(0)] + [mpf(p) for p in ["0.1", "1", "10", "100", "1000"] if mpf(p) < z] + [z]
+    d_h = C / h0
+    t_h = MPC_KM / h0 / GYR_S
+    dc = d_h * quad(lambda x: 1 / e(x), points)
+    lookback = t_h * quad(lambda x: 1 / ((1 + x) * e(x)), points)
+    age = t_h * quad(lambda x: 1 / ((1 + x) * e(x)), [z, z + 1, 10 * (z + 1), inf])
+    dl = (1 + z) * dc
+    da = dc / (1 + z)
+    distmod = 5 * log10(dl) + 25 if z > 0 else -inf
+    return [h0, om, z, dc, dl, da, 4 * pi / 3 * dc ** 3, lookback, age, distmod,
+            da * 1000 * ARCSEC, dc * 1000 * ARCSEC, h0 * e(z)]
+
+
+def astropy_row(h0, om, z):
+    from astropy.cosmology import FlatLambdaCDM
+    import astropy.units as u
+
+    cosmo = FlatLambdaCDM(H0=h0, Om0=om, Tcmb0=0)
+    return [h0, om, z, cosmo.comoving_distance(z).value, cosmo.luminosity_distance(z).value,
+            cosmo.angular_diameter_distance(z).value, cosmo.comoving_volume(z).value,
+            cosmo.lookback_time(z).value, cosmo.age(z).value,
+            cosmo.distmod(z).value if z > 0 else float("-inf"),
+            cosmo.kpc_proper_per_arcmin(z).to(u.kpc / u.arcsec).value,
+            cosmo.kpc_comoving_per_arcmin(z).to(u.kpc / u.arcsec).value, cosmo.H(z).value]
+
+
+def compare(rows):
+    try:
+        import astropy  # noqa: F401
+    except ImportError:
+        return
+    worst = 0
+    for values in rows:
+        astropy = astropy_row(*(float(v) for v in values[:3]))[3:]
+        for ours, theirs in zip(values[3:], astropy):
+            if ours != 0 and abs(ours) != inf:
+                worst = max(worst, abs(float(ours) - theirs) / abs(float(ours)))
+    print(f"largest relative difference from astropy: {worst:.2e}", file=sys.stderr)
+
+
+print(",".join(COLUMNS))
+if "--astropy" in sys.argv[1:]:
+    for h0, om in COSMOLOGIES:
+        for z in REDSHIFTS:
+            print(",".join(repr(float(v)) for v in astropy_row(float(h0), float(om), float(z))))
+else:
+    rows = [row(h0, om, mpf(z)) for h0, om in COSMOLOGIES for z in REDSHIFTS]
+    for values in rows:
+        print(",".join(mp.nstr(v, 17, min_fixed=-inf, max_fixed=inf) if abs(v) != inf else "-inf"
+                       for v in values))
+    compare(rows)
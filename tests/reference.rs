@@ -0,0 +1,188 @@
+//! Validation of the adaptive quadrature backend against high-precision reference tables of the
+//! flat ΛCDM quantities (in astropy's FlatLambdaCDM conventions), generated with mpmath by
+//! tests/data/make_reference.py.
+
+use cosmoxide::Cosmology;
+
+use cosmo::numerics::Estimate;
+use cosmo::precise::Precise;
+
+const REFERENCE: &str = include_str!("data/reference.csv");
+
+/// Agreement required with the reference values.
+const TOLERANCE: f64 = 1e-8;
+
+type Method = fn(&Precise, f64) -> Estimate;
+
+const QUANTITIES: [(&str, Method); 10] = [
+    ("codist", Precise::comoving_distance),
+    ("lumdist", Precise::luminosity_distance),
+    ("angdist", Precise::angular_diameter_distance),
+    ("covol", Precise::comoving_volume),
+    ("lookback", Precise::look_back_time),
+    ("age", Precise::age),
+    ("distmod", Precise::distance_modulus),
+    ("angscale_phys", Precise::kpc_per_arcsecond_physical),
+    ("angscale_co", Precise::kpc_per_arcsecond_comoving),
+    ("hz", Precise::h_at_z),
+];
+
+type Inverse = fn(&Precise, f64) -> Option<Estimate>;
+
+const INVERSES: [(&str, Inverse); 5] = [
+    ("codist", Precise::inverse_codist),
+    ("lumdist", Precise::inverse_lumdist),
+    ("covol", Precise::inverse_covol),
+    ("lookback", Precise::inverse_lookback_time),
+    ("age", Precise::inverse_age),
+];
+
+struct Row {
+    cosmo: Cosmology,
+    z: f64,
+    values: Vec<(String, f64)>,
+}
+
+impl Row {
+    fn get(&self, name: &str) -> f64 {
+        self.values
+            .iter()
+            .find(|(column, _)| column == name)
+            .unwrap()
+            .1
+    }
+}
+
+fn reference() -> Vec<Row> {
+    table(REFERENCE)
+}
+
+fn table(csv: &str) -> Vec<Row> {
+    let mut lines = csv.lines();
+    let header: Vec<&str> = lines.next().unwrap().split(',').collect();
+    lines
+        .map(|line| {
+            let values: Vec<(String, f64)> = header
+                .iter()
+                .zip(line.split(','))
+                .map(|(name, value)| (name.to_string(), value.parse().unwrap()))
+                .collect();
+            let get = |name: &str| values.iter().find(|(column, _)| column == name).unwrap().1;
+            let omega_m = get("omega_m");
+            Row {
+                cosmo: Cosmology {
+                    omega_m,
+                    omega_k: 0.,
+                    omega_l: 1. - omega_m,
+                    h0: get("h0"),
+                },
+                z: get("z"),
+                values,
+            }
+        })
+        .collect()
+}
+
+fn relative_error(value: f64, expected: f64) -> f64 {
+    if expected == 0. {
+        value.abs()
+    } else {
+        ((value - expected) / expected).abs()
+    }
+}
+
+fn check_quantities(rows: Vec<Row>, source: &str) {
+    for row in rows {
+        let precise = Precise::new(&row.cosmo, 1e-10);
+        for (name, method) in QUANTITIES {
+            let expected = row.get(name);
+            if expected.is_infinite() {
+                continue;
+            }
+            let value = method(&precise, row.z).value;
+            assert!(
+                relative_error(value, expected) <= TOLERANCE,
+                "{} at z = {} for omega_m = {}, H0 = {}: {} against {} from {}",
+                name,
+                row.z,
+                row.cosmo.omega_m,
+                row.cosmo.h0,
+                value,
+                expected,
+                source
+            );
+        }
+    }
+}
+
+#[test]
+fn quantities_match_reference() {
+    check_quantities(reference(), "mpmath");
+}
+
+/// The same table written by astropy itself, which isn't committed as it needs astropy to
+/// generate. Run with `cargo test -- --ignored` once it is.
+#[test]
+#[ignore = "needs tests/data/astropy.csv from make_reference.py --astropy"]
+fn quantities_match_astropy() {
+    let path = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/data/astropy.csv");
+    let csv = std::fs::read_to_string(path).expect("tests/data/astropy.csv is missing");
+    check_quantities(table(&csv), "astropy");
+}
+
+#[test]
+fn inverses_recover_reference_redshifts() {
+    for row in reference().iter().filter(|row| row.z > 0.) {
+        let precise = Precise::new(&row.cosmo, 1e-10);
+        for (name, inverse) in INVERSES {
+            let z = inverse(&precise, row.get(name)).unwrap().value;
+            assert!(
+                relative_error(z, row.z) <= TOLERANCE,
+                "inverse {} at z = {} for omega_m = {}, H0 = {}: {}",
+                name,
+                row.z,
+                row.cosmo.omega_m,
+                row.cosmo.h0,
+                z
+            );
+        }
+    }
+}
+
+#[test]
+fn errors_are_within_estimates_and_tolerance() {
+    for rtol in [1e-4, 1e-6, 1e-8] {
+        for row in reference() {
+            let precise = Precise::new(&row.cosmo, rtol);
+            for (name, method) in [QUANTITIES[0], QUANTITIES[4], QUANTITIES[5]] {
+                let expected = row.get(name);
+                let estimate = method(&precise, row.z);
+                let error = (estimate.value - expected).abs();
+                assert!(
+                    error <= estimate.error + 1e-15 * expected.abs(),
+                    "{} at z = {} with rtol {}: error {:e} above estimate {:e}",
+                    name,
+                    row.z,
+                    rtol,
+                    error,
+                    estimate.error
+                );
+                assert!(
+                    relative_error(estimate.value, expected) <= rtol,
+                    "{} at z = {} with rtol {}: relative error {:e}",
+                    name,
+                    row.z,
+                    rtol,
+                    relative_error(estimate.value, expected)
+                );
+            }
+        }
+    }
+}
+
+#[test]
+fn out_of_range_inverses_are_none() {
+    let precise = Precise::new(&reference()[0].cosmo, 1e-8);
+    assert!(precise.inverse_age(20.).is_none());
+    assert!(precise.inverse_codist(-1.).is_none());
+}